@@ -28,7 +28,7 @@ impl RequestHandler<(i32, i32)> for Adder {
 }
 
 fn main() {
-    let nodes = lunatic::distributed::nodes();
+    let nodes = lunatic::distributed::remote_nodes();
 
     println!("Nodes {nodes:?}");
 
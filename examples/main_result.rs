@@ -0,0 +1,12 @@
+use std::io;
+
+use lunatic::net::TcpListener;
+use lunatic::Mailbox;
+
+#[lunatic::main]
+fn main(_: Mailbox<()>) -> io::Result<()> {
+    // A returned error is printed and the process exits with a non-zero code.
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    println!("Listening on {}", listener.local_addr()?);
+    Ok(())
+}
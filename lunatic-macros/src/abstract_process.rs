@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::iter::repeat;
 
 use convert_case::{Case, Casing};
@@ -20,10 +21,18 @@ pub struct AbstractProcess {
     terminate: Option<syn::ImplItemMethod>,
     /// Handle link died method.
     handle_link_death: Option<syn::ImplItemMethod>,
+    /// Handle signal method.
+    handle_signal: Option<syn::ImplItemMethod>,
+    /// Handle idle timeout method.
+    handle_timeout: Option<syn::ImplItemMethod>,
+    /// Message metrics hook.
+    on_message: Option<syn::ImplItemMethod>,
     /// Message handler methods.
     message_handlers: Vec<syn::ImplItemMethod>,
     /// Request handler methods.
     request_handlers: Vec<syn::ImplItemMethod>,
+    /// Serializers of request handlers that don't use the process serializer.
+    request_serializers: HashMap<syn::Ident, syn::Type>,
     /// Deferred request handler methods.
     deferred_request_handlers: Vec<syn::ImplItemMethod>,
     /// Name of trait wrapping messages
@@ -54,10 +63,14 @@ impl AbstractProcess {
                 ))
             }
         };
+        let request_serializers = Self::request_serializers(&item_impl)?;
         let (
             init,
             terminate,
             handle_link_death,
+            handle_signal,
+            handle_timeout,
+            on_message,
             message_handlers,
             request_handlers,
             deferred_request_handlers,
@@ -93,12 +106,25 @@ impl AbstractProcess {
                 Some((item_attr, impl_item_method))
             })
             .fold(
-                Ok((None, None, None, Vec::new(), Vec::new(), Vec::new())),
+                Ok((
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                )),
                 |acc, (item_attr, impl_item_method)| {
                     let (
                         mut init,
                         mut terminate,
                         mut handle_link_death,
+                        mut handle_signal,
+                        mut handle_timeout,
+                        mut on_message,
                         mut message_handlers,
                         mut request_handlers,
                         mut deferred_request_handlers,
@@ -135,6 +161,36 @@ impl AbstractProcess {
 
                             handle_link_death = Some(impl_item_method);
                         }
+                        ItemAttr::HandleSignal => {
+                            if handle_signal.is_some() {
+                                return Err(syn::Error::new(
+                                    impl_item_method.sig.ident.span(),
+                                    "handle_signal method already defined",
+                                ));
+                            }
+
+                            handle_signal = Some(impl_item_method);
+                        }
+                        ItemAttr::HandleTimeout => {
+                            if handle_timeout.is_some() {
+                                return Err(syn::Error::new(
+                                    impl_item_method.sig.ident.span(),
+                                    "handle_timeout method already defined",
+                                ));
+                            }
+
+                            handle_timeout = Some(impl_item_method);
+                        }
+                        ItemAttr::OnMessage => {
+                            if on_message.is_some() {
+                                return Err(syn::Error::new(
+                                    impl_item_method.sig.ident.span(),
+                                    "on_message method already defined",
+                                ));
+                            }
+
+                            on_message = Some(impl_item_method);
+                        }
                         ItemAttr::HandleMessage => {
                             message_handlers.push(impl_item_method);
                         }
@@ -150,6 +206,9 @@ impl AbstractProcess {
                         init,
                         terminate,
                         handle_link_death,
+                        handle_signal,
+                        handle_timeout,
+                        on_message,
                         message_handlers,
                         request_handlers,
                         deferred_request_handlers,
@@ -189,14 +248,48 @@ impl AbstractProcess {
             init,
             terminate,
             handle_link_death,
+            handle_signal,
+            handle_timeout,
+            on_message,
             message_handlers,
             request_handlers,
+            request_serializers,
             deferred_request_handlers,
             message_trait_name,
             request_trait_name,
         })
     }
 
+    /// Collects the serializers set with
+    /// `#[handle_request(serializer = "...")]`.
+    fn request_serializers(
+        item_impl: &syn::ItemImpl,
+    ) -> syn::Result<HashMap<syn::Ident, syn::Type>> {
+        let mut serializers = HashMap::new();
+        for item in item_impl.items.iter() {
+            let impl_item_method = match item {
+                syn::ImplItem::Method(impl_item_method) => impl_item_method,
+                _ => continue,
+            };
+            for attr in impl_item_method.attrs.iter() {
+                if !attr.path.is_ident("handle_request") || attr.tokens.is_empty() {
+                    continue;
+                }
+                let serializer = attr.parse_args_with(|input: ParseStream| {
+                    let ident: syn::Ident = input.parse()?;
+                    if ident != "serializer" {
+                        return Err(syn::Error::new(ident.span(), "unknown argument"));
+                    }
+                    let _: syn::Token![=] = input.parse()?;
+                    let serializer: syn::LitStr = input.parse()?;
+                    serializer.parse::<syn::Type>()
+                })?;
+                serializers.insert(impl_item_method.sig.ident.clone(), serializer);
+            }
+        }
+        Ok(serializers)
+    }
+
     /// Expands macro.
     pub fn expand(&self) -> TokenStream {
         let handler_wrappers = self.expand_handler_wrappers();
@@ -322,6 +415,9 @@ impl AbstractProcess {
         let (init_impl, startup_error) = self.expand_init_impl();
         let terminate_impl = self.expand_terminate_impl();
         let handle_link_death_impl = self.expand_handle_link_death_impl();
+        let handle_signal_impl = self.expand_handle_signal_impl();
+        let handle_timeout_impl = self.expand_handle_timeout_impl();
+        let on_message_impl = self.expand_on_message_impl();
 
         quote! {
             impl #impl_generics lunatic::ap::AbstractProcess for #self_ty #where_clause {
@@ -334,6 +430,9 @@ impl AbstractProcess {
                 #init_impl
                 #terminate_impl
                 #handle_link_death_impl
+                #handle_signal_impl
+                #handle_timeout_impl
+                #on_message_impl
             }
         }
     }
@@ -348,7 +447,12 @@ impl AbstractProcess {
         let request_wrappers = self.request_handlers.iter().map(|impl_item_method| {
             let ident = Self::handler_wrapper_ident(&impl_item_method.sig.ident);
             let (_, generics, _) = &self.item_impl.generics.split_for_impl();
-            quote! { lunatic::ap::handlers::Request<#ident #generics>, }
+            match self.request_serializers.get(&impl_item_method.sig.ident) {
+                Some(serializer) => {
+                    quote! { lunatic::ap::handlers::RequestWith<#ident #generics, #serializer>, }
+                }
+                None => quote! { lunatic::ap::handlers::Request<#ident #generics>, },
+            }
         });
         let deferred_request_wrappers =
             self.deferred_request_handlers
@@ -406,6 +510,22 @@ impl AbstractProcess {
             .map(|terminate| {
                 let ident = &terminate.sig.ident;
 
+                // A `reason: Option<R>` argument receives the decoded shutdown reason.
+                if filter_typed_args(terminate.sig.inputs.iter())
+                    .next()
+                    .is_some()
+                {
+                    return quote! {
+                        fn terminate(state: Self::State) {
+                            state.#ident(None)
+                        }
+
+                        fn terminate_with(state: Self::State, reason: lunatic::ap::ShutdownReason) {
+                            state.#ident(reason.decode())
+                        }
+                    };
+                }
+
                 quote! {
                     fn terminate(state: Self::State) {
                         state.#ident()
@@ -432,6 +552,59 @@ impl AbstractProcess {
             .unwrap_or_default()
     }
 
+    /// Expands the `handle_signal` method in the abstract process
+    /// implementation.
+    fn expand_handle_signal_impl(&self) -> TokenStream {
+        self.handle_signal
+            .as_ref()
+            .map(|handle_signal| {
+                let ident = &handle_signal.sig.ident;
+
+                quote! {
+                    fn handle_signal(mut state: lunatic::ap::State<Self>, signal: lunatic::ap::ProcessSignal) {
+                        state.#ident(signal);
+                    }
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// Expands the `handle_timeout` method in the abstract process
+    /// implementation.
+    fn expand_handle_timeout_impl(&self) -> TokenStream {
+        self.handle_timeout
+            .as_ref()
+            .map(|handle_timeout| {
+                let ident = &handle_timeout.sig.ident;
+
+                quote! {
+                    fn handle_timeout(mut state: lunatic::ap::State<Self>) {
+                        state.#ident();
+                    }
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// Expands the `on_message` method in the abstract process implementation
+    /// and enables message metrics.
+    fn expand_on_message_impl(&self) -> TokenStream {
+        self.on_message
+            .as_ref()
+            .map(|on_message| {
+                let ident = &on_message.sig.ident;
+
+                quote! {
+                    const MESSAGE_METRICS: bool = true;
+
+                    fn on_message(metric: lunatic::ap::MessageMetric) {
+                        Self::#ident(metric);
+                    }
+                }
+            })
+            .unwrap_or_default()
+    }
+
     /// Expands the `MessageHandler` implementations for the message handler
     /// wrapper types.
     fn expand_message_handler_impls(&self) -> TokenStream {
@@ -669,6 +842,7 @@ impl AbstractProcess {
             item_impl,
             message_handlers,
             request_handlers,
+            request_serializers,
             deferred_request_handlers,
             message_trait_name,
             request_trait_name,
@@ -676,6 +850,11 @@ impl AbstractProcess {
         } = self;
         let self_ty = &item_impl.self_ty;
         let (impl_generics, ty_generics, where_clause) = item_impl.generics.split_for_impl();
+        // Requests to handlers with their own serializer use `request_with`.
+        let request_call = |ident: &syn::Ident| match request_serializers.get(ident) {
+            Some(serializer) => quote! { self.request_with::<#serializer, _>(req) },
+            None => quote! { self.request(req) },
+        };
         let arg_phantom = if !item_impl.generics.params.is_empty() {
             Some(quote! { std::marker::PhantomData, })
         } else {
@@ -750,12 +929,13 @@ impl AbstractProcess {
                 } = handler;
 
                 let return_ty_type = format_ident!("ReturnTy_{}", ident);
+                let request = request_call(ident);
                 quote! {
                     type #return_ty_type = #return_ty;
                     #( #attrs )*
                     fn #ident #generics (&self #(, #args )*) -> Self::#return_ty_type {
                         let req = #message_type(#arg_phantom #( #handler_args ),*);
-                        self.request(req)
+                        #request
                     }
                 }
             });
@@ -776,12 +956,13 @@ impl AbstractProcess {
                 } = handler;
 
                 let return_ty_type = format_ident!("ReturnTy_{}", ident);
+                let request = request_call(ident);
                 quote! {
                     type #return_ty_type = Result<#return_ty, lunatic::time::Timeout>;
                     #( #attrs )*
                     fn #ident #generics (&self #(, #args )*) -> Self::#return_ty_type {
                         let req = #message_type(#arg_phantom #( #handler_args ),*);
-                        self.request(req)
+                        #request
                     }
                 }
             });
@@ -948,6 +1129,9 @@ enum ItemAttr {
     Init,
     Terminate,
     HandleLinkTrapped,
+    HandleSignal,
+    HandleTimeout,
+    OnMessage,
     HandleMessage,
     HandleRequest,
     HandleDeferredRequest,
@@ -959,6 +1143,9 @@ impl ItemAttr {
             "init" => Some(ItemAttr::Init),
             "terminate" => Some(ItemAttr::Terminate),
             "handle_link_death" => Some(ItemAttr::HandleLinkTrapped),
+            "handle_signal" => Some(ItemAttr::HandleSignal),
+            "handle_timeout" => Some(ItemAttr::HandleTimeout),
+            "on_message" => Some(ItemAttr::OnMessage),
             "handle_message" => Some(ItemAttr::HandleMessage),
             "handle_request" => Some(ItemAttr::HandleRequest),
             "handle_deferred_request" => Some(ItemAttr::HandleDeferredRequest),
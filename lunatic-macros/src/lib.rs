@@ -14,6 +14,12 @@ mod process_name;
 /// Note: The macro can only be used on `main` function with 1 argument of type
 /// `Mailbox<T>`.
 ///
+/// After `main` returns, the hooks registered with `lunatic::on_app_shutdown`
+/// are run.
+///
+/// Like the standard `main`, it can also return a `Result`. If an error is
+/// returned, it's printed and the process exits with a non-zero code.
+///
 /// # Example
 /// ```ignore
 /// #[lunatic::main]
@@ -21,6 +27,15 @@ mod process_name;
 ///     println!("Hello, world!");
 /// }
 /// ```
+///
+/// ```ignore
+/// #[lunatic::main]
+/// fn main(_: Mailbox<()>) -> std::io::Result<()> {
+///     let listener = TcpListener::bind("127.0.0.1:0")?;
+///     println!("Listening on {}", listener.local_addr()?);
+///     Ok(())
+/// }
+/// ```
 #[allow(clippy::needless_doctest_main)]
 #[proc_macro_attribute]
 pub fn main(_args: TokenStream, item: TokenStream) -> TokenStream {
@@ -37,14 +52,19 @@ pub fn main(_args: TokenStream, item: TokenStream) -> TokenStream {
     }
 
     let arguments = input.sig.inputs;
+    let output = input.sig.output;
     let block = input.block;
 
+    // The result is returned from the real `main`, so that errors are reported
+    // the same way as by the standard library.
     quote! {
-        fn main() {
-            fn __with_mailbox(#arguments) {
+        fn main() #output {
+            fn __with_mailbox(#arguments) #output {
                 #block
             }
-            unsafe { __with_mailbox(lunatic::Mailbox::new()) };
+            let result = unsafe { __with_mailbox(lunatic::Mailbox::new()) };
+            lunatic::shutdown::run_app_shutdown();
+            result
         }
     }
     .into()
@@ -55,9 +75,20 @@ pub fn main(_args: TokenStream, item: TokenStream) -> TokenStream {
 ///
 /// - Use `#[init]`, `#[terminate]`, and `#[handle_link_trapped]` attributes to
 /// specify methods for implementing [`AbstractProcess`].
+/// - The `#[terminate]` method can take a `reason: Option<R>` argument, that
+///   receives the reason passed to `ProcessRef::shutdown_with`.
 /// - Use `#[handle_message]`, `#[handle_request]` and
 ///   `#[handle_deferred_request]` attributes to specify message and request
 ///   handlers.
+/// - Use `#[handle_request(serializer = "lunatic::serializer::Json")]` to
+///   encode the requests and responses of a single handler with a different
+///   serializer than the rest of the process.
+/// - Use the `#[handle_signal]` attribute on a method taking a
+///   [`ProcessSignal`] to handle signals sent with `ProcessRef::signal`.
+/// - Use the `#[handle_timeout]` attribute on a method to run code when the
+///   process shuts down after its idle timeout.
+/// - Use the `#[on_message]` attribute on an associated function taking a
+///   [`MessageMetric`] to receive measurements of each handled message.
 ///
 /// Specifying message types is unnecessary because the macro will create
 /// wrapper types for messages on all handlers. Handlers can take an arbitrary
@@ -123,6 +154,8 @@ pub fn main(_args: TokenStream, item: TokenStream) -> TokenStream {
 ///     );
 /// ```
 /// [`AbstractProcess`]: process/trait.AbstractProcess.html
+/// [`MessageMetric`]: ap/struct.MessageMetric.html
+/// [`ProcessSignal`]: ap/enum.ProcessSignal.html
 #[proc_macro_attribute]
 pub fn abstract_process(args: TokenStream, item: TokenStream) -> TokenStream {
     match abstract_process::AbstractProcess::new(args, item) {
@@ -156,6 +189,19 @@ pub fn abstract_process(args: TokenStream, item: TokenStream) -> TokenStream {
 ///
 /// assert_eq!(LoggingProcess.process_name(), "global_logging_process");
 /// ```
+///
+/// To only change the shape of the generated name, use the
+/// `#[lunatic(process_name_format = "...")]` attribute. The `{pkg}`,
+/// `{version}`, `{module}` and `{ident}` placeholders are replaced with the
+/// parts of the default name.
+///
+/// ```ignore
+/// #[derive(ProcessName)]
+/// #[lunatic(process_name_format = "{pkg}:{ident}")]
+/// struct LoggingProcess;
+///
+/// assert_eq!(LoggingProcess.process_name(), "lunatic:LoggingProcess");
+/// ```
 #[proc_macro_derive(ProcessName, attributes(lunatic))]
 pub fn process_name(input: TokenStream) -> TokenStream {
     let process_name_derive = parse_macro_input!(input as ProcessNameDerive);
@@ -1,15 +1,18 @@
 use darling::FromAttributes;
 use quote::{quote, ToTokens, TokenStreamExt};
-use syn::{spanned::Spanned, DeriveInput};
+use syn::spanned::Spanned;
+use syn::DeriveInput;
 
 #[derive(Default, FromAttributes)]
 #[darling(attributes(lunatic))]
 struct Attrs {
     process_name: Option<String>,
+    process_name_format: Option<syn::LitStr>,
 }
 
 pub struct ProcessNameDerive {
     attrs: Result<Attrs, darling::Error>,
+    format: Option<Vec<proc_macro2::TokenStream>>,
     ident: syn::Ident,
 }
 
@@ -30,8 +33,25 @@ impl syn::parse::Parse for ProcessNameDerive {
             ));
         }
 
+        let format = match &attrs {
+            Ok(Attrs {
+                process_name,
+                process_name_format: Some(format),
+            }) => {
+                if process_name.is_some() {
+                    return Err(syn::Error::new(
+                        format.span(),
+                        "`process_name` and `process_name_format` can't be used together",
+                    ));
+                }
+                Some(format_pieces(format, &input.ident)?)
+            }
+            _ => None,
+        };
+
         Ok(ProcessNameDerive {
             attrs,
+            format,
             ident: input.ident,
         })
     }
@@ -39,7 +59,11 @@ impl syn::parse::Parse for ProcessNameDerive {
 
 impl ToTokens for ProcessNameDerive {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let Self { attrs, ident } = self;
+        let Self {
+            attrs,
+            format,
+            ident,
+        } = self;
 
         let attrs = match attrs {
             Ok(attrs) => attrs,
@@ -49,9 +73,10 @@ impl ToTokens for ProcessNameDerive {
             }
         };
 
-        let process_name_impl = match &attrs.process_name {
-            Some(process_name) => quote! { #process_name },
-            None => {
+        let process_name_impl = match (&attrs.process_name, format) {
+            (Some(process_name), _) => quote! { #process_name },
+            (None, Some(pieces)) => quote! { std::concat!(#(#pieces),*) },
+            (None, None) => {
                 let ident_str = ident.to_string();
                 quote! {
                     std::concat!(
@@ -76,3 +101,75 @@ impl ToTokens for ProcessNameDerive {
         });
     }
 }
+
+/// Splits a `process_name_format` template into the arguments of a `concat!`
+/// call, replacing the placeholders with the matching values.
+fn format_pieces(
+    format: &syn::LitStr,
+    ident: &syn::Ident,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let template = format.value();
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = match rest.find('}') {
+                    Some(end) => end,
+                    None => {
+                        return Err(syn::Error::new(
+                            format.span(),
+                            "unterminated placeholder, use `{{` to escape `{`",
+                        ))
+                    }
+                };
+                let placeholder = &rest[..end];
+                let value = match placeholder {
+                    "pkg" => quote! { std::env!("CARGO_PKG_NAME") },
+                    "version" => quote! { std::env!("CARGO_PKG_VERSION") },
+                    "module" => quote! { std::module_path!() },
+                    "ident" => {
+                        let ident_str = ident.to_string();
+                        quote! { #ident_str }
+                    }
+                    _ => {
+                        return Err(syn::Error::new(
+                            format.span(),
+                            format!(
+                                "unknown placeholder `{{{}}}`, expected one of `{{pkg}}`, `{{version}}`, `{{module}}` or `{{ident}}`",
+                                placeholder
+                            ),
+                        ))
+                    }
+                };
+                if !literal.is_empty() {
+                    pieces.push(quote! { #literal });
+                    literal.clear();
+                }
+                pieces.push(value);
+                chars = rest[end + 1..].chars();
+            }
+            '}' => {
+                return Err(syn::Error::new(
+                    format.span(),
+                    "unmatched `}`, use `}}` to escape it",
+                ))
+            }
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() || pieces.is_empty() {
+        pieces.push(quote! { #literal });
+    }
+    Ok(pieces)
+}
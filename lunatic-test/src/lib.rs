@@ -7,20 +7,49 @@ use syn::spanned::Spanned;
 
 /// Marks function to be executed by the lunatic runtime as a unit test. This is
 /// a drop-in replacement for the standard `#[test]` attribute macro.
+///
+/// Helpers for testing failure handling, like `kill_and_wait`, are available
+/// in the `lunatic::test` module.
+///
+/// A `#[timeout(500)]` attribute fails the test if it runs for longer than
+/// the given number of milliseconds. It can be combined with
+/// `#[should_panic]`.
 #[proc_macro_attribute]
 pub fn test(_args: TokenStream, item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemFn);
-    let original_input = input.clone();
+    let mut original_input = input.clone();
+    // `#[timeout]` is unknown to the standard `#[test]`.
+    original_input
+        .attrs
+        .retain(|attribute| !attribute.path.is_ident("timeout"));
     let attributes = &input.attrs;
     let span = input.span();
 
     // Check if #[should_panic] attribute is present.
     let mut should_panic = None;
-    let mut ignore = "";
+    let mut ignore = false;
+    let mut timeout = None;
     for attribute in attributes.iter() {
         if let Some(ident) = attribute.path.get_ident() {
             if ident == "ignore" {
-                ignore = "#ignore_";
+                ignore = true;
+            }
+            if ident == "timeout" {
+                let error = syn::Error::new_spanned(
+                    &attribute.tokens,
+                    "argument must be of the form: `timeout(milliseconds)`",
+                )
+                .to_compile_error()
+                .into();
+
+                let millis = match attribute.parse_args::<syn::LitInt>() {
+                    Ok(millis) => millis,
+                    Err(_) => return error,
+                };
+                match millis.base10_parse::<u64>() {
+                    Ok(millis) => timeout = Some(millis),
+                    Err(_) => return error,
+                }
             }
             if ident == "should_panic" {
                 // Common error message
@@ -76,12 +105,7 @@ pub fn test(_args: TokenStream, item: TokenStream) -> TokenStream {
         }
     }
 
-    let mut export_name = format!("#lunatic_test_{}", ignore);
-    if let Some(ref panic_str) = should_panic {
-        // Escape # in panic_str
-        let panic_str = panic_str.replace('#', "\\#");
-        export_name = format!("{}#panic_{}#", export_name, panic_str,);
-    }
+    let export_name = export_name(ignore, timeout, should_panic.as_deref());
     let function_name = input.sig.ident.to_string();
 
     let name = input.sig.ident;
@@ -133,3 +157,40 @@ pub fn test(_args: TokenStream, item: TokenStream) -> TokenStream {
     }
     .into()
 }
+
+/// Encodes the test options into the prefix of the exported function name, so
+/// that the runtime can read them.
+fn export_name(ignore: bool, timeout: Option<u64>, should_panic: Option<&str>) -> String {
+    let mut export_name = "#lunatic_test_".to_string();
+    if ignore {
+        export_name.push_str("#ignore_");
+    }
+    if let Some(panic_str) = should_panic {
+        // Escape # in panic_str
+        let panic_str = panic_str.replace('#', "\\#");
+        export_name = format!("{}#panic_{}#", export_name, panic_str,);
+    }
+    // Appended last, so that the existing `#ignore_` and `#panic_` markers
+    // keep their position for runtimes that don't know about timeouts.
+    if let Some(millis) = timeout {
+        export_name = format!("{}#timeout_{}_", export_name, millis);
+    }
+    export_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::export_name;
+
+    #[test]
+    fn export_name_contains_timeout() {
+        assert_eq!(
+            export_name(false, Some(500), None),
+            "#lunatic_test_#timeout_500_"
+        );
+        assert_eq!(
+            export_name(true, Some(20), Some("#")),
+            "#lunatic_test_#ignore_#panic_\\###timeout_20_"
+        );
+    }
+}
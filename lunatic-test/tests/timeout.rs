@@ -0,0 +1,16 @@
+use std::time::Duration;
+
+use lunatic_test::test;
+
+#[test]
+#[timeout(500)]
+fn finishes_before_timeout() {
+    lunatic::sleep(Duration::from_millis(10));
+}
+
+#[test]
+#[timeout(500)]
+#[should_panic(expected = "failed")]
+fn timeout_with_should_panic() {
+    panic!("failed before the timeout");
+}
@@ -1,6 +1,7 @@
 use std::marker::PhantomData;
 
 use super::{lifecycles, AbstractProcess, ProcessRef, StartupError};
+use crate::distributed::{self, NodePicker, RoundRobin};
 use crate::function::process::{process_name, ProcessType};
 use crate::{LunaticError, Mailbox, Process, ProcessConfig, ProcessName, Tag};
 
@@ -74,6 +75,21 @@ where
         }
     }
 
+    /// Spawns the process on one of the [`nodes`](distributed::nodes) in
+    /// the cluster, chosen with the [`RoundRobin`] picker.
+    pub fn on_any_node(self) -> AbstractProcessBuilder<'a, T> {
+        self.on_any_node_with(&RoundRobin)
+    }
+
+    /// Spawns the process on one of the [`nodes`](distributed::nodes) in
+    /// the cluster, chosen by `picker`.
+    ///
+    /// The node is picked when this method is called.
+    pub fn on_any_node_with<P: NodePicker>(self, picker: &P) -> AbstractProcessBuilder<'a, T> {
+        let node = picker.pick_node(&distributed::nodes());
+        self.on_node(node)
+    }
+
     /// Starts a new `AbstractProcess` and returns a reference to it.
     ///
     /// This call will block until the `init` function finishes. If the `init`
@@ -147,8 +163,8 @@ where
         name: &N,
         arg: T::Arg,
     ) -> Result<ProcessRef<T>, StartupError<T>> {
-        let name: &str = name.process_name();
-        let name = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name);
+        let registered_name: &str = name.process_name();
+        let name = process_name::<T, T::Serializer>(ProcessType::ProcessRef, registered_name);
         let init_tag = Tag::new();
         let this = unsafe { Process::<Result<(), StartupError<T>>, T::Serializer>::this() };
         let entry_data = (this, init_tag, arg);
@@ -208,6 +224,11 @@ where
             _ => unreachable!(),
         };
 
+        // Spawning on a remote node doesn't register the name.
+        if self.node.is_none() {
+            crate::registry::record(registered_name, name, process.node_id(), process.id());
+        }
+
         // Wait on `init()`
         let mailbox: Mailbox<Result<(), StartupError<T>>, T::Serializer> =
             unsafe { Mailbox::new() };
@@ -0,0 +1,89 @@
+//! Routing of messages that an [`AbstractProcess`] can't handle.
+
+use std::cell::RefCell;
+use std::io::{Read, Write};
+
+use super::{AbstractProcess, MessageHandler, ProcessRef};
+use crate::serializer::{CanSerialize, MessageRw};
+use crate::{host, process_local, Tag};
+
+type Sink = Box<dyn Fn(DeadLetter)>;
+
+process_local! {
+    // Sink that receives all messages rejected by the abstract process running
+    // inside of this process.
+    static DEAD_LETTER_SINK: RefCell<Option<Sink>> = RefCell::new(None);
+}
+
+/// A message that was rejected by an [`AbstractProcess`].
+///
+/// Dead letters carry the raw message, so that they can be inspected and
+/// later replayed with [`DeadLetter::replay`].
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetter {
+    /// ID of the process that rejected the message.
+    pub process_id: u64,
+    /// ID of the node that the rejecting process is running on.
+    pub node_id: u64,
+    /// Why the message was rejected.
+    pub reason: DeadLetterReason,
+    /// The original tag of the message, including the handler ID.
+    pub tag: Tag,
+    /// The raw, still serialized, message.
+    pub data: Vec<u8>,
+}
+
+/// The reason why a message ended up as a [`DeadLetter`].
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum DeadLetterReason {
+    /// The message couldn't be deserialized by the handler.
+    DecodeFailed(String),
+    /// The message didn't match any handler of the abstract process.
+    UnknownHandler(u8),
+}
+
+impl DeadLetter {
+    /// Sends the raw message again to the process `to`, using the original
+    /// tag.
+    ///
+    /// The message is not checked in any way, and it's up to the caller to
+    /// make sure that `to` can handle it.
+    pub fn replay<T: AbstractProcess>(&self, to: ProcessRef<T>) {
+        unsafe { host::api::message::create_data(self.tag.id(), self.data.len() as u64) };
+        MessageRw {}.write_all(&self.data).unwrap();
+        host::send(to.node_id(), to.id());
+    }
+}
+
+/// Sets the sink for messages that the abstract process rejects.
+pub(crate) fn set_sink<D>(sink: ProcessRef<D>)
+where
+    D: MessageHandler<DeadLetter> + 'static,
+    D::Serializer: CanSerialize<DeadLetter>,
+{
+    DEAD_LETTER_SINK.set(Some(Box::new(move |dead_letter| sink.send(dead_letter))));
+}
+
+/// Routes the message currently in the scratch buffer to the dead-letter sink.
+///
+/// Returns the `reason` back if no sink is configured.
+pub(crate) fn route(tag: Tag, reason: DeadLetterReason) -> Result<(), DeadLetterReason> {
+    DEAD_LETTER_SINK.with_borrow(|sink| match sink.as_ref() {
+        Some(sink) => {
+            // The handler could have already consumed part of the message.
+            unsafe { host::api::message::seek_data(0) };
+            let size = unsafe { host::api::message::data_size() };
+            let mut data = Vec::with_capacity(size as usize);
+            MessageRw {}.read_to_end(&mut data).unwrap();
+            sink(DeadLetter {
+                process_id: host::process_id(),
+                node_id: host::node_id(),
+                reason,
+                tag,
+                data,
+            });
+            Ok(())
+        }
+        None => Err(reason),
+    })
+}
@@ -3,17 +3,33 @@
 use std::any::{type_name, TypeId};
 use std::marker::PhantomData;
 
+use super::dead_letter::DeadLetterReason;
 use super::messages::RequestMessage;
-use super::{AbstractProcess, DeferredRequestHandler, MessageHandler, RequestHandler};
-use crate::serializer::CanSerialize;
+use super::stream::{StreamItem, StreamResponder};
+use super::{
+    AbstractProcess, DeferredRequestHandler, MessageHandler, RequestHandler, StreamRequestHandler,
+};
+use crate::serializer::{CanSerialize, DecodeError};
 use crate::Tag;
 
 pub struct Message<T>(PhantomData<T>);
 pub struct Request<T>(PhantomData<T>);
+/// A request encoded with the serializer `S` instead of the
+/// [`AbstractProcess::Serializer`].
+///
+/// The request and response only need to be serializable by `S`.
+///
+/// Requests to it are made with
+/// [`ProcessRef::request_with`](super::ProcessRef::request_with).
+pub struct RequestWith<T, S>(PhantomData<(T, S)>);
 pub struct DeferredRequest<T>(PhantomData<T>);
+pub struct StreamRequest<T>(PhantomData<T>);
 
 pub trait Handler<AP: AbstractProcess> {
-    fn handle(response_tag: Tag, state: &mut AP::State);
+    /// Decodes the incoming message and passes it to the handler.
+    ///
+    /// If the message can't be decoded, the handler is not called.
+    fn handle(response_tag: Tag, state: &mut AP::State) -> Result<(), DecodeError>;
 }
 
 impl<AP, T> Handler<AP> for Message<T>
@@ -21,10 +37,11 @@ where
     AP: MessageHandler<T>,
     AP::Serializer: CanSerialize<T>,
 {
-    fn handle(_: Tag, state: &mut <AP as AbstractProcess>::State) {
+    fn handle(_: Tag, state: &mut <AP as AbstractProcess>::State) -> Result<(), DecodeError> {
         let state = super::State { state };
-        let message = AP::Serializer::decode().unwrap();
+        let message = AP::Serializer::decode()?;
         AP::handle(state, message);
+        Ok(())
     }
 }
 
@@ -35,12 +52,34 @@ where
     AP::Serializer: CanSerialize<AP::Response>,
     AP::Serializer: CanSerialize<RequestMessage<T, AP::Response, AP::Serializer>>,
 {
-    fn handle(response_tag: Tag, state: &mut <AP as AbstractProcess>::State) {
+    fn handle(
+        response_tag: Tag,
+        state: &mut <AP as AbstractProcess>::State,
+    ) -> Result<(), DecodeError> {
         let state = super::State { state };
-        let request: RequestMessage<T, AP::Response, AP::Serializer> =
-            AP::Serializer::decode().unwrap();
+        let request: RequestMessage<T, AP::Response, AP::Serializer> = AP::Serializer::decode()?;
         let response = AP::handle(state, request.0);
         request.1.send_response(response, response_tag);
+        Ok(())
+    }
+}
+
+impl<AP, T, S> Handler<AP> for RequestWith<T, S>
+where
+    AP: RequestHandler<T>,
+    S: CanSerialize<T>,
+    S: CanSerialize<AP::Response>,
+    S: CanSerialize<RequestMessage<T, AP::Response, S>>,
+{
+    fn handle(
+        response_tag: Tag,
+        state: &mut <AP as AbstractProcess>::State,
+    ) -> Result<(), DecodeError> {
+        let state = super::State { state };
+        let request: RequestMessage<T, AP::Response, S> = S::decode()?;
+        let response = AP::handle(state, request.0);
+        request.1.send_response(response, response_tag);
+        Ok(())
     }
 }
 
@@ -51,24 +90,45 @@ where
     AP::Serializer: CanSerialize<AP::Response>,
     AP::Serializer: CanSerialize<RequestMessage<T, AP::Response, AP::Serializer>>,
 {
-    fn handle(response_tag: Tag, state: &mut <AP as AbstractProcess>::State) {
+    fn handle(
+        response_tag: Tag,
+        state: &mut <AP as AbstractProcess>::State,
+    ) -> Result<(), DecodeError> {
         let state = super::State { state };
-        let request: RequestMessage<T, AP::Response, AP::Serializer> =
-            AP::Serializer::decode().unwrap();
+        let request: RequestMessage<T, AP::Response, AP::Serializer> = AP::Serializer::decode()?;
         AP::handle(
             state,
             request.0,
-            super::DeferredResponse {
-                tag: response_tag,
-                return_address: request.1,
-            },
+            super::DeferredResponse::new(response_tag, request.1),
         );
+        Ok(())
+    }
+}
+
+impl<AP, T> Handler<AP> for StreamRequest<T>
+where
+    AP: StreamRequestHandler<T>,
+    AP::Serializer: CanSerialize<T>,
+    AP::Serializer: CanSerialize<StreamItem<AP::Item>>,
+    AP::Serializer: CanSerialize<RequestMessage<T, StreamItem<AP::Item>, AP::Serializer>>,
+{
+    fn handle(
+        response_tag: Tag,
+        state: &mut <AP as AbstractProcess>::State,
+    ) -> Result<(), DecodeError> {
+        let state = super::State { state };
+        let request: RequestMessage<T, StreamItem<AP::Item>, AP::Serializer> =
+            AP::Serializer::decode()?;
+        let responder = StreamResponder::new(response_tag, request.1.process());
+        AP::handle(state, request.0, responder);
+        Ok(())
     }
 }
 
 pub trait Handlers<AP: AbstractProcess> {
     fn handler_id<Handler: 'static>() -> u8;
-    fn handle(response_tag: Tag, id: u8, state: &mut AP::State);
+    fn handler_name(id: u8) -> &'static str;
+    fn handle(response_tag: Tag, id: u8, state: &mut AP::State) -> Result<(), DeadLetterReason>;
 }
 
 // Implement `Handlers` for tuple containing up to 16 handlers.
@@ -110,18 +170,26 @@ mod macros {
                     }
                 }
 
+                fn handler_name(id: u8) -> &'static str {
+                    match id {
+                        $($i => type_name::<$args>(),)*
+                        _ => "unknown",
+                    }
+                }
+
                 #[allow(unused_variables)]
-                fn handle(response_tag: Tag, id: u8, state: &mut <AP as AbstractProcess>::State) {
+                fn handle(
+                    response_tag: Tag,
+                    id: u8,
+                    state: &mut <AP as AbstractProcess>::State
+                ) -> Result<(), DeadLetterReason> {
                     match id {
                         // Handlers start with a value of 1. Zero indicates that this is a response from another
                         // process where the call timed out, and we don't care about the result.
-                        0 => (),
-                        $($i => $args::handle(response_tag, state),)*
-                        _ => unreachable!(
-                            "AbstractProcess `{}` received message with unknown message ID: {}.",
-                            type_name::<AP>(),
-                            id
-                        ),
+                        0 => Ok(()),
+                        $($i => $args::handle(response_tag, state)
+                            .map_err(|err| DeadLetterReason::DecodeFailed(err.to_string())),)*
+                        _ => Err(DeadLetterReason::UnknownHandler(id)),
                     }
                 }
             }
@@ -1,16 +1,58 @@
 //! The [`AbstractProcess`] has well defined lifecycles, from startup to
 //! termination. This file contains the implementation of each lifecycle.
 
+use std::any::type_name;
+use std::cell::{Cell, RefCell};
 use std::ptr::null;
+use std::time::{Duration, Instant};
 
+use super::dead_letter::{self, DeadLetterReason};
 use super::handlers::Handlers;
-use super::messages::{ShutdownMessage, SHUTDOWN_HANDLER};
+use super::messages::{
+    ExitValueMessage, ShutdownMessage, SignatureMessage, SnapshotMessage, EXIT_VALUE_HANDLER,
+    SHUTDOWN_HANDLER, SIGNAL_HANDLER, SIGNATURE_HANDLER, SNAPSHOT_HANDLER,
+};
 use super::tag::AbstractProcessTag;
-use super::{AbstractProcess, Config, StartupError};
-use crate::mailbox::LINK_DIED;
+use super::{
+    persistence, AbstractProcess, Config, ExitValue, MessageMetric, ProcessSnapshot, StartupError,
+};
+use crate::mailbox::{LINK_DIED, PROCESS_DIED, TIMEOUT};
 use crate::panic::{catch_panic, Panicked};
-use crate::serializer::CanSerialize;
-use crate::{host, Mailbox, Process, Tag};
+use crate::serializer::{Bincode, CanSerialize};
+use crate::{host, process_local, Mailbox, Process, Tag};
+
+process_local! {
+    // Point in time at which the `AbstractProcess` was started.
+    static STARTED: Cell<Option<Instant>> = Cell::new(None);
+    // Shut down if no message arrives during this period.
+    static IDLE_TIMEOUT: Cell<Option<Duration>> = Cell::new(None);
+    // Number of deferred responses held by this process that are not sent yet.
+    static PENDING_DEFERRED: Cell<usize> = Cell::new(0);
+    // Panic on messages that can't be handled, instead of skipping them.
+    static STRICT_DECODING: Cell<bool> = Cell::new(false);
+    // Sent to the parent after a normal exit.
+    static EXIT_VALUE: RefCell<Option<ExitValue>> = RefCell::new(None);
+}
+
+pub(crate) fn set_idle_timeout(timeout: Duration) {
+    IDLE_TIMEOUT.set(Some(timeout));
+}
+
+pub(crate) fn set_strict_decoding(strict: bool) {
+    STRICT_DECODING.set(strict);
+}
+
+pub(crate) fn set_exit_value(value: ExitValue) {
+    EXIT_VALUE.set(Some(value));
+}
+
+pub(crate) fn deferred_started() {
+    PENDING_DEFERRED.set(PENDING_DEFERRED.get() + 1);
+}
+
+pub(crate) fn deferred_finished() {
+    PENDING_DEFERRED.set(PENDING_DEFERRED.get().saturating_sub(1));
+}
 
 type ParentProcessRef<AP> =
     Process<Result<(), StartupError<AP>>, <AP as AbstractProcess>::Serializer>;
@@ -31,6 +73,8 @@ pub(crate) fn entry<AP: AbstractProcess>(
     AP::Serializer: CanSerialize<()>,
     AP::Serializer: CanSerialize<ShutdownMessage<AP::Serializer>>,
 {
+    STARTED.set(Some(Instant::now()));
+
     // Catch errors during startup and notify parent. Panics will also be caught.
     let mut state = match startup::<AP>(arg) {
         Ok(state) => {
@@ -45,8 +89,27 @@ pub(crate) fn entry<AP: AbstractProcess>(
         }
     };
 
-    let shutdown_tag = loop_and_handle::<AP>(&mut state);
-    shutdown::<AP>(shutdown_tag, state);
+    match loop_and_handle::<AP>(&mut state) {
+        Some(shutdown_tag) => shutdown::<AP>(shutdown_tag, state, parent),
+        // The idle timeout expired.
+        None => {
+            AP::handle_timeout(super::State { state: &mut state });
+            persistence::take(&state);
+            AP::terminate(state);
+            send_exit_value(parent);
+        }
+    }
+}
+
+/// Sends the value set with [`set_exit_value`](super::set_exit_value) to the
+/// parent.
+fn send_exit_value<M, S>(parent: Process<M, S>) {
+    if let Some(value) = EXIT_VALUE.take() {
+        let tag = AbstractProcessTag::from_u6(EXIT_VALUE_HANDLER);
+        let parent: Process<ExitValueMessage, Bincode> =
+            unsafe { Process::new(parent.node_id(), parent.id()) };
+        parent.tag_send(tag, ExitValueMessage(host::process_id(), value));
+    }
 }
 
 /// This code is executed during the [`AbstractProcess::start`] call.
@@ -61,14 +124,49 @@ fn startup<AP: AbstractProcess>(arg: AP::Arg) -> Result<AP::State, StartupError<
 
 /// Extracts the handler out of the tag for each incoming message, until
 /// shutdown message is received.
-fn loop_and_handle<AP: AbstractProcess>(state: &mut AP::State) -> Tag {
+///
+/// Returns `None` if the process shuts down because it was idle.
+fn loop_and_handle<AP: AbstractProcess>(state: &mut AP::State) -> Option<Tag> {
+    let idle_timeout = IDLE_TIMEOUT.get();
+    let mut last_activity = Instant::now();
     loop {
-        // Wait for next message & handle link died if result matches constant.
-        if unsafe { host::api::message::receive(null(), 0, u64::MAX) } == LINK_DIED {
-            let tag = unsafe { host::api::message::get_tag() };
-            let tag = Tag::from(tag);
-            AP::handle_link_death(super::State { state }, tag);
-            continue;
+        persistence::take_if_due(state);
+
+        // Wake up for the idle timeout or the next snapshot, whichever is first.
+        let idle_remaining =
+            idle_timeout.map(|timeout| timeout.saturating_sub(last_activity.elapsed()));
+        let timeout = match (idle_remaining, persistence::until_due()) {
+            (Some(idle), Some(snapshot)) => idle.min(snapshot).as_millis() as u64,
+            (Some(timeout), None) | (None, Some(timeout)) => timeout.as_millis() as u64,
+            (None, None) => u64::MAX,
+        };
+
+        // Wait for next message & handle link or monitor deaths if result matches
+        // constant.
+        match unsafe { host::api::message::receive(null(), 0, timeout) } {
+            TIMEOUT => {
+                if matches!(idle_timeout, Some(idle) if last_activity.elapsed() >= idle) {
+                    if PENDING_DEFERRED.get() == 0 {
+                        return None;
+                    }
+                    last_activity = Instant::now();
+                }
+                continue;
+            }
+            LINK_DIED => {
+                last_activity = Instant::now();
+                let tag = unsafe { host::api::message::get_tag() };
+                let tag = Tag::from(tag);
+                AP::handle_link_death(super::State { state }, tag);
+                continue;
+            }
+            PROCESS_DIED => {
+                last_activity = Instant::now();
+                let process_id = unsafe { host::api::message::get_process_id() };
+                AP::handle_process_death(super::State { state }, process_id);
+                continue;
+            }
+            _ => last_activity = Instant::now(),
         }
 
         // Extract `data` from tag
@@ -78,16 +176,98 @@ fn loop_and_handle<AP: AbstractProcess>(state: &mut AP::State) -> Tag {
 
         // Check if `data` matches the shutdown message
         if data == SHUTDOWN_HANDLER {
-            break response_tag;
+            return Some(response_tag);
         }
 
+        if data == SNAPSHOT_HANDLER {
+            snapshot(response_tag);
+            continue;
+        }
+
+        if data == SIGNATURE_HANDLER {
+            if let Ok(SignatureMessage(return_address)) = Bincode::decode() {
+                return_address.send_response(super::signature::<AP>(), response_tag);
+            }
+            continue;
+        }
+
+        if data == EXIT_VALUE_HANDLER {
+            if let Ok(ExitValueMessage(process_id, value)) = Bincode::decode() {
+                AP::handle_exit_value(super::State { state }, process_id, value);
+            }
+            continue;
+        }
+
+        if data == SIGNAL_HANDLER {
+            // Signals are always sent with `ProcessRef::signal`, a signal that
+            // can't be decoded is dropped.
+            if let Ok(signal) = Bincode::decode() {
+                AP::handle_signal(super::State { state }, signal);
+            }
+            continue;
+        }
+
+        // Only measure messages if the `on_message` hook is enabled.
+        let metric = if AP::MESSAGE_METRICS && data != 0 {
+            let size = unsafe { host::api::message::data_size() } as usize;
+            Some((size, Instant::now()))
+        } else {
+            None
+        };
+
         // Use `data` to look up the right handler function
-        AP::Handlers::handle(response_tag, data, state);
+        let result = AP::Handlers::handle(response_tag, data, state);
+
+        if let Some((size, start)) = metric {
+            AP::on_message(MessageMetric {
+                handler: AP::Handlers::handler_name(data),
+                size,
+                duration: start.elapsed(),
+            });
+        }
+
+        if let Err(reason) = result {
+            // Forward rejected messages to the dead-letter sink, if one is set.
+            // Otherwise, skip them so that one bad message doesn't take down
+            // the process, unless strict decoding is enabled.
+            let reason = match dead_letter::route(tag, reason) {
+                Ok(()) => continue,
+                Err(reason) => reason,
+            };
+            if STRICT_DECODING.get() {
+                match reason {
+                    DeadLetterReason::DecodeFailed(err) => panic!(
+                        "AbstractProcess `{}` failed to decode message: {}.",
+                        type_name::<AP>(),
+                        err
+                    ),
+                    DeadLetterReason::UnknownHandler(id) => panic!(
+                        "AbstractProcess `{}` received message with unknown message ID: {}.",
+                        type_name::<AP>(),
+                        id
+                    ),
+                }
+            }
+            AP::handle_rejected_message(super::State { state }, reason);
+        }
     }
 }
 
+/// Is executed if the [`AbstractProcess`] receives a `snapshot` request.
+fn snapshot(response_tag: Tag) {
+    let snapshot_message: SnapshotMessage = Bincode::decode().unwrap();
+    let snapshot = ProcessSnapshot {
+        id: host::process_id(),
+        node_id: host::node_id(),
+        alive: true,
+        names: host::registered_names(),
+        uptime: STARTED.get().map(|started| started.elapsed()),
+    };
+    snapshot_message.0.send_response(snapshot, response_tag);
+}
+
 /// Is executed if the [`AbstractProcess`] receives a `shutdown` command.
-fn shutdown<AP>(shutdown_tag: Tag, state: AP::State)
+fn shutdown<AP>(shutdown_tag: Tag, state: AP::State, parent: ParentProcessRef<AP>)
 where
     AP: AbstractProcess,
     AP::Serializer: CanSerialize<()>,
@@ -96,6 +276,14 @@ where
     // The shutdown message needs to deserialize before `terminate` is called.
     // After `terminate` we could have another message in the buffer.
     let shutdown_message: ShutdownMessage<AP::Serializer> = AP::Serializer::decode().unwrap();
-    AP::terminate(state);
+    // Persist the final state before it's handed to `terminate`.
+    persistence::take(&state);
+    match shutdown_message.1 {
+        Some(reason) => AP::terminate_with(state, reason),
+        None => AP::terminate(state),
+    }
+    // Sent first, so that the value arrives before the one waiting on the
+    // shutdown can ask for it.
+    send_exit_value(parent);
     shutdown_message.0.send_response((), shutdown_tag);
 }
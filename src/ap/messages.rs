@@ -1,4 +1,5 @@
-use crate::serializer::CanSerialize;
+use super::{ExitValue, ProcessSnapshot, ShutdownReason};
+use crate::serializer::{Bincode, CanSerialize};
 use crate::{Process, Tag};
 
 /// Contains information about the request sender, so that a response can be
@@ -27,17 +28,67 @@ where
     }
 }
 
+impl<Response, Serializer> ReturnAddress<Response, Serializer> {
+    /// Returns the process waiting on the response.
+    pub(crate) fn process(&self) -> Process<Response, Serializer> {
+        self.process
+    }
+}
+
 /// Value identifying the shutdown handler.
 ///
 /// All other handlers have a value from 0-16.
 pub(crate) const SHUTDOWN_HANDLER: u8 = 32;
 
+/// Value identifying the snapshot handler.
+pub(crate) const SNAPSHOT_HANDLER: u8 = 33;
+
+/// Value identifying the signal handler.
+///
+/// Signals are [`ProcessSignal`](super::ProcessSignal) values, always
+/// serialized with [`Bincode`].
+pub(crate) const SIGNAL_HANDLER: u8 = 34;
+
+/// Value identifying the exit value handler.
+///
+/// Exit values are [`ExitValueMessage`] values, always serialized with
+/// [`Bincode`].
+pub(crate) const EXIT_VALUE_HANDLER: u8 = 35;
+
+/// Value identifying the signature handler.
+///
+/// Answers with the [`signature`](super::signature) of the
+/// [`AbstractProcess`](super::AbstractProcess), always serialized with
+/// [`Bincode`].
+pub(crate) const SIGNATURE_HANDLER: u8 = 36;
+
+/// An incoming message asking for the signature of the running
+/// [`AbstractProcess`](super::AbstractProcess).
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct SignatureMessage(pub(crate) ReturnAddress<String, Bincode>);
+
+/// The final value of a process, sent to the process that started it.
+///
+/// Contains the ID of the exited process.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct ExitValueMessage(pub(crate) u64, pub(crate) ExitValue);
+
+/// An incoming message requesting a [`ProcessSnapshot`].
+///
+/// It's always serialized with [`Bincode`], independent of the serializer used
+/// by the [`AbstractProcess`].
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct SnapshotMessage(pub(crate) ReturnAddress<ProcessSnapshot, Bincode>);
+
 /// An incoming message indicating a shutdown for the [`AbstractProcess`].
 ///
 /// The message combined with the `SHUTDOWN_HANDLER` data inside the tag.
 #[derive(serde::Serialize, serde::Deserialize)]
 #[serde(bound = "")]
-pub struct ShutdownMessage<Serializer>(pub(crate) ReturnAddress<(), Serializer>);
+pub struct ShutdownMessage<Serializer>(
+    pub(crate) ReturnAddress<(), Serializer>,
+    pub(crate) Option<ShutdownReason>,
+);
 
 /// An incoming message indicating a request for the [`AbstractProcess`].
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -1,29 +1,45 @@
 //! Contains the [`AbstractProcess`] abstraction.
 
 mod builder;
+mod dead_letter;
 mod lifecycles;
+mod monitor;
+mod persistence;
+mod reply;
+mod stream;
 mod tag;
 
 pub mod handlers;
 pub(crate) mod messages;
 
 use std::any::type_name;
-use std::fmt::Debug;
+use std::cell::Cell;
+use std::fmt::{Debug, Display};
 use std::marker::PhantomData;
-use std::mem;
 use std::ops::{Deref, DerefMut};
 use std::time::Duration;
+use std::{io, mem};
 
 use self::builder::AbstractProcessBuilder;
-use self::handlers::{DeferredRequest, Handlers, Message, Request};
-use self::messages::{RequestMessage, ReturnAddress, ShutdownMessage, SHUTDOWN_HANDLER};
+pub use self::dead_letter::{DeadLetter, DeadLetterReason};
+use self::handlers::{DeferredRequest, Handlers, Message, Request, RequestWith, StreamRequest};
+use self::messages::{
+    RequestMessage, ReturnAddress, ShutdownMessage, SignatureMessage, SnapshotMessage,
+    SHUTDOWN_HANDLER, SIGNAL_HANDLER, SIGNATURE_HANDLER, SNAPSHOT_HANDLER,
+};
+pub use self::monitor::{ExitReason, MonitorRef, ProcessDown};
+pub use self::persistence::{FileSnapshotStore, Snapshot, SnapshotStore};
+pub use self::reply::PendingReply;
+pub use self::stream::{ResponseStream, StreamItem, StreamResponder};
 use self::tag::AbstractProcessTag;
+use crate::distributed::NodePicker;
 use crate::function::process::{process_name, ProcessType};
 use crate::mailbox::{MailboxError, MessageSignal};
 use crate::protocol::ProtocolCapture;
-use crate::serializer::CanSerialize;
-use crate::time::{Timeout, TimerRef, WithDelay, WithTimeout};
-use crate::{host, MailboxResult, Process, ProcessConfig, ProcessName, Tag};
+use crate::registry::Lease;
+use crate::serializer::{Bincode, CanSerialize};
+use crate::time::{IntervalRef, Timeout, TimerRef, WithDelay, WithTimeout};
+use crate::{host, Mailbox, MailboxResult, Process, ProcessConfig, ProcessName, Tag};
 
 /// Building block for processes that act as a server of a client-server
 /// relation.
@@ -91,6 +107,20 @@ use crate::{host, MailboxResult, Process, ProcessConfig, ProcessName, Tag};
 /// }
 /// ```
 ///
+/// A [`StreamRequestHandler`] answers a request with multiple items. The
+/// stream is complete once the responder is finished or dropped.
+///
+/// ```rust
+/// impl StreamRequestHandler<Count> for Counter {
+///     type Item = u32;
+///     fn handle(state: State<Self>, _: Count, responder: StreamResponder<Self::Item, Self>) {
+///         for i in 0..state.0 {
+///             responder.push(i);
+///         }
+///     }
+/// }
+/// ```
+///
 /// _It is not enough just to define the handlers, they also need to be
 /// associated with the `AbstractProcess` using the [`Self::Handlers`] type:_
 ///
@@ -168,6 +198,11 @@ where
     /// Errors that can be returned from the `init` call to the spawner.
     type StartupError: Debug;
 
+    /// Enables the [`on_message`](AbstractProcess::on_message) hook.
+    ///
+    /// If it's `false`, messages are not measured at all.
+    const MESSAGE_METRICS: bool = false;
+
     /// Entry function of the new process.
     ///
     /// This function is executed inside the new process. It will receive the
@@ -182,9 +217,53 @@ where
     /// Called when a `shutdown` command is received.
     fn terminate(_state: Self::State) {}
 
+    /// Called instead of [`terminate`](AbstractProcess::terminate) when the
+    /// shutdown was requested with [`ProcessRef::shutdown_with`].
+    ///
+    /// By default it ignores the reason and calls `terminate`.
+    fn terminate_with(state: Self::State, _reason: ShutdownReason) {
+        Self::terminate(state)
+    }
+
     /// This function will be called if another linked process dies.
     fn handle_link_death(_state: State<Self>, _tag: Tag) {}
 
+    /// This function will be called if a monitored process dies.
+    ///
+    /// Unlike link deaths, it's also called if the process finished normally.
+    fn handle_process_death(_state: State<Self>, _process_id: u64) {}
+
+    /// This function will be called if no message arrives within the
+    /// [idle timeout](Config::set_idle_timeout).
+    ///
+    /// The process shuts down afterwards and calls
+    /// [`terminate`](AbstractProcess::terminate).
+    fn handle_timeout(_state: State<Self>) {}
+
+    /// This function will be called if the process skips a message that it
+    /// can't handle.
+    ///
+    /// Messages forwarded to a [`dead_letter_sink`](Config::dead_letter_sink)
+    /// or rejected with [strict decoding](Config::set_strict_decoding) enabled
+    /// don't reach this function. Skipped messages are ignored by default.
+    fn handle_rejected_message(_state: State<Self>, _reason: DeadLetterReason) {}
+
+    /// This function will be called if the process receives a signal sent with
+    /// [`ProcessRef::signal`].
+    ///
+    /// Signals are ignored by default.
+    fn handle_signal(_state: State<Self>, _signal: ProcessSignal) {}
+
+    /// This function will be called if a process started by this one exits
+    /// normally, after setting an exit value with [`set_exit_value`].
+    ///
+    /// Exit values are ignored by default.
+    fn handle_exit_value(_state: State<Self>, _process_id: u64, _value: ExitValue) {}
+
+    /// This function will be called after each handled message, if
+    /// [`MESSAGE_METRICS`](AbstractProcess::MESSAGE_METRICS) is `true`.
+    fn on_message(_metric: MessageMetric) {}
+
     /// Starts a new `AbstractProcess` and returns a reference to it.
     ///
     /// This call will block until the `init` function finishes. If the `init`
@@ -236,6 +315,18 @@ where
     fn on_node(node: u64) -> AbstractProcessBuilder<'static, Self> {
         AbstractProcessBuilder::new().on_node(node)
     }
+
+    /// Spawns the process on one of the nodes in the cluster, picking them in
+    /// turns.
+    fn on_any_node() -> AbstractProcessBuilder<'static, Self> {
+        AbstractProcessBuilder::new().on_any_node()
+    }
+
+    /// Spawns the process on one of the nodes in the cluster, chosen by
+    /// `picker`.
+    fn on_any_node_with<P: NodePicker>(picker: &P) -> AbstractProcessBuilder<'static, Self> {
+        AbstractProcessBuilder::new().on_any_node_with(picker)
+    }
 }
 
 /// [`AbstractProcess`] startup configuration.
@@ -243,6 +334,14 @@ where
 /// Available configuration options:
 /// - [`die_if_link_dies`](Config::die_if_link_dies) - Sets if link deaths
 ///   should be caught.
+/// - [`dead_letter_sink`](Config::dead_letter_sink) - Sets where rejected
+///   messages should be forwarded to.
+/// - [`set_idle_timeout`](Config::set_idle_timeout) - Shuts the process down
+///   after a period of inactivity.
+/// - [`set_strict_decoding`](Config::set_strict_decoding) - Sets if messages
+///   that can't be handled should crash the process.
+/// - [`enable_snapshots`](Config::enable_snapshots) - Periodically persists
+///   the state for crash recovery.
 ///
 /// The `Config` struct can also be used to acquire a self reference with
 /// [`self_ref`](Config::self_ref) to send messages to itself during the
@@ -270,6 +369,107 @@ impl<AP: AbstractProcess> Config<AP> {
         unsafe { host::api::process::die_when_link_dies(die as u32) };
     }
 
+    /// Forwards all messages that can't be handled to `sink`, instead of
+    /// skipping them.
+    ///
+    /// A message is rejected if it fails to deserialize, or if it doesn't
+    /// match any of the [`Handlers`](AbstractProcess::Handlers). The sink
+    /// receives a [`DeadLetter`] containing the raw message and the reason why
+    /// it was rejected.
+    pub fn dead_letter_sink<D>(&self, sink: ProcessRef<D>)
+    where
+        D: MessageHandler<DeadLetter> + 'static,
+        D::Serializer: CanSerialize<DeadLetter>,
+    {
+        dead_letter::set_sink(sink);
+    }
+
+    /// Shuts the process down if no message arrives for the duration of
+    /// `timeout`.
+    ///
+    /// The [`handle_timeout`](AbstractProcess::handle_timeout) and
+    /// [`terminate`](AbstractProcess::terminate) handlers are called before
+    /// the process exits. The process is not considered idle while it holds
+    /// a [`DeferredResponse`] that wasn't sent yet.
+    pub fn set_idle_timeout(&self, timeout: Duration) {
+        lifecycles::set_idle_timeout(timeout);
+    }
+
+    /// If set to `true`, the process panics when it receives a message that
+    /// can't be handled.
+    ///
+    /// A message can't be handled if it fails to deserialize, or if it doesn't
+    /// match any of the [`Handlers`](AbstractProcess::Handlers). By default,
+    /// such a message is skipped and passed to
+    /// [`handle_rejected_message`](AbstractProcess::handle_rejected_message),
+    /// so that a single malformed message doesn't take down a process shared by
+    /// many clients. The sender
+    /// of a skipped request will never receive a response. Messages forwarded
+    /// to a [`dead_letter_sink`](Config::dead_letter_sink) are not affected.
+    ///
+    /// Default value is `false`.
+    pub fn set_strict_decoding(&self, strict: bool) {
+        lifecycles::set_strict_decoding(strict);
+    }
+
+    /// Persists a snapshot of the state to `store` every `interval`.
+    ///
+    /// A snapshot is also taken right before the process shuts down. The
+    /// state is only encoded inside of the process, writing it to the store
+    /// happens in a separate process and doesn't block message handling. If
+    /// snapshots are produced faster than they can be written, only the latest
+    /// one is saved.
+    ///
+    /// Use [`restore_snapshot`](Config::restore_snapshot) inside of `init` to
+    /// resume from the latest snapshot after a restart.
+    ///
+    /// ```
+    /// fn init(config: Config<Self>, _: ()) -> Result<Self, ()> {
+    ///     let store = FileSnapshotStore::new("/data/counter.snapshot");
+    ///     let counter = config.restore_snapshot(&store).ok().flatten();
+    ///     config.enable_snapshots(Duration::from_secs(10), store);
+    ///     Ok(counter.unwrap_or(Counter(0)))
+    /// }
+    /// ```
+    pub fn enable_snapshots<St: SnapshotStore>(&self, interval: Duration, store: St)
+    where
+        AP::State: Snapshot,
+    {
+        persistence::enable::<AP::State, St>(interval, store);
+    }
+
+    /// Returns the latest state snapshot saved in `store`, or `None` if there
+    /// is no snapshot yet.
+    pub fn restore_snapshot<St: SnapshotStore>(&self, store: &St) -> io::Result<Option<AP::State>>
+    where
+        AP::State: Snapshot,
+    {
+        persistence::restore(store)
+    }
+
+    /// Sends `message` to the process every `every`, until the process dies
+    /// or the returned [`IntervalRef`] is canceled.
+    ///
+    /// The messages are sent by a separate process, so a slow handler doesn't
+    /// delay the following ticks. If the handler is slower than `every`, the
+    /// messages queue up in the mailbox.
+    pub fn send_interval<M>(&self, message: M, every: Duration) -> IntervalRef
+    where
+        AP: MessageHandler<M>,
+        AP::Serializer: CanSerialize<M>,
+        M: serde::Serialize + serde::de::DeserializeOwned + Clone + 'static,
+    {
+        let ticker = Process::spawn(
+            (self.self_ref(), message, every),
+            |(process, message, every), mailbox: Mailbox<()>| {
+                crate::time::run_ticker(mailbox, process.process, every, || {
+                    process.send(message.clone())
+                })
+            },
+        );
+        IntervalRef::new(ticker)
+    }
+
     /// Get a reference to the running [`AbstractProcess`].
     pub fn self_ref(&self) -> ProcessRef<AP> {
         let process = unsafe { Process::this() };
@@ -284,11 +484,7 @@ where
     fn handle(state: State<Self>, message: Message);
 }
 
-pub trait RequestHandler<Request>: AbstractProcess
-where
-    Self::Serializer: CanSerialize<Request>,
-    Self::Serializer: CanSerialize<Self::Response>,
-{
+pub trait RequestHandler<Request>: AbstractProcess {
     type Response;
 
     fn handle(state: State<Self>, request: Request) -> Self::Response;
@@ -308,6 +504,22 @@ where
     );
 }
 
+/// Handles requests that are answered with multiple items.
+///
+/// Items are sent back with [`StreamResponder::push`] and the stream is
+/// completed with [`StreamResponder::finish`], or when the responder is
+/// dropped. The caller receives them through the [`ResponseStream`] returned
+/// by [`ProcessRef::subscribe_request`].
+pub trait StreamRequestHandler<Request>: AbstractProcess
+where
+    Self::Serializer: CanSerialize<Request>,
+    Self::Serializer: CanSerialize<StreamItem<Self::Item>>,
+{
+    type Item;
+
+    fn handle(state: State<Self>, request: Request, responder: StreamResponder<Self::Item, Self>);
+}
+
 /// A reference to the state inside handlers.
 pub struct State<'a, AP: AbstractProcess> {
     state: &'a mut AP::State,
@@ -335,11 +547,29 @@ impl<'a, AP: AbstractProcess> DerefMut for State<'a, AP> {
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-#[serde(bound = "")]
 pub struct DeferredResponse<Response, AP: AbstractProcess> {
     tag: Tag,
     return_address: ReturnAddress<Response, AP::Serializer>,
+    // Responses held by the abstract process that received the request are
+    // tracked, so that it isn't considered idle until they are sent.
+    tracked: Cell<bool>,
+}
+
+impl<Response, AP: AbstractProcess> DeferredResponse<Response, AP> {
+    pub(crate) fn new(tag: Tag, return_address: ReturnAddress<Response, AP::Serializer>) -> Self {
+        lifecycles::deferred_started();
+        DeferredResponse {
+            tag,
+            return_address,
+            tracked: Cell::new(true),
+        }
+    }
+
+    fn untrack(&self) {
+        if self.tracked.replace(false) {
+            lifecycles::deferred_finished();
+        }
+    }
 }
 
 impl<Response, AP: AbstractProcess> DeferredResponse<Response, AP>
@@ -347,7 +577,81 @@ where
     AP::Serializer: CanSerialize<Response>,
 {
     pub fn send_response(self, response: Response) {
-        self.return_address.send_response(response, self.tag);
+        self.return_address.process().tag_send(self.tag, response);
+    }
+}
+
+impl<Response, AP: AbstractProcess> DeferredResponse<Response, AP> {
+    /// Turns the deferred response into a [`ResponseSender`] that doesn't
+    /// depend on the type of the abstract process.
+    ///
+    /// The sender can be passed on to any other process, which answers the
+    /// original request with [`ResponseSender::send_response`]. The abstract
+    /// process stops tracking the response, so it can be considered idle
+    /// while the requester is still waiting for an answer.
+    pub fn into_sender(self) -> ResponseSender<Response, AP::Serializer> {
+        self.untrack();
+        ResponseSender {
+            tag: self.tag,
+            process: self.return_address.process(),
+        }
+    }
+}
+
+impl<Response, AP: AbstractProcess> Drop for DeferredResponse<Response, AP> {
+    fn drop(&mut self) {
+        self.untrack();
+    }
+}
+
+impl<Response, AP: AbstractProcess> serde::Serialize for DeferredResponse<Response, AP> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        // Once sent to another process, the response is not tracked anymore.
+        self.untrack();
+        (self.tag, &self.return_address).serialize(serializer)
+    }
+}
+
+impl<'de, Response, AP: AbstractProcess> serde::Deserialize<'de>
+    for DeferredResponse<Response, AP>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let (tag, return_address) = serde::Deserialize::deserialize(deserializer)?;
+        Ok(DeferredResponse {
+            tag,
+            return_address,
+            tracked: Cell::new(false),
+        })
+    }
+}
+
+/// Answers a deferred request from any process.
+///
+/// Created with [`DeferredResponse::into_sender`]. There is only one sender
+/// per request and sending the response consumes it, so the requester keeps
+/// waiting until whoever holds the sender responds (or its timeout expires).
+/// If the sender is dropped without responding, the requester never gets an
+/// answer.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(bound = "")]
+pub struct ResponseSender<Response, Serializer> {
+    tag: Tag,
+    process: Process<Response, Serializer>,
+}
+
+impl<Response, Serializer> ResponseSender<Response, Serializer>
+where
+    Serializer: CanSerialize<Response>,
+{
+    /// Sends the response to the process that made the request.
+    pub fn send_response(self, response: Response) {
+        self.process.tag_send(self.tag, response);
     }
 }
 
@@ -357,6 +661,14 @@ where
 /// messages of different types, as long as the traits
 /// `MessageHandler<Message>`, `RequestHandler<Request>` or
 /// `DeferredRequestHandler<Request>` are implemented for `T`.
+///
+/// A `ProcessRef<T>` is obtained from starting `T`, [`State::self_ref`], or a
+/// typed [`lookup`](ProcessRef::lookup) of a name registered by `T`. A
+/// `Process` with the same serializer can be converted into a `ProcessRef<T>`
+/// with [`from_process`](ProcessRef::from_process), which asks the process if
+/// it's running `T`. The other way around, every `ProcessRef<T>`
+/// converts into a `Process<(), T::Serializer>` that can be used to link,
+/// monitor or kill the process.
 #[derive(serde::Serialize, serde::Deserialize)]
 #[serde(bound = "")]
 pub struct ProcessRef<T>
@@ -405,10 +717,59 @@ where
         }
     }
 
+    /// Returns a `ProcessRef<T>` to `process`, if it's running the abstract
+    /// process `T`.
+    ///
+    /// The process is asked for the abstract process it's running, and this
+    /// call blocks until it answers or `timeout` expires. A process that isn't
+    /// an [`AbstractProcess`] never answers, and the question stays in its
+    /// mailbox. It's received by the next untagged `receive` of that process,
+    /// and fails to deserialize as any message the process expects. Only use
+    /// this on processes that are known to be abstract processes.
+    pub fn from_process<M>(
+        process: Process<M, T::Serializer>,
+        timeout: Duration,
+    ) -> Result<Self, ConversionError> {
+        if process.node_id() == host::node_id() && !process.is_alive() {
+            return Err(ConversionError::NoAnswer);
+        }
+        let return_address = ReturnAddress::from_self();
+        let message = SignatureMessage(return_address);
+        let send_tag = AbstractProcessTag::from_u6(SIGNATURE_HANDLER);
+        let (receive_tag, _) = AbstractProcessTag::extract_u6_data(send_tag);
+        let signature = unsafe {
+            let process: Process<SignatureMessage, Bincode> =
+                Process::new(process.node_id(), process.id());
+            match process.tag_send_receive(send_tag, receive_tag, message, Some(timeout)) {
+                MailboxResult::Ok(MessageSignal::Message(signature)) => signature,
+                MailboxResult::Err(MailboxError::TimedOut) => {
+                    return Err(ConversionError::NoAnswer)
+                }
+                _ => unreachable!("send_receive should panic in case of other errors"),
+            }
+        };
+        if signature == self::signature::<T>() {
+            Ok(unsafe { Self::new(process.node_id(), process.id()) })
+        } else {
+            Err(ConversionError::Mismatch(signature))
+        }
+    }
+
     /// Registers process under `name`.
     pub fn register<N: ProcessName>(&self, name: &N) {
-        let name = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
-        unsafe { host::api::registry::put(name.as_ptr(), name.len(), self.node_id(), self.id()) };
+        let key = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
+        unsafe { host::api::registry::put(key.as_ptr(), key.len(), self.node_id(), self.id()) };
+        crate::registry::record(name.process_name(), key, self.node_id(), self.id());
+    }
+
+    /// Registers process under `name` for the duration of `ttl`.
+    ///
+    /// The name is removed from the registry if the returned [`Lease`] isn't
+    /// refreshed within `ttl`, or as soon as the process dies. See the
+    /// [`registry`](crate::registry) module for details.
+    pub fn register_with_lease<N: ProcessName>(&self, name: &N, ttl: Duration) -> Lease {
+        let key = process_name::<T, T::Serializer>(ProcessType::ProcessRef, name.process_name());
+        Lease::new(name.process_name(), key, self.node_id(), self.id(), ttl)
     }
 
     /// Returns `true` for processes on the local node that are running.
@@ -439,11 +800,105 @@ where
         unsafe { host::api::process::unlink(self.process.id()) };
     }
 
+    /// Starts monitoring the process from the one currently running.
+    ///
+    /// When the process exits, a [`ProcessDown`] message is sent to the
+    /// caller with the [`tag`](MonitorRef::tag) of the returned monitor.
+    /// Unlike [`link`](Self::link), the caller doesn't die if the process
+    /// fails.
+    ///
+    /// Panics if called on a remote process.
+    #[track_caller]
+    pub fn monitor(&self) -> MonitorRef {
+        assert_eq!(
+            self.process.node_id(),
+            host::node_id(),
+            "monitor() can only be used with local processes"
+        );
+        MonitorRef::new(self.process.id())
+    }
+
+    /// Blocks until the process exits and returns how it exited.
+    ///
+    /// Panics if called on a remote process.
+    #[track_caller]
+    pub fn wait(&self) -> ExitReason {
+        let monitor = self.monitor();
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<ProcessDown> = unsafe { Mailbox::new() };
+        mailbox.tag_receive(&[monitor.tag()]).reason
+    }
+
     /// Kill process
     pub fn kill(&self) {
         unsafe { host::api::process::kill(self.process.id()) };
     }
 
+    /// Sends a signal to the process.
+    ///
+    /// Signals are handled by [`AbstractProcess::handle_signal`] and don't
+    /// need a message handler. Unlike [`kill`](Self::kill), they only ask the
+    /// process to do something, e.g. to pause its work.
+    pub fn signal(&self, signal: ProcessSignal) {
+        let tag = AbstractProcessTag::from_u6(SIGNAL_HANDLER);
+        let process: Process<ProcessSignal, Bincode> =
+            unsafe { Process::new(self.node_id(), self.id()) };
+        process.tag_send(tag, signal);
+    }
+
+    /// Returns a snapshot of the process' current state.
+    ///
+    /// If the process is alive, this call will block until the process handles
+    /// the snapshot request. If it dies before that, the snapshot shows it as
+    /// not alive.
+    ///
+    /// Panics if called on a remote process.
+    #[track_caller]
+    pub fn snapshot(&self) -> ProcessSnapshot {
+        // Names registered by the caller are only known to the caller.
+        let names = crate::registry::names_of(self.node_id(), self.id());
+        let dead = |names| ProcessSnapshot {
+            id: self.id(),
+            node_id: self.node_id(),
+            names,
+            ..ProcessSnapshot::default()
+        };
+        if !self.is_alive() {
+            return dead(names);
+        }
+        // The process can still die before it handles the request.
+        let monitor = MonitorRef::new(self.id());
+        let return_address = ReturnAddress::from_self();
+        let message = SnapshotMessage(return_address);
+        let send_tag = AbstractProcessTag::from_u6(SNAPSHOT_HANDLER);
+        let (receive_tag, _) = AbstractProcessTag::extract_u6_data(send_tag);
+        let process: Process<SnapshotMessage, Bincode> =
+            unsafe { Process::new(self.node_id(), self.id()) };
+        process.tag_send(send_tag, message);
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<ProcessSnapshot, Bincode> = unsafe { Mailbox::new() };
+        // A timeout of `u64::MAX` milliseconds waits forever.
+        let result = mailbox.tag_receive_timeout(
+            &[receive_tag, monitor.tag()],
+            Duration::from_millis(u64::MAX),
+        );
+        let tag = Tag::from(unsafe { host::api::message::get_tag() });
+        monitor.demonitor();
+        if tag == monitor.tag() {
+            return dead(names);
+        }
+        let mut snapshot = match result {
+            Ok(snapshot) => snapshot,
+            Err(err) => panic!("could not receive snapshot: {err}"),
+        };
+        for name in names {
+            if !snapshot.names.contains(&name) {
+                snapshot.names.push(name);
+            }
+        }
+        snapshot
+    }
+
     /// Shuts the [`AbstractProcess`] down.
     #[track_caller]
     pub fn shutdown(&self)
@@ -456,6 +911,21 @@ where
         self.shutdown_timeout(None).unwrap();
     }
 
+    /// Shuts the [`AbstractProcess`] down, passing `reason` to
+    /// [`terminate_with`](AbstractProcess::terminate_with).
+    ///
+    /// The reason is encoded with [`Bincode`] and can be read back with
+    /// [`ShutdownReason::decode`].
+    #[track_caller]
+    pub fn shutdown_with<R: serde::Serialize>(&self, reason: R)
+    where
+        T::Serializer: CanSerialize<ShutdownMessage<T::Serializer>>,
+        T::Serializer: CanSerialize<()>,
+    {
+        self.shutdown_(Some(ShutdownReason::new(&reason)), None)
+            .unwrap();
+    }
+
     /// Shuts the [`AbstractProcess`] down.
     ///
     /// If a timeout is specified the function will only block for the timeout
@@ -467,9 +937,22 @@ where
         // return value.
         T::Serializer: CanSerialize<ShutdownMessage<T::Serializer>>,
         T::Serializer: CanSerialize<()>,
+    {
+        self.shutdown_(None, timeout)
+    }
+
+    #[track_caller]
+    fn shutdown_(
+        &self,
+        reason: Option<ShutdownReason>,
+        timeout: Option<Duration>,
+    ) -> Result<(), Timeout>
+    where
+        T::Serializer: CanSerialize<ShutdownMessage<T::Serializer>>,
+        T::Serializer: CanSerialize<()>,
     {
         let return_address = ReturnAddress::from_self();
-        let message = ShutdownMessage(return_address);
+        let message = ShutdownMessage(return_address, reason);
         let send_tag = AbstractProcessTag::from_u6(SHUTDOWN_HANDLER);
         let (receive_tag, _) = AbstractProcessTag::extract_u6_data(send_tag);
         unsafe {
@@ -497,6 +980,18 @@ where
         process.tag_send(tag, message);
     }
 
+    /// Send message to the process after the specified duration has passed.
+    ///
+    /// This is the same as `process.with_delay(duration).send(message)`. The
+    /// returned [`TimerRef`] can be used to cancel the message.
+    #[track_caller]
+    pub fn send_after<M: 'static>(&self, message: M, duration: Duration) -> TimerRef
+    where
+        T::Serializer: CanSerialize<M>,
+    {
+        self.delayed_send(message, duration)
+    }
+
     /// Send message to the process after the specified duration has passed.
     #[track_caller]
     pub(crate) fn delayed_send<M: 'static>(&self, message: M, duration: Duration) -> TimerRef
@@ -537,15 +1032,67 @@ where
         T::Serializer: CanSerialize<R>,
         T::Serializer: CanSerialize<T::Response>,
         T::Serializer: CanSerialize<RequestMessage<R, T::Response, T::Serializer>>,
+    {
+        self.request_handler_timeout::<Request<R>, T::Serializer, R>(request, timeout)
+    }
+
+    /// Make a request to a handler that uses the serializer `S`.
+    ///
+    /// The handler needs to be listed as
+    /// [`RequestWith<R, S>`](handlers::RequestWith) in the
+    /// [`AbstractProcess::Handlers`], and the response is sent back with the
+    /// same serializer.
+    #[track_caller]
+    pub fn request_with<S, R: 'static>(&self, request: R) -> T::Response
+    where
+        T: RequestHandler<R>,
+        S: CanSerialize<R> + 'static,
+        S: CanSerialize<T::Response>,
+        S: CanSerialize<RequestMessage<R, T::Response, S>>,
+    {
+        self.request_with_timeout::<S, R>(request, None).unwrap()
+    }
+
+    /// Make a request to a handler that uses the serializer `S`.
+    ///
+    /// If a timeout is specified the function will only block for the timeout
+    /// period before returning `Err(Timeout)`.
+    #[track_caller]
+    pub(crate) fn request_with_timeout<S, R: 'static>(
+        &self,
+        request: R,
+        timeout: Option<Duration>,
+    ) -> Result<T::Response, Timeout>
+    where
+        T: RequestHandler<R>,
+        S: CanSerialize<R> + 'static,
+        S: CanSerialize<T::Response>,
+        S: CanSerialize<RequestMessage<R, T::Response, S>>,
+    {
+        self.request_handler_timeout::<RequestWith<R, S>, S, R>(request, timeout)
+    }
+
+    /// Sends the request to the handler `H` and waits for the response.
+    #[track_caller]
+    fn request_handler_timeout<H: 'static, S, R: 'static>(
+        &self,
+        request: R,
+        timeout: Option<Duration>,
+    ) -> Result<T::Response, Timeout>
+    where
+        T: RequestHandler<R>,
+        S: CanSerialize<R>,
+        S: CanSerialize<T::Response>,
+        S: CanSerialize<RequestMessage<R, T::Response, S>>,
     {
         let return_address = ReturnAddress::from_self();
         let message = RequestMessage(request, return_address);
-        let handler_id = T::Handlers::handler_id::<Request<R>>();
+        let handler_id = T::Handlers::handler_id::<H>();
         let send_tag = AbstractProcessTag::from_u6(handler_id);
         let (receive_tag, _) = AbstractProcessTag::extract_u6_data(send_tag);
         unsafe {
             // Cast into the right type for sending.
-            let process: Process<RequestMessage<R, T::Response, T::Serializer>, T::Serializer> =
+            let process: Process<RequestMessage<R, T::Response, S>, S> =
                 mem::transmute(self.process);
             match process.tag_send_receive(send_tag, receive_tag, message, timeout) {
                 MailboxResult::Ok(MessageSignal::Message(message)) => Ok(message),
@@ -555,6 +1102,30 @@ where
         }
     }
 
+    /// Make a request to the process without waiting on the response.
+    ///
+    /// The response is received later through the returned
+    /// [`PendingReply`], so the caller can keep handling other messages or
+    /// make requests to other processes in the meantime.
+    #[track_caller]
+    pub fn request_async<R: 'static>(&self, request: R) -> PendingReply<T::Response, T::Serializer>
+    where
+        T: RequestHandler<R>,
+        T::Serializer: CanSerialize<R>,
+        T::Serializer: CanSerialize<T::Response>,
+        T::Serializer: CanSerialize<RequestMessage<R, T::Response, T::Serializer>>,
+    {
+        let return_address = ReturnAddress::from_self();
+        let message = RequestMessage(request, return_address);
+        let handler_id = T::Handlers::handler_id::<Request<R>>();
+        let send_tag = AbstractProcessTag::from_u6(handler_id);
+        let (receive_tag, _) = AbstractProcessTag::extract_u6_data(send_tag);
+        // Cast into the right type for sending.
+        let process: Process<_, T::Serializer> = unsafe { mem::transmute(self.process) };
+        process.tag_send(send_tag, message);
+        PendingReply::new(receive_tag)
+    }
+
     /// Make a deferred request to the process.
     #[track_caller]
     pub fn deferred_request<R: 'static>(&self, request: R) -> T::Response
@@ -600,6 +1171,33 @@ where
         }
     }
 
+    /// Make a request to the process that is answered with a stream of items.
+    ///
+    /// The call doesn't block, items are received by iterating over the
+    /// returned [`ResponseStream`]. Iteration finishes when the handler
+    /// completes the stream.
+    #[track_caller]
+    pub fn subscribe_request<R: 'static>(
+        &self,
+        request: R,
+    ) -> ResponseStream<T::Item, T::Serializer>
+    where
+        T: StreamRequestHandler<R>,
+        T::Serializer: CanSerialize<R>,
+        T::Serializer: CanSerialize<StreamItem<T::Item>>,
+        T::Serializer: CanSerialize<RequestMessage<R, StreamItem<T::Item>, T::Serializer>>,
+    {
+        let return_address = ReturnAddress::from_self();
+        let message = RequestMessage(request, return_address);
+        let handler_id = T::Handlers::handler_id::<StreamRequest<R>>();
+        let send_tag = AbstractProcessTag::from_u6(handler_id);
+        let (receive_tag, _) = AbstractProcessTag::extract_u6_data(send_tag);
+        // Cast into the right type for sending.
+        let process: Process<_, T::Serializer> = unsafe { mem::transmute(self.process) };
+        process.tag_send(send_tag, message);
+        ResponseStream::new(receive_tag)
+    }
+
     /// Set a timeout on the next action performed on this process.
     ///
     /// Timeouts affect [`ProcessRef::shutdown`], [`ProcessRef::request`] and
@@ -652,6 +1250,170 @@ where
 
 impl<T> Eq for ProcessRef<T> where T: AbstractProcess {}
 
+impl<T: AbstractProcess> From<ProcessRef<T>> for Process<(), T::Serializer> {
+    fn from(process_ref: ProcessRef<T>) -> Self {
+        process_ref.process
+    }
+}
+
+/// Error returned if a [`Process`] can't be converted into a [`ProcessRef`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// The process didn't answer in time. It's not running, or it's not an
+    /// [`AbstractProcess`].
+    NoAnswer,
+    /// The process is running a different [`AbstractProcess`], with the
+    /// contained signature.
+    Mismatch(String),
+}
+
+/// Identifies the type and serializer of an [`AbstractProcess`].
+pub(crate) fn signature<T: AbstractProcess>() -> String {
+    format!("{}/{}", type_name::<T>(), type_name::<T::Serializer>())
+}
+
+/// Sends `message` to all `targets`.
+///
+/// The message is only serialized once and the encoded bytes are copied into
+/// a new message for each target, which is cheaper than calling
+/// [`send`](ProcessRef::send) in a loop for larger messages.
+///
+/// Messages containing resources, like a [`TcpStream`](crate::net::TcpStream),
+/// can't be shared between multiple receivers and must not be broadcast.
+pub fn broadcast<M: 'static, T>(targets: &[ProcessRef<T>], message: M)
+where
+    T: AbstractProcess,
+    T::Serializer: CanSerialize<M>,
+{
+    if targets.is_empty() {
+        return;
+    }
+    let handler_id = T::Handlers::handler_id::<Message<M>>();
+    let tag = AbstractProcessTag::from_u6(handler_id);
+
+    // Encode the message once and read the bytes back out of the message buffer.
+    unsafe { host::api::message::create_data(tag.id(), 0) };
+    T::Serializer::encode(&message).unwrap();
+    let mut data = vec![0; unsafe { host::api::message::data_size() } as usize];
+    unsafe {
+        host::api::message::seek_data(0);
+        host::api::message::read_data(data.as_mut_ptr(), data.len());
+    }
+
+    for target in targets {
+        unsafe {
+            host::api::message::create_data(tag.id(), data.len() as u64);
+            host::api::message::write_data(data.as_ptr(), data.len());
+        }
+        host::send(target.node_id(), target.id());
+    }
+}
+
+/// Measurements of a single message handled by an [`AbstractProcess`].
+///
+/// Passed to [`AbstractProcess::on_message`].
+#[derive(Debug, Clone, Copy)]
+pub struct MessageMetric {
+    /// Type name of the handler, e.g.
+    /// `lunatic::ap::handlers::Request<app::Count>`.
+    pub handler: &'static str,
+    /// Size of the encoded message in bytes.
+    pub size: usize,
+    /// Time it took to decode and handle the message.
+    pub duration: Duration,
+}
+
+/// A signal sent to an [`AbstractProcess`] with [`ProcessRef::signal`].
+///
+/// Signals are independent of the message types and serializer of the
+/// process, so any [`AbstractProcess`] can receive them.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProcessSignal {
+    /// Asks the process to stop what it's doing, without shutting down.
+    Interrupt,
+    /// Asks the process to pause its work until it's resumed.
+    Pause,
+    /// Asks a paused process to continue its work.
+    Resume,
+    /// An application specific signal.
+    Custom(u32),
+}
+
+/// The reason of a shutdown requested with [`ProcessRef::shutdown_with`].
+///
+/// The reason is always encoded with [`Bincode`], independent of the
+/// serializer used by the process.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReason(Vec<u8>);
+
+impl ShutdownReason {
+    pub(crate) fn new<R: serde::Serialize>(reason: &R) -> Self {
+        ShutdownReason(bincode::serialize(reason).expect("failed to encode the shutdown reason"))
+    }
+
+    /// Decodes the reason as `R`.
+    ///
+    /// Returns `None` if the reason isn't a valid `R`.
+    pub fn decode<R: serde::de::DeserializeOwned>(&self) -> Option<R> {
+        bincode::deserialize(&self.0).ok()
+    }
+}
+
+/// The final value of an [`AbstractProcess`], set with [`set_exit_value`].
+///
+/// The value is always encoded with [`Bincode`], independent of the
+/// serializer used by the process.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExitValue(Vec<u8>);
+
+impl ExitValue {
+    pub(crate) fn new<V: serde::Serialize>(value: &V) -> Self {
+        ExitValue(bincode::serialize(value).expect("failed to encode the exit value"))
+    }
+
+    /// Decodes the value as `V`.
+    ///
+    /// Returns `None` if the value isn't a valid `V`.
+    pub fn decode<V: serde::de::DeserializeOwned>(&self) -> Option<V> {
+        bincode::deserialize(&self.0).ok()
+    }
+}
+
+/// Sets the final value of the currently running [`AbstractProcess`].
+///
+/// Once the process exits normally, because it was shut down or was idle for
+/// too long, the value is sent to the process that started it and handled by
+/// [`AbstractProcess::handle_exit_value`]. Supervisors keep the values of
+/// children that are not restarted, see
+/// [`ProcessRef::exit_value`].
+///
+/// Calling it again replaces the previous value.
+pub fn set_exit_value<V: serde::Serialize>(value: &V) {
+    lifecycles::set_exit_value(ExitValue::new(value));
+}
+
+/// A snapshot of a process' state, returned by [`ProcessRef::snapshot`].
+///
+/// The runtime doesn't expose the links, mailbox length, memory or fuel usage
+/// of a process, so they are not part of the snapshot.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessSnapshot {
+    /// The process ID.
+    pub id: u64,
+    /// The node ID.
+    pub node_id: u64,
+    /// `true` if the process was running at the time of the snapshot.
+    pub alive: bool,
+    /// Names the process is registered under.
+    ///
+    /// The registry can't be searched by process, so only names registered by
+    /// the process itself or by the caller of [`ProcessRef::snapshot`] are
+    /// included.
+    pub names: Vec<String>,
+    /// Time since the process was started, or `None` if it's not running.
+    pub uptime: Option<Duration>,
+}
+
 /// Result of [`AbstractProcess::start`].
 #[derive(serde::Serialize, serde::Deserialize)]
 pub enum StartupError<AP: AbstractProcess> {
@@ -706,3 +1468,55 @@ where
 }
 
 impl<AP: AbstractProcess> Eq for StartupError<AP> where AP::StartupError: Eq {}
+
+/// An error that is transmitted as its `Display` and `Debug` output.
+///
+/// Errors returned from an [`AbstractProcess`] need to be serializable. This
+/// wrapper can be used for error types that aren't, e.g. as
+/// [`AbstractProcess::StartupError`]. Any [`std::error::Error`] can be
+/// converted into it, so the `?` operator works inside of `init`.
+///
+/// ```
+/// fn init(_: Config<Self>, path: String) -> Result<Self, StringifiedError> {
+///     let file = std::fs::File::open(path)?;
+///     Ok(Self { file })
+/// }
+/// ```
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct StringifiedError {
+    display: String,
+    debug: String,
+}
+
+impl StringifiedError {
+    /// Captures the `Display` and `Debug` output of `error`.
+    pub fn new<E: Display + Debug + ?Sized>(error: &E) -> Self {
+        StringifiedError {
+            display: error.to_string(),
+            debug: format!("{error:?}"),
+        }
+    }
+
+    /// Returns the `Debug` output of the original error.
+    pub fn debug(&self) -> &str {
+        &self.debug
+    }
+}
+
+impl<E: std::error::Error> From<E> for StringifiedError {
+    fn from(error: E) -> Self {
+        StringifiedError::new(&error)
+    }
+}
+
+impl Display for StringifiedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.display)
+    }
+}
+
+impl Debug for StringifiedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.debug)
+    }
+}
@@ -0,0 +1,119 @@
+//! Monitors notify an observer about the exit of a process.
+//!
+//! Unlike links, monitors are one-directional and never kill the observer.
+//! The runtime only reports the death of a monitored process, so each monitor
+//! is kept by a separate watcher process. The watcher is also linked to the
+//! target, which tells it if the target failed.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{host, Mailbox, MessageSignal, Process, Signal, Tag};
+
+/// How a process exited.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExitReason {
+    /// The process finished normally.
+    Normal,
+    /// The process panicked or was killed.
+    Failed,
+    /// The process didn't exist when the monitor was created.
+    NoProcess,
+}
+
+/// Message sent to the monitoring process when a monitored process exits.
+///
+/// It's sent with the [`tag`](MonitorRef::tag) of the monitor and the default
+/// serializer, so it can be received from a `Mailbox<ProcessDown>`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProcessDown {
+    /// The monitor that fired.
+    pub monitor: MonitorRef,
+    /// How the monitored process exited.
+    pub reason: ExitReason,
+}
+
+#[derive(Serialize, Deserialize)]
+enum WatcherMessage {
+    Demonitor,
+}
+
+/// Reference to a monitor created with
+/// [`ProcessRef::monitor`](super::ProcessRef::monitor).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MonitorRef {
+    watcher: Process<WatcherMessage>,
+    tag: Tag,
+}
+
+impl MonitorRef {
+    /// Starts monitoring the local process `process_id` from the current
+    /// process.
+    pub(crate) fn new(process_id: u64) -> Self {
+        let observer = unsafe { Process::<ProcessDown>::this() };
+        let tag = Tag::new();
+        let watcher = Process::spawn((observer, process_id, tag), watch);
+        MonitorRef { watcher, tag }
+    }
+
+    /// Returns the tag that the [`ProcessDown`] message is sent with.
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    /// Cancels the monitor.
+    ///
+    /// A [`ProcessDown`] message that was sent before the monitor got
+    /// cancelled can still arrive.
+    pub fn demonitor(self) {
+        self.watcher.send(WatcherMessage::Demonitor);
+    }
+}
+
+/// Waits for the target to exit and notifies the observer.
+///
+/// The watcher gives up if the observer dies first.
+fn watch(
+    (observer, target, tag): (Process<ProcessDown>, u64, Tag),
+    mailbox: Mailbox<WatcherMessage>,
+) {
+    let monitor = MonitorRef {
+        watcher: mailbox.this(),
+        tag,
+    };
+    if unsafe { host::api::process::exists(target) } == 0 {
+        let reason = ExitReason::NoProcess;
+        observer.tag_send(tag, ProcessDown { monitor, reason });
+        return;
+    }
+
+    let mailbox = mailbox.monitorable();
+    mailbox.monitor(observer);
+    mailbox.monitor(unsafe { Process::<()>::new(host::node_id(), target) });
+    let mailbox = mailbox.catch_link_failure();
+    // A link only reports failures, a normal exit doesn't trigger it.
+    let link_tag = Tag::new();
+    unsafe { host::api::process::link(link_tag.id(), target) };
+
+    let reason = match mailbox.receive() {
+        MessageSignal::Message(WatcherMessage::Demonitor) => {
+            unsafe { host::api::process::unlink(target) };
+            return;
+        }
+        MessageSignal::Signal(Signal::LinkDied(_)) => ExitReason::Failed,
+        MessageSignal::Signal(Signal::ProcessDied(id)) if id == target => {
+            // Check if the link reported a failure right before.
+            match mailbox.tag_receive_timeout(&[link_tag], Duration::ZERO) {
+                Ok(MessageSignal::Signal(Signal::LinkDied(_))) => ExitReason::Failed,
+                _ => ExitReason::Normal,
+            }
+        }
+        MessageSignal::Signal(Signal::ProcessDied(_)) => {
+            // The observer died.
+            unsafe { host::api::process::unlink(target) };
+            return;
+        }
+    };
+    observer.tag_send(tag, ProcessDown { monitor, reason });
+}
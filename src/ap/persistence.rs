@@ -0,0 +1,174 @@
+//! Periodic snapshots of the [`AbstractProcess`](super::AbstractProcess)
+//! state for crash recovery.
+
+use std::cell::RefCell;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{process_local, Mailbox, MessageSignal, Process};
+
+/// State of an [`AbstractProcess`](super::AbstractProcess) that can be persisted with
+/// [`Config::enable_snapshots`](super::Config::enable_snapshots).
+///
+/// Snapshots are encoded with bincode.
+pub trait Snapshot: Serialize + DeserializeOwned {}
+
+/// Storage for the latest snapshot of one
+/// [`AbstractProcess`](super::AbstractProcess).
+///
+/// Snapshots are saved from a separate process, so the store needs to be
+/// serializable. Resources like database connections should be opened inside
+/// of [`save`](SnapshotStore::save).
+pub trait SnapshotStore: Serialize + DeserializeOwned + 'static {
+    /// Replaces the stored snapshot with `snapshot`.
+    fn save(&self, snapshot: &[u8]) -> io::Result<()>;
+
+    /// Returns the stored snapshot, or `None` if there is none.
+    fn load(&self) -> io::Result<Option<Vec<u8>>>;
+
+    /// Is called with the error returned by [`save`](SnapshotStore::save).
+    ///
+    /// The failed snapshot is dropped and the next one is saved as usual.
+    /// Errors are ignored by default.
+    fn save_failed(&self, _err: io::Error) {}
+}
+
+/// Stores snapshots in a file.
+///
+/// The file is replaced atomically, so a crash during a save never leaves a
+/// partially written snapshot behind. The directory of the file needs to be
+/// accessible to the process.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileSnapshotStore {
+    path: PathBuf,
+}
+
+impl FileSnapshotStore {
+    /// Creates a store that keeps the snapshot at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileSnapshotStore { path: path.into() }
+    }
+}
+
+impl SnapshotStore for FileSnapshotStore {
+    fn save(&self, snapshot: &[u8]) -> io::Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, snapshot)?;
+        std::fs::rename(tmp, &self.path)
+    }
+
+    fn load(&self) -> io::Result<Option<Vec<u8>>> {
+        match std::fs::read(&self.path) {
+            Ok(snapshot) => Ok(Some(snapshot)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+struct Snapshots {
+    interval: Duration,
+    next: Instant,
+    writer: Process<Vec<u8>>,
+    // Encodes the state, which is passed as a type-erased pointer.
+    encode: fn(*const ()) -> Vec<u8>,
+}
+
+process_local! {
+    static SNAPSHOTS: RefCell<Option<Snapshots>> = RefCell::new(None);
+}
+
+/// Starts taking snapshots of the state every `interval`.
+///
+/// Must be called from the abstract process that owns the state of type `S`.
+pub(crate) fn enable<S: Snapshot, St: SnapshotStore>(interval: Duration, store: St) {
+    let owner = unsafe { Process::<()>::this() };
+    let writer = Process::spawn((store, owner), writer::<St>);
+    SNAPSHOTS.set(Some(Snapshots {
+        interval,
+        next: Instant::now() + interval,
+        writer,
+        encode: encode::<S>,
+    }));
+}
+
+/// Loads the latest snapshot from `store`.
+pub(crate) fn restore<S: Snapshot, St: SnapshotStore>(store: &St) -> io::Result<Option<S>> {
+    match store.load()? {
+        Some(snapshot) => bincode::deserialize(&snapshot)
+            .map(Some)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the time until the next snapshot is due, if snapshots are enabled.
+pub(crate) fn until_due() -> Option<Duration> {
+    SNAPSHOTS.with_borrow(|snapshots| {
+        snapshots
+            .as_ref()
+            .map(|snapshots| snapshots.next.saturating_duration_since(Instant::now()))
+    })
+}
+
+/// Takes a snapshot of `state` if one is due.
+pub(crate) fn take_if_due<State>(state: &State) {
+    if until_due() == Some(Duration::ZERO) {
+        take(state);
+    }
+}
+
+/// Takes a snapshot of `state` if snapshots are enabled.
+///
+/// Only the encoding happens in the current process, the snapshot is written
+/// to the store by a separate process.
+pub(crate) fn take<State>(state: &State) {
+    SNAPSHOTS.with_borrow_mut(|mut snapshots| {
+        if let Some(snapshots) = snapshots.as_mut() {
+            // `enable` was called by the same abstract process with its state type.
+            let snapshot = (snapshots.encode)(state as *const State as *const ());
+            snapshots.writer.send(snapshot);
+            snapshots.next = Instant::now() + snapshots.interval;
+        }
+    });
+}
+
+fn encode<S: Snapshot>(state: *const ()) -> Vec<u8> {
+    let state = unsafe { &*(state as *const S) };
+    bincode::serialize(state).unwrap()
+}
+
+/// Saves snapshots until the owning process dies.
+///
+/// Only the latest of multiple queued snapshots is saved.
+fn writer<St: SnapshotStore>((store, owner): (St, Process<()>), mailbox: Mailbox<Vec<u8>>) {
+    let mailbox = mailbox.monitorable();
+    mailbox.monitor(owner);
+    loop {
+        let mut snapshot = match mailbox.receive() {
+            MessageSignal::Message(snapshot) => snapshot,
+            MessageSignal::Signal(_) => return,
+        };
+        let mut owner_died = false;
+        loop {
+            match mailbox.receive_timeout(Duration::ZERO) {
+                Ok(MessageSignal::Message(newer)) => snapshot = newer,
+                Ok(MessageSignal::Signal(_)) => {
+                    owner_died = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        if let Err(err) = store.save(&snapshot) {
+            store.save_failed(err);
+        }
+        if owner_died {
+            return;
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Replies to requests that were sent without waiting on them.
+
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use crate::serializer::CanSerialize;
+use crate::time::Timeout;
+use crate::{Mailbox, MailboxError, Tag};
+
+/// The reply to a request made with
+/// [`request_async`](super::ProcessRef::request_async).
+///
+/// The reply stays in the mailbox of the requesting process until it's
+/// received, so other messages can be handled while the request is still
+/// being processed. If the `PendingReply` is dropped before the reply is
+/// received, the reply will also stay in the mailbox.
+pub struct PendingReply<Response, S>
+where
+    S: CanSerialize<Response>,
+{
+    tag: Tag,
+    phantom: PhantomData<(Response, S)>,
+}
+
+impl<Response, S> PendingReply<Response, S>
+where
+    S: CanSerialize<Response>,
+{
+    pub(crate) fn new(tag: Tag) -> Self {
+        PendingReply {
+            tag,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Blocks until the reply arrives.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the reply can't be deserialized.
+    #[track_caller]
+    pub fn recv(self) -> Response {
+        self.mailbox().tag_receive(&[self.tag])
+    }
+
+    /// Waits for the reply only for the duration of `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the reply can't be deserialized.
+    #[track_caller]
+    pub fn recv_timeout(self, timeout: Duration) -> Result<Response, Timeout> {
+        self.receive_timeout(timeout)
+    }
+
+    /// Returns the reply if it already arrived, without blocking.
+    ///
+    /// Once the reply was returned, it's removed from the mailbox and later
+    /// calls will not find it anymore.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the reply can't be deserialized.
+    #[track_caller]
+    pub fn try_recv(&self) -> Option<Response> {
+        self.receive_timeout(Duration::ZERO).ok()
+    }
+
+    #[track_caller]
+    fn receive_timeout(&self, timeout: Duration) -> Result<Response, Timeout> {
+        match self.mailbox().tag_receive_timeout(&[self.tag], timeout) {
+            Ok(response) => Ok(response),
+            Err(MailboxError::TimedOut) => Err(Timeout),
+            Err(MailboxError::DeserializationFailed(err)) => {
+                panic!("Failed to deserialize reply: {err}")
+            }
+            // Only messages with the reply tag are received and no link uses
+            // it.
+            Err(MailboxError::LinkDied(tag)) => unreachable!("link died with reply tag {tag:?}"),
+            Err(MailboxError::ProcessDied(id)) => unreachable!("process {id} died with reply tag"),
+        }
+    }
+
+    fn mailbox(&self) -> Mailbox<Response, S> {
+        unsafe { Mailbox::new() }
+    }
+}
@@ -0,0 +1,185 @@
+//! Requests that are answered with a stream of responses.
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::AbstractProcess;
+use crate::serializer::CanSerialize;
+use crate::time::Timeout;
+use crate::{host, Mailbox, MailboxError, Process, Tag};
+
+/// A single message of a response stream.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum StreamItem<T> {
+    /// The next item of the stream.
+    Item(T),
+    /// The stream is finished and no more items will follow.
+    End,
+}
+
+/// Used by [`StreamRequestHandler`](super::StreamRequestHandler)s to send
+/// items back to the subscriber.
+///
+/// The stream is completed by calling [`finish`](Self::finish), or by dropping
+/// the responder. Like [`DeferredResponse`](super::DeferredResponse), the
+/// responder can be sent to another process that produces the items.
+///
+/// Pushing items never blocks. There is no backpressure, items that the
+/// subscriber doesn't consume fast enough are queued in its mailbox.
+pub struct StreamResponder<Item, AP: AbstractProcess>
+where
+    AP::Serializer: CanSerialize<StreamItem<Item>>,
+{
+    tag: Tag,
+    subscriber: Process<StreamItem<Item>, AP::Serializer>,
+    // If the responder was serialized and sent to another process, the stream
+    // is not finished when it's dropped.
+    consumed: Cell<bool>,
+}
+
+impl<Item, AP: AbstractProcess> StreamResponder<Item, AP>
+where
+    AP::Serializer: CanSerialize<StreamItem<Item>>,
+{
+    pub(crate) fn new(tag: Tag, subscriber: Process<StreamItem<Item>, AP::Serializer>) -> Self {
+        StreamResponder {
+            tag,
+            subscriber,
+            consumed: Cell::new(false),
+        }
+    }
+
+    /// Sends the next item to the subscriber.
+    pub fn push(&self, item: Item) {
+        self.subscriber.tag_send(self.tag, StreamItem::Item(item));
+    }
+
+    /// Returns `false` if the subscribing process doesn't exist anymore.
+    ///
+    /// Long-running producers should check this to stop producing items
+    /// nobody is waiting on. Subscribers on remote nodes are always reported
+    /// as subscribed.
+    pub fn is_subscribed(&self) -> bool {
+        self.subscriber.node_id() != host::node_id() || self.subscriber.is_alive()
+    }
+
+    /// Signals the subscriber that no more items will follow.
+    pub fn finish(self) {
+        // Completion is signaled inside of `Drop`.
+    }
+}
+
+impl<Item, AP: AbstractProcess> Drop for StreamResponder<Item, AP>
+where
+    AP::Serializer: CanSerialize<StreamItem<Item>>,
+{
+    fn drop(&mut self) {
+        if !self.consumed.get() {
+            self.subscriber.tag_send(self.tag, StreamItem::End);
+        }
+    }
+}
+
+impl<Item, AP: AbstractProcess> Serialize for StreamResponder<Item, AP>
+where
+    AP::Serializer: CanSerialize<StreamItem<Item>>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.consumed.set(true);
+        (self.tag, self.subscriber).serialize(serializer)
+    }
+}
+
+impl<'de, Item, AP: AbstractProcess> Deserialize<'de> for StreamResponder<Item, AP>
+where
+    AP::Serializer: CanSerialize<StreamItem<Item>>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let (tag, subscriber) = Deserialize::deserialize(deserializer)?;
+        Ok(StreamResponder::new(tag, subscriber))
+    }
+}
+
+/// Items received as the response to a
+/// [`subscribe_request`](super::ProcessRef::subscribe_request).
+///
+/// The iterator finishes once the handler completes the stream. If the stream
+/// is dropped before it finished, items that are still arriving will stay in
+/// the mailbox of the process.
+pub struct ResponseStream<Item, S>
+where
+    S: CanSerialize<StreamItem<Item>>,
+{
+    tag: Tag,
+    finished: bool,
+    phantom: PhantomData<(Item, S)>,
+}
+
+impl<Item, S> ResponseStream<Item, S>
+where
+    S: CanSerialize<StreamItem<Item>>,
+{
+    pub(crate) fn new(tag: Tag) -> Self {
+        ResponseStream {
+            tag,
+            finished: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Waits for the next item only for the duration of `timeout`.
+    ///
+    /// Returns `Ok(None)` if the stream finished.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Result<Option<Item>, Timeout> {
+        if self.finished {
+            return Ok(None);
+        }
+        let mailbox: Mailbox<StreamItem<Item>, S> = unsafe { Mailbox::new() };
+        match mailbox.tag_receive_timeout(&[self.tag], timeout) {
+            Ok(item) => Ok(self.handle(item)),
+            Err(MailboxError::TimedOut) => Err(Timeout),
+            Err(MailboxError::DeserializationFailed(err)) => {
+                panic!("Failed to deserialize stream item: {err}")
+            }
+            // Only messages with the stream tag are received and no link uses
+            // it.
+            Err(MailboxError::LinkDied(tag)) => unreachable!("link died with stream tag {tag:?}"),
+            Err(MailboxError::ProcessDied(id)) => unreachable!("process {id} died with stream tag"),
+        }
+    }
+
+    fn handle(&mut self, item: StreamItem<Item>) -> Option<Item> {
+        match item {
+            StreamItem::Item(item) => Some(item),
+            StreamItem::End => {
+                self.finished = true;
+                None
+            }
+        }
+    }
+}
+
+impl<Item, S> Iterator for ResponseStream<Item, S>
+where
+    S: CanSerialize<StreamItem<Item>>,
+{
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Item> {
+        if self.finished {
+            return None;
+        }
+        let mailbox: Mailbox<StreamItem<Item>, S> = unsafe { Mailbox::new() };
+        let item = mailbox.tag_receive(&[self.tag]);
+        self.handle(item)
+    }
+}
@@ -0,0 +1,195 @@
+//! A typed publish/subscribe event bus.
+//!
+//! ```no_run
+//! use lunatic::bus::EventBus;
+//! use lunatic::{AbstractProcess, Mailbox};
+//!
+//! #[lunatic::main]
+//! fn main(mailbox: Mailbox<String>) {
+//!     let bus = EventBus::<String>::link().start(()).unwrap();
+//!     bus.subscribe(mailbox.this());
+//!     bus.publish("hello".to_owned());
+//!     assert_eq!(mailbox.receive(), "hello");
+//! }
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::ap::handlers::{Message, Request};
+use crate::ap::{AbstractProcess, Config, MessageHandler, ProcessRef, RequestHandler, State};
+use crate::function::reference::FuncRef;
+use crate::serializer::Bincode;
+use crate::{host, Process};
+
+/// An [`AbstractProcess`] forwarding published events to all subscribers.
+///
+/// Events are delivered at most once. They are sent to each subscriber
+/// without waiting for an acknowledgement, and subscribers that died are
+/// removed from the bus during the next publish. Events published while a
+/// subscriber is dying are lost.
+pub struct EventBus<E> {
+    subscribers: Vec<Subscriber<E>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+struct Subscriber<E> {
+    process: Process<E>,
+    filter: Option<Filter<E>>,
+}
+
+/// A filter function, sent to the bus as a reference inside of the same
+/// module.
+type Filter<E> = FuncRef<fn(E) -> bool>;
+
+impl<E> Subscriber<E> {
+    fn is_alive(&self) -> bool {
+        // Remote processes can't be checked and are only removed by
+        // unsubscribing.
+        self.process.node_id() != host::node_id() || self.process.is_alive()
+    }
+}
+
+/// Message publishing an event on the bus, sent by
+/// [`publish`](ProcessRef::publish).
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "E: Serialize + DeserializeOwned")]
+pub struct Publish<E>(E);
+
+/// Request adding a subscriber to the bus, sent by
+/// [`subscribe`](ProcessRef::subscribe) and
+/// [`subscribe_filtered`](ProcessRef::subscribe_filtered).
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Subscribe<E>(Subscriber<E>);
+
+/// Request removing a subscriber from the bus, sent by
+/// [`unsubscribe`](ProcessRef::unsubscribe).
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Unsubscribe<E>(Process<E>);
+
+/// Request returning the number of subscribers, sent by
+/// [`subscriber_count`](ProcessRef::subscriber_count).
+#[derive(Serialize, Deserialize)]
+pub struct SubscriberCount;
+
+impl<E> AbstractProcess for EventBus<E>
+where
+    E: Serialize + DeserializeOwned + Clone + 'static,
+{
+    type State = Self;
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = (
+        Message<Publish<E>>,
+        Request<Subscribe<E>>,
+        Request<Unsubscribe<E>>,
+        Request<SubscriberCount>,
+    );
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: ()) -> Result<Self, ()> {
+        Ok(EventBus {
+            subscribers: Vec::new(),
+        })
+    }
+}
+
+impl<E> MessageHandler<Publish<E>> for EventBus<E>
+where
+    E: Serialize + DeserializeOwned + Clone + 'static,
+{
+    fn handle(mut state: State<Self>, Publish(event): Publish<E>) {
+        state.subscribers.retain(Subscriber::is_alive);
+        for subscriber in state.subscribers.iter() {
+            let accepted = match &subscriber.filter {
+                Some(filter) => filter(event.clone()),
+                None => true,
+            };
+            if accepted {
+                subscriber.process.send(event.clone());
+            }
+        }
+    }
+}
+
+impl<E> RequestHandler<Subscribe<E>> for EventBus<E>
+where
+    E: Serialize + DeserializeOwned + Clone + 'static,
+{
+    type Response = ();
+
+    fn handle(mut state: State<Self>, Subscribe(subscriber): Subscribe<E>) {
+        state.subscribers.push(subscriber);
+    }
+}
+
+impl<E> RequestHandler<Unsubscribe<E>> for EventBus<E>
+where
+    E: Serialize + DeserializeOwned + Clone + 'static,
+{
+    type Response = ();
+
+    fn handle(mut state: State<Self>, Unsubscribe(process): Unsubscribe<E>) {
+        state
+            .subscribers
+            .retain(|subscriber| subscriber.process != process);
+    }
+}
+
+impl<E> RequestHandler<SubscriberCount> for EventBus<E>
+where
+    E: Serialize + DeserializeOwned + Clone + 'static,
+{
+    type Response = usize;
+
+    fn handle(state: State<Self>, _: SubscriberCount) -> usize {
+        state.subscribers.len()
+    }
+}
+
+impl<E> ProcessRef<EventBus<E>>
+where
+    E: Serialize + DeserializeOwned + Clone + 'static,
+{
+    /// Sends `event` to all subscribers.
+    pub fn publish(&self, event: E) {
+        self.send(Publish(event));
+    }
+
+    /// Subscribes `process` to all events published on the bus.
+    ///
+    /// Once this function returns, the process will receive all following
+    /// events. Subscribing the same process twice delivers events twice.
+    pub fn subscribe(&self, process: Process<E>) {
+        self.request(Subscribe(Subscriber {
+            process,
+            filter: None,
+        }));
+    }
+
+    /// Subscribes `process` to events for which `filter` returns `true`.
+    ///
+    /// The filter runs inside of the bus process and gets a copy of each
+    /// event.
+    pub fn subscribe_filtered(&self, process: Process<E>, filter: fn(E) -> bool) {
+        self.request(Subscribe(Subscriber {
+            process,
+            filter: Some(FuncRef::new(filter)),
+        }));
+    }
+
+    /// Removes all subscriptions of `process`.
+    pub fn unsubscribe(&self, process: Process<E>) {
+        self.request(Unsubscribe(process));
+    }
+
+    /// Returns the number of subscriptions.
+    ///
+    /// Subscribers that died are still counted until the next publish.
+    pub fn subscriber_count(&self) -> usize {
+        self.request(SubscriberCount)
+    }
+}
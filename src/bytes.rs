@@ -0,0 +1,121 @@
+//! A cheaply cloneable, immutable byte buffer.
+//!
+//! [`SharedBytes`] is meant for large read-only payloads that are passed
+//! around a lot. Inside of a process, clones share the same buffer and only
+//! increase a reference count.
+//!
+//! The runtime doesn't support sharing memory between processes, so sending a
+//! [`SharedBytes`] in a message copies the buffer once. The receiving process
+//! gets its own buffer that can again be cloned without copying.
+
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A reference-counted, immutable byte buffer.
+///
+/// ```
+/// use lunatic::bytes::SharedBytes;
+///
+/// let payload = SharedBytes::from(vec![0u8; 1024]);
+/// let copy = payload.clone();
+/// assert_eq!(payload.ref_count(), 2);
+/// assert_eq!(&copy[..4], &[0, 0, 0, 0]);
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SharedBytes {
+    inner: Rc<[u8]>,
+}
+
+impl SharedBytes {
+    /// Returns the number of references to the buffer inside of the current
+    /// process.
+    pub fn ref_count(&self) -> usize {
+        Rc::strong_count(&self.inner)
+    }
+
+    /// Returns a copy of the bytes.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.inner.to_vec()
+    }
+}
+
+impl Deref for SharedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.inner
+    }
+}
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.inner
+    }
+}
+
+impl From<Vec<u8>> for SharedBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SharedBytes {
+            inner: bytes.into(),
+        }
+    }
+}
+
+impl From<&[u8]> for SharedBytes {
+    fn from(bytes: &[u8]) -> Self {
+        SharedBytes {
+            inner: bytes.into(),
+        }
+    }
+}
+
+impl fmt::Debug for SharedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedBytes")
+            .field("len", &self.inner.len())
+            .finish()
+    }
+}
+
+impl Serialize for SharedBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.inner)
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_byte_buf(SharedBytesVisitor)
+    }
+}
+
+struct SharedBytesVisitor;
+
+impl<'de> Visitor<'de> for SharedBytesVisitor {
+    type Value = SharedBytes;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte array")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Ok(bytes.into())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(bytes.into())
+    }
+
+    // Self-describing formats like JSON encode bytes as a sequence.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes.into())
+    }
+}
@@ -1,10 +1,28 @@
+use std::io::{self, ErrorKind};
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::{host, LunaticError};
 
 /// Process configurations determine permissions of processes.
 ///
 /// The functions `spawn_config` & `spawn_link_config` can be used to create
 /// processes with a specific configuration.
-pub struct ProcessConfig(ProcessConfigType);
+///
+/// A configuration can be serialized, e.g. to send it to another node. Only
+/// the limits and permissions are serialized, environment variables, command
+/// line arguments and pre-opened directories are not part of it. Deserializing
+/// creates a new configuration and requires the permission to create configs.
+pub struct ProcessConfig {
+    config: ProcessConfigType,
+    /// Environment variables, command line arguments and pre-opened
+    /// directories can't be read back from the host, so they are also kept
+    /// here to be able to clone the configuration.
+    environment: Vec<(String, String)>,
+    arguments: Vec<String>,
+    dirs: Vec<String>,
+}
 
 enum ProcessConfigType {
     /// ID of a configuration held by the host as a resource.
@@ -25,7 +43,7 @@ impl Drop for ProcessConfigType {
 
 impl std::fmt::Debug for ProcessConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.0 {
+        match self.config {
             ProcessConfigType::Config(_) => f
                 .debug_struct("ProcessConfig")
                 .field("max_memory", &self.get_max_memory())
@@ -33,12 +51,67 @@ impl std::fmt::Debug for ProcessConfig {
                 .field("can_compile_modules", &self.can_compile_modules())
                 .field("can_create_configs", &self.can_create_configs())
                 .field("can_spawn_processes", &self.can_spawn_processes())
+                .field("environment", &self.environment)
+                .field("arguments", &self.arguments)
+                .field("dirs", &self.dirs)
                 .finish(),
             ProcessConfigType::Inherit => f.debug_struct("ProcessConfig::Inherit").finish(),
         }
     }
 }
 
+/// Serialized representation of a [`ProcessConfig`].
+#[derive(Serialize, Deserialize)]
+struct ProcessConfigData {
+    max_memory: u64,
+    max_fuel: u64,
+    can_compile_modules: bool,
+    can_create_configs: bool,
+    can_spawn_processes: bool,
+}
+
+impl Serialize for ProcessConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.data().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ProcessConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = Option::<ProcessConfigData>::deserialize(deserializer)?;
+        ProcessConfig::from_data(data).map_err(D::Error::custom)
+    }
+}
+
+/// Creates a new configuration with the same limits, permissions, environment
+/// variables, command line arguments and pre-opened directories.
+///
+/// The host can't duplicate a configuration, so a new one is created. A process
+/// holding a configuration that is not inherited was already able to create it,
+/// so creating the copy doesn't fail.
+impl Clone for ProcessConfig {
+    fn clone(&self) -> Self {
+        let mut config = ProcessConfig::from_data(self.data())
+            .expect("process holding a config can create configs");
+        for (key, value) in &self.environment {
+            config.add_environment_variable(key, value);
+        }
+        for argument in &self.arguments {
+            config.add_command_line_argument(argument);
+        }
+        for dir in &self.dirs {
+            config.add_dir(dir);
+        }
+        config
+    }
+}
+
 impl ProcessConfig {
     /// Create a new process configuration with all permissions denied.
     ///
@@ -47,18 +120,58 @@ impl ProcessConfig {
     pub fn new() -> Result<Self, LunaticError> {
         match unsafe { host::api::process::create_config() } {
             -1 => Err(LunaticError::PermissionDenied),
-            id => Ok(Self(ProcessConfigType::Config(id as u64))),
+            id => Ok(Self::from_type(ProcessConfigType::Config(id as u64))),
         }
     }
 
     pub(crate) fn inherit() -> Self {
-        Self(ProcessConfigType::Inherit)
+        Self::from_type(ProcessConfigType::Inherit)
+    }
+
+    fn from_type(config: ProcessConfigType) -> Self {
+        ProcessConfig {
+            config,
+            environment: Vec::new(),
+            arguments: Vec::new(),
+            dirs: Vec::new(),
+        }
+    }
+
+    /// Returns the limits and permissions, or `None` for an inherited
+    /// configuration.
+    fn data(&self) -> Option<ProcessConfigData> {
+        match self.config {
+            ProcessConfigType::Config(_) => Some(ProcessConfigData {
+                max_memory: self.get_max_memory(),
+                max_fuel: self.get_max_fuel(),
+                can_compile_modules: self.can_compile_modules(),
+                can_create_configs: self.can_create_configs(),
+                can_spawn_processes: self.can_spawn_processes(),
+            }),
+            ProcessConfigType::Inherit => None,
+        }
+    }
+
+    /// Creates a configuration from the limits and permissions returned by
+    /// [`data`](Self::data).
+    fn from_data(data: Option<ProcessConfigData>) -> Result<Self, LunaticError> {
+        let data = match data {
+            Some(data) => data,
+            None => return Ok(ProcessConfig::inherit()),
+        };
+        let mut config = ProcessConfig::new()?;
+        config.set_max_memory(data.max_memory);
+        config.set_max_fuel(data.max_fuel);
+        config.set_can_compile_modules(data.can_compile_modules);
+        config.set_can_create_configs(data.can_create_configs);
+        config.set_can_spawn_processes(data.can_spawn_processes);
+        Ok(config)
     }
 
     /// Returns the id of the configuration resource or -1 in case it's an
     /// inherited configuration.
     pub fn id(&self) -> i64 {
-        match self.0 {
+        match self.config {
             ProcessConfigType::Config(id) => id as i64,
             ProcessConfigType::Inherit => -1,
         }
@@ -125,6 +238,61 @@ impl ProcessConfig {
         (unsafe { host::api::process::config_can_spawn_processes(self.id() as u64) }) > 0
     }
 
+    /// Sets the ability of a process to spawn sub-processes.
+    ///
+    /// Same as [`set_can_spawn_processes`](Self::set_can_spawn_processes).
+    pub fn set_allow_process_spawn(&mut self, allow: bool) {
+        self.set_can_spawn_processes(allow);
+    }
+
+    /// Returns true if processes can spawn sub-processes.
+    pub fn allow_process_spawn(&self) -> bool {
+        self.can_spawn_processes()
+    }
+
+    /// Sets the ability of a process to use networking.
+    ///
+    /// The runtime doesn't support restricting networking yet. Denying it
+    /// returns an error with [`ErrorKind::Unsupported`].
+    pub fn set_allow_networking(&mut self, allow: bool) -> io::Result<()> {
+        capability_unsupported("networking", allow, true)
+    }
+
+    /// Returns true if processes can use networking.
+    pub fn allow_networking(&self) -> bool {
+        true
+    }
+
+    /// Sets the ability of a process to access the filesystem.
+    ///
+    /// Processes can only access directories that were explicitly pre-opened,
+    /// so there is no ambient filesystem access. Allowing it returns an error
+    /// with [`ErrorKind::Unsupported`].
+    pub fn set_allow_filesystem(&mut self, allow: bool) -> io::Result<()> {
+        capability_unsupported("filesystem access", allow, false)
+    }
+
+    /// Returns true if processes have ambient access to the filesystem.
+    pub fn allow_filesystem(&self) -> bool {
+        false
+    }
+
+    /// Sets the ability of a process to spawn processes on other nodes.
+    ///
+    /// Spawning on remote nodes is controlled by the same permission as
+    /// spawning locally, see
+    /// [`set_allow_process_spawn`](Self::set_allow_process_spawn). Denying
+    /// only distributed spawns returns an error with
+    /// [`ErrorKind::Unsupported`].
+    pub fn set_allow_distributed(&mut self, allow: bool) -> io::Result<()> {
+        capability_unsupported("distributed spawning", allow, self.allow_process_spawn())
+    }
+
+    /// Returns true if processes can spawn processes on other nodes.
+    pub fn allow_distributed(&self) -> bool {
+        self.allow_process_spawn()
+    }
+
     /// Adds environment variable.
     pub fn add_environment_variable(&mut self, key: &str, value: &str) {
         unsafe {
@@ -136,6 +304,7 @@ impl ProcessConfig {
                 value.len(),
             )
         }
+        self.environment.push((key.to_owned(), value.to_owned()));
     }
 
     /// Adds command line argument.
@@ -147,6 +316,7 @@ impl ProcessConfig {
                 argument.len(),
             )
         }
+        self.arguments.push(argument.to_owned());
     }
 
     #[rustversion::before(1.67)]
@@ -156,6 +326,108 @@ impl ProcessConfig {
     /// - https://github.com/rust-lang/rust/issues/107635
     /// - https://github.com/rust-lang/rust/pull/108097
     pub fn preopen_dir(&mut self, dir: &str) {
+        self.add_dir(dir);
+    }
+
+    fn add_dir(&mut self, dir: &str) {
         unsafe { host::api::wasi::config_preopen_dir(self.id() as u64, dir.as_ptr(), dir.len()) }
+        self.dirs.push(dir.to_owned());
+    }
+
+    #[rustversion::before(1.67)]
+    /// Mark the directory `host_path` as pre-opened under `guest_path`.
+    ///
+    /// The process can only access files inside of pre-opened directories.
+    /// The runtime always uses the host path inside of the process, so
+    /// mapping it to a different guest path returns an error with
+    /// [`ErrorKind::Unsupported`].
+    ///
+    /// Like [`preopen_dir`](Self::preopen_dir), this API is only available in
+    /// Rust 1.66 and below. See:
+    /// - https://github.com/rust-lang/rust/issues/107635
+    /// - https://github.com/rust-lang/rust/pull/108097
+    pub fn preopen_dir_as(&mut self, host_path: &str, guest_path: &str) -> io::Result<()> {
+        if host_path != guest_path {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "mapping pre-opened directories is not supported by the runtime",
+            ));
+        }
+        self.preopen_dir(host_path);
+        Ok(())
+    }
+}
+
+/// Builder-style methods that take and return the configuration, e.g.
+///
+/// ```no_run
+/// # use lunatic::ProcessConfig;
+/// let config = ProcessConfig::new()
+///     .unwrap()
+///     .with_max_memory(5_000_000)
+///     .with_env("LOG", "debug");
+/// ```
+impl ProcessConfig {
+    /// Same as [`set_max_memory`](Self::set_max_memory).
+    pub fn with_max_memory(mut self, max_memory: u64) -> Self {
+        self.set_max_memory(max_memory);
+        self
+    }
+
+    /// Same as [`set_max_fuel`](Self::set_max_fuel).
+    pub fn with_max_fuel(mut self, max_fuel: u64) -> Self {
+        self.set_max_fuel(max_fuel);
+        self
+    }
+
+    /// Same as [`set_can_compile_modules`](Self::set_can_compile_modules).
+    pub fn with_can_compile_modules(mut self, can: bool) -> Self {
+        self.set_can_compile_modules(can);
+        self
+    }
+
+    /// Same as [`set_can_create_configs`](Self::set_can_create_configs).
+    pub fn with_can_create_configs(mut self, can: bool) -> Self {
+        self.set_can_create_configs(can);
+        self
+    }
+
+    /// Same as [`set_can_spawn_processes`](Self::set_can_spawn_processes).
+    pub fn with_can_spawn_processes(mut self, can: bool) -> Self {
+        self.set_can_spawn_processes(can);
+        self
+    }
+
+    /// Same as [`add_environment_variable`](Self::add_environment_variable).
+    pub fn with_env(mut self, key: &str, value: &str) -> Self {
+        self.add_environment_variable(key, value);
+        self
+    }
+
+    /// Same as [`add_command_line_argument`](Self::add_command_line_argument).
+    pub fn with_argument(mut self, argument: &str) -> Self {
+        self.add_command_line_argument(argument);
+        self
+    }
+
+    #[rustversion::before(1.67)]
+    /// Same as [`preopen_dir`](Self::preopen_dir).
+    pub fn with_dir(mut self, dir: &str) -> Self {
+        self.preopen_dir(dir);
+        self
+    }
+}
+
+/// Returns an error if the requested value of a capability differs from the
+/// one enforced by the runtime.
+fn capability_unsupported(capability: &str, requested: bool, enforced: bool) -> io::Result<()> {
+    if requested == enforced {
+        Ok(())
+    } else {
+        let action = if requested { "allowing" } else { "denying" };
+        Err(io::Error::new(
+            ErrorKind::Unsupported,
+            format!("{action} {capability} is not supported by the runtime"),
+        ))
     }
 }
@@ -1,22 +1,309 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::ap::{AbstractProcess, ProcessRef};
+use crate::function::reference::FuncRef;
 use crate::host::api::distributed::{
     copy_lookup_nodes_results, exec_lookup_nodes, get_nodes, module_id, nodes_count,
 };
 use crate::host::api::{self};
+use crate::mailbox::MailboxError;
 use crate::module::{params_to_vec, Param};
-use crate::LunaticError;
+use crate::serializer::CanSerialize;
+use crate::{process_local, LunaticError, Mailbox, Process, ProcessName, Tag};
+
+/// How often the cluster membership is checked for changes.
+const NODE_EVENT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often [`await_registration`] checks the registry.
+pub const REGISTRATION_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 pub fn node_id() -> u64 {
     unsafe { api::distributed::node_id() }
 }
 
+/// Returns the ids of all nodes currently known to be in the cluster.
+///
+/// The local node is always part of the result, so it can be used to spread
+/// work over all nodes, including the current one.
+///
+/// ```no_run
+/// let nodes = lunatic::distributed::nodes();
+/// for (i, task) in ["a", "b", "c"].iter().enumerate() {
+///     let node = nodes[i % nodes.len()];
+///     println!("{task} goes to node {node}");
+/// }
+/// ```
 pub fn nodes() -> Vec<u64> {
     let cnt = unsafe { nodes_count() as usize };
     let mut nodes = vec![0; cnt];
     let copied_cnt = unsafe { get_nodes(nodes.as_mut_ptr(), cnt as u32) as usize };
     nodes.truncate(copied_cnt);
+    let local = node_id();
+    if !nodes.contains(&local) {
+        nodes.push(local);
+    }
     nodes
 }
 
+/// Returns the ids of all other nodes currently known to be in the cluster.
+///
+/// Same as [`nodes`], but without the local node.
+pub fn remote_nodes() -> Vec<u64> {
+    let local = node_id();
+    nodes().into_iter().filter(|node| *node != local).collect()
+}
+
+/// Chooses the node a new process is spawned on.
+///
+/// Used by
+/// [`AbstractProcessBuilder::on_any_node_with`](crate::ap::AbstractProcessBuilder::on_any_node_with).
+pub trait NodePicker {
+    /// Returns one of the `nodes`.
+    ///
+    /// `nodes` is never empty, it contains at least the local node.
+    fn pick_node(&self, nodes: &[u64]) -> u64;
+}
+
+process_local! {
+    static NEXT_NODE: Cell<usize> = Cell::new(0);
+}
+
+/// Picks the nodes in turns.
+///
+/// The runtime doesn't expose the load of other nodes, so this is the picker
+/// used by
+/// [`AbstractProcessBuilder::on_any_node`](crate::ap::AbstractProcessBuilder::on_any_node).
+/// The turn is kept per process.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundRobin;
+
+impl NodePicker for RoundRobin {
+    fn pick_node(&self, nodes: &[u64]) -> u64 {
+        let next = NEXT_NODE.with(|next| next.replace(next.get().wrapping_add(1)));
+        nodes[next % nodes.len()]
+    }
+}
+
+/// A change in the cluster membership.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeEvent {
+    /// A node joined the cluster.
+    Joined(u64),
+    /// A node left the cluster.
+    Left(u64),
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+enum ListenerMessage {
+    Stop,
+}
+
+/// Handle to the process observing the cluster membership, returned by
+/// [`on_node_event`].
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeEventListener {
+    listener: Process<ListenerMessage>,
+}
+
+impl NodeEventListener {
+    /// Stops calling the handler.
+    pub fn stop(self) {
+        self.listener.send(ListenerMessage::Stop);
+    }
+
+    /// Returns `true` if the handler is still called on changes.
+    pub fn is_active(&self) -> bool {
+        self.listener.is_alive()
+    }
+}
+
+/// Calls `handler` every time a node joins or leaves the cluster.
+///
+/// The cluster membership is observed from a new process, and `handler` runs
+/// inside of it. It stops once [`NodeEventListener::stop`] is called or the
+/// caller dies.
+///
+/// ```no_run
+/// lunatic::distributed::on_node_event(|event| println!("{event:?}"));
+/// ```
+pub fn on_node_event(handler: fn(NodeEvent)) -> NodeEventListener {
+    let owner = unsafe { Process::<()>::new(node_id(), crate::host::process_id()) };
+    let listener = Process::spawn(
+        (FuncRef::new(handler), owner),
+        |(handler, owner), mailbox: Mailbox<ListenerMessage>| {
+            let mailbox = mailbox.monitorable();
+            mailbox.monitor(owner);
+            let mut known = nodes();
+            // Anything else than a timeout means it was stopped or the owner died.
+            while let Err(MailboxError::TimedOut) =
+                mailbox.receive_timeout(NODE_EVENT_POLL_INTERVAL)
+            {
+                let current = nodes();
+                for node in current.iter().filter(|node| !known.contains(node)) {
+                    handler(NodeEvent::Joined(*node));
+                }
+                for node in known.iter().filter(|node| !current.contains(node)) {
+                    handler(NodeEvent::Left(*node));
+                }
+                known = current;
+            }
+        },
+    );
+    NodeEventListener { listener }
+}
+
+/// A part of a payload sent with [`send_chunked`].
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Chunk {
+    /// The `index`-th part of the payload.
+    Data { index: u32, bytes: Vec<u8> },
+    /// Marks the end of the payload, consisting of `count` parts.
+    Complete { count: u32 },
+}
+
+/// Error returned by [`receive_chunked`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ChunkError {
+    /// The chunk with this index didn't arrive in time.
+    #[error("chunk {0} is missing")]
+    Missing(u32),
+    /// A chunk failed to deserialize.
+    #[error("chunk failed to deserialize: {0}")]
+    DeserializationFailed(String),
+}
+
+/// Sends `data` to `process`, split into messages of at most `chunk_size`
+/// bytes.
+///
+/// All chunks are sent with `tag`, followed by a [`Chunk::Complete`] marker.
+/// This allows sending payloads to remote nodes that would otherwise exceed
+/// the limits of a single message. The payload is reassembled on the
+/// receiving side with [`receive_chunked`].
+///
+/// # Panics
+///
+/// This function will panic if `chunk_size` is 0.
+pub fn send_chunked<S>(process: Process<Chunk, S>, tag: Tag, data: &[u8], chunk_size: usize)
+where
+    S: CanSerialize<Chunk>,
+{
+    assert!(chunk_size > 0, "chunk_size must be greater than 0");
+    let mut count = 0;
+    for (index, bytes) in data.chunks(chunk_size).enumerate() {
+        let index = index as u32;
+        process.tag_send(
+            tag,
+            Chunk::Data {
+                index,
+                bytes: bytes.to_vec(),
+            },
+        );
+        count = index + 1;
+    }
+    process.tag_send(tag, Chunk::Complete { count });
+}
+
+/// Receives a payload sent with [`send_chunked`] and the same `tag`.
+///
+/// Chunks can arrive in any order and are put back together by their index.
+/// If no new chunk arrives for the duration of `timeout` before the payload
+/// is complete, [`ChunkError::Missing`] is returned with the index of the
+/// first missing chunk. If only the [`Chunk::Complete`] marker is missing, the
+/// index is equal to the number of received chunks.
+pub fn receive_chunked<S>(
+    mailbox: &Mailbox<Chunk, S>,
+    tag: Tag,
+    timeout: Duration,
+) -> Result<Vec<u8>, ChunkError>
+where
+    S: CanSerialize<Chunk>,
+{
+    let mut chunks = BTreeMap::new();
+    let mut count = None;
+    while count != Some(chunks.len() as u32) {
+        match mailbox.tag_receive_timeout(&[tag], timeout) {
+            Ok(Chunk::Data { index, bytes }) => {
+                chunks.insert(index, bytes);
+            }
+            Ok(Chunk::Complete { count: total }) => count = Some(total),
+            // A sender that died won't deliver the remaining chunks either.
+            Err(
+                MailboxError::TimedOut | MailboxError::LinkDied(_) | MailboxError::ProcessDied(_),
+            ) => {
+                let missing = (0..).find(|index| !chunks.contains_key(index));
+                return Err(ChunkError::Missing(missing.unwrap_or_default()));
+            }
+            Err(MailboxError::DeserializationFailed(err)) => {
+                return Err(ChunkError::DeserializationFailed(err.to_string()))
+            }
+        }
+    }
+    Ok(chunks.into_values().flatten().collect())
+}
+
+/// Waits until a process is registered under `name` anywhere in the cluster.
+///
+/// Returns `None` if no matching process is registered before `timeout`
+/// expires. The registry doesn't notify about new registrations, so it's
+/// polled every [`REGISTRATION_POLL_INTERVAL`]. Use
+/// [`await_registration_with_interval`] to change the interval.
+///
+/// This removes the need to start services in a particular order, even if
+/// they are spread across nodes.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use lunatic::ap::{AbstractProcess, Config};
+/// # struct Database;
+/// # impl AbstractProcess for Database {
+/// #     type State = ();
+/// #     type Serializer = lunatic::serializer::Bincode;
+/// #     type Arg = ();
+/// #     type Handlers = ();
+/// #     type StartupError = ();
+/// #     fn init(_: Config<Self>, _: ()) -> Result<(), ()> { Ok(()) }
+/// # }
+/// let db = lunatic::distributed::await_registration::<Database, _>(
+///     &"database",
+///     Duration::from_secs(30),
+/// )
+/// .expect("database didn't start in time");
+/// ```
+pub fn await_registration<T, N>(name: &N, timeout: Duration) -> Option<ProcessRef<T>>
+where
+    T: AbstractProcess,
+    N: ProcessName + ?Sized,
+{
+    await_registration_with_interval(name, timeout, REGISTRATION_POLL_INTERVAL)
+}
+
+/// Same as [`await_registration`], but polls the registry every `interval`.
+pub fn await_registration_with_interval<T, N>(
+    name: &N,
+    timeout: Duration,
+    interval: Duration,
+) -> Option<ProcessRef<T>>
+where
+    T: AbstractProcess,
+    N: ProcessName + ?Sized,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(process) = ProcessRef::lookup(name) {
+            return Some(process);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+        crate::sleep(remaining.min(interval));
+    }
+}
+
 /// Executes a lookup query request to the control node and returns `u64` node
 /// ids.
 ///
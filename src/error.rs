@@ -10,9 +10,15 @@ use crate::host::api::error;
 /// enumerate all of them. This is especially true for calls that involve
 /// compiling raw binary data to WebAssembly modules. Because of this an opaque
 /// error ID is returned from host that can be transformed to a string.
+///
+/// The [`Display`] implementation fetches the message of the error from the
+/// host, so the error can be used with `?` like any other
+/// [`std::error::Error`].
 #[derive(Error)]
 pub enum LunaticError {
+    /// An error described by the host, identified by the error ID.
     Error(u64),
+    /// The process doesn't have the permission for the operation.
     PermissionDenied,
     /// A process under this name already exists (node_id, process_id)
     NameAlreadyRegistered(u64, u64),
@@ -32,17 +38,7 @@ impl Drop for LunaticError {
 
 impl Debug for LunaticError {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        match self {
-            Self::Error(id) => {
-                let size = unsafe { error::string_size(*id) };
-                let mut buff = vec![0; size as usize];
-                unsafe { error::to_string(*id, buff.as_mut_ptr()) };
-                let error = std::str::from_utf8(&buff).unwrap();
-                write!(f, "{}", error)
-            }
-            Self::PermissionDenied => write!(f, "Permission denied"),
-            Self::NameAlreadyRegistered(_, _) => write!(f, "Name is already registered"),
-        }
+        Display::fmt(self, f)
     }
 }
 
@@ -53,7 +49,7 @@ impl Display for LunaticError {
                 let size = unsafe { error::string_size(*id) };
                 let mut buff = vec![0; size as usize];
                 unsafe { error::to_string(*id, buff.as_mut_ptr()) };
-                let error = std::str::from_utf8(&buff).unwrap();
+                let error = String::from_utf8_lossy(&buff);
                 write!(f, "{}", error)
             }
             Self::PermissionDenied => write!(f, "Permission denied"),
@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::host::{self, node_id, process_id};
 use crate::mailbox::{MailboxError, MessageSignal, TIMEOUT};
-use crate::protocol::ProtocolCapture;
+use crate::protocol::{Protocol, ProtocolCapture, Recv, TaskDied, TaskEnd};
 use crate::serializer::{Bincode, CanSerialize};
 use crate::time::TimerRef;
 use crate::{LunaticError, MailboxResult, ProcessConfig, ProcessName, Tag};
@@ -215,7 +215,15 @@ impl<M, S> Process<M, S> {
         T::spawn(capture, entry, Some(name), None, None, Some(node_id))
     }
 
-    /// Spawn a process on a remote node.
+    /// Spawn a process on a remote node with a specific configuration.
+    ///
+    /// The limits and permissions of `config` are sent together with the
+    /// spawn request and the remote node uses them instead of its own
+    /// defaults, so the spawning node decides the sandbox of the process.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the remote node rejects the spawn request.
     #[track_caller]
     pub fn spawn_node_config<C, T>(
         node_id: u64,
@@ -395,8 +403,25 @@ impl<M, S> Process<M, S> {
     /// Register process under a name.
     pub fn register<N: ProcessName>(&self, name: &N) {
         // Encode type information in name
-        let name = process_name::<M, S>(ProcessType::Process, name.process_name());
-        unsafe { host::api::registry::put(name.as_ptr(), name.len(), self.node_id, self.id) };
+        let key = process_name::<M, S>(ProcessType::Process, name.process_name());
+        unsafe { host::api::registry::put(key.as_ptr(), key.len(), self.node_id, self.id) };
+        crate::registry::record(name.process_name(), key, self.node_id, self.id);
+    }
+
+    /// Returns the first name this process was registered under by the
+    /// current process.
+    ///
+    /// The registry can't be searched by process, so only the registrations
+    /// made from the current process are known. These are names passed to
+    /// `register`, `register_with_lease` and `start_as`, which includes the
+    /// children started by a supervisor, as seen from the supervisor itself.
+    /// A name registered by any other process, e.g. by the parent of this
+    /// process, is not returned. Names that were removed or taken over by
+    /// another process are skipped.
+    pub fn name(&self) -> Option<String> {
+        crate::registry::names_of(self.node_id, self.id)
+            .into_iter()
+            .next()
     }
 
     /// Look up a process.
@@ -451,7 +476,7 @@ where
         // Send it!
         let timer_id =
             unsafe { host::api::timer::send_after(self.id, duration.as_millis() as u64) };
-        TimerRef::new(timer_id)
+        TimerRef::new(timer_id, duration)
     }
 
     /// Send message to process with a specific tag.
@@ -484,7 +509,7 @@ where
         // Send it!
         let timer_id =
             unsafe { host::api::timer::send_after(self.id, duration.as_millis() as u64) };
-        TimerRef::new(timer_id)
+        TimerRef::new(timer_id, duration)
     }
 
     /// Sends message and waits on response until timeout (if specified).
@@ -571,6 +596,36 @@ impl<M, S> Clone for Process<M, S> {
 
 impl<M, S> Copy for Process<M, S> {}
 
+/// Waits for all tasks to finish and returns their results in the same order.
+///
+/// # Panics
+///
+/// This function will panic if any of the tasks dies without returning a
+/// result. Use [`try_join_all`] to handle failed tasks.
+#[track_caller]
+pub fn join_all<A, S, Z>(tasks: Vec<Protocol<Recv<A, TaskEnd>, S, Z>>) -> Vec<A>
+where
+    S: CanSerialize<A>,
+{
+    try_join_all(tasks)
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|err| panic!("{err}")))
+        .collect()
+}
+
+/// Waits for all tasks to finish and returns their results in the same order.
+///
+/// Tasks that die without returning a result are returned as [`TaskDied`]
+/// errors, see [`Protocol::try_result`].
+pub fn try_join_all<A, S, Z>(
+    tasks: Vec<Protocol<Recv<A, TaskEnd>, S, Z>>,
+) -> Vec<Result<A, TaskDied>>
+where
+    S: CanSerialize<A>,
+{
+    tasks.into_iter().map(Protocol::try_result).collect()
+}
+
 #[derive(Clone, Copy, Debug)]
 pub(crate) enum ProcessType {
     Process,
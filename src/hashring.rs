@@ -0,0 +1,181 @@
+//! Consistent hashing for distributing keys over a set of processes or nodes.
+//!
+//! A [`HashRing`] maps keys to members, so that adding or removing a member
+//! only remaps the keys that belonged to it, or are taken over by it. This
+//! makes it a good fit for sharding stateful work, e.g. spawning one process
+//! per user on the node responsible for that user.
+//!
+//! ```
+//! use lunatic::hashring::HashRing;
+//!
+//! let mut ring = HashRing::new();
+//! ring.add(1);
+//! ring.add(2);
+//! let node = ring.get(&"user:42").unwrap();
+//! assert!(*node == 1 || *node == 2);
+//! ```
+
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+/// Number of virtual nodes per unit of weight, if not specified otherwise.
+pub const DEFAULT_VIRTUAL_NODES: u32 = 160;
+
+/// A consistent hash ring.
+///
+/// Each member is placed on the ring multiple times as virtual nodes, which
+/// spreads the keys evenly. Members with a higher weight get proportionally
+/// more virtual nodes, and with that more keys.
+///
+/// The hash function is stable, so all processes and nodes agree on the
+/// mapping as long as they add the same members.
+#[derive(Debug, Clone)]
+pub struct HashRing<T> {
+    virtual_nodes: u32,
+    // Points are keyed by their position and the hash of the member, so that
+    // virtual nodes of different members can share a position.
+    ring: BTreeMap<(u64, u64), T>,
+    members: Vec<(T, u32)>,
+}
+
+impl<T: Hash + Eq + Clone> HashRing<T> {
+    /// Creates an empty ring with [`DEFAULT_VIRTUAL_NODES`] virtual nodes per
+    /// member.
+    pub fn new() -> Self {
+        Self::with_virtual_nodes(DEFAULT_VIRTUAL_NODES)
+    }
+
+    /// Creates an empty ring with `virtual_nodes` virtual nodes per unit of
+    /// weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `virtual_nodes` is 0.
+    pub fn with_virtual_nodes(virtual_nodes: u32) -> Self {
+        assert!(virtual_nodes > 0, "at least one virtual node is required");
+        HashRing {
+            virtual_nodes,
+            ring: BTreeMap::new(),
+            members: Vec::new(),
+        }
+    }
+
+    /// Adds `member` with a weight of 1.
+    pub fn add(&mut self, member: T) {
+        self.add_weighted(member, 1);
+    }
+
+    /// Adds `member` with `weight`.
+    ///
+    /// A member with weight 2 receives about twice as many keys as one with
+    /// weight 1. Adding a member that is already part of the ring updates its
+    /// weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` times the number of virtual nodes overflows a `u32`.
+    pub fn add_weighted(&mut self, member: T, weight: u32) {
+        let replicas = weight
+            .checked_mul(self.virtual_nodes)
+            .expect("weight times virtual nodes overflows u32");
+        self.remove(&member);
+        for replica in 0..replicas {
+            self.ring.insert(point(&member, replica), member.clone());
+        }
+        self.members.push((member, weight));
+    }
+
+    /// Removes `member` from the ring and returns `true` if it was part of it.
+    pub fn remove(&mut self, member: &T) -> bool {
+        let index = match self.members.iter().position(|(m, _)| m == member) {
+            Some(index) => index,
+            None => return false,
+        };
+        let (member, weight) = self.members.remove(index);
+        // Checked when the member was added.
+        for replica in 0..weight * self.virtual_nodes {
+            self.ring.remove(&point(&member, replica));
+        }
+        true
+    }
+
+    /// Returns the member responsible for `key`, or `None` if the ring is
+    /// empty.
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<&T> {
+        let hash = hash(key);
+        self.ring
+            .range((hash, 0)..)
+            .next()
+            .or_else(|| self.ring.iter().next())
+            .map(|(_, member)| member)
+    }
+
+    /// Returns the weight of `member`, or `None` if it's not part of the ring.
+    pub fn weight(&self, member: &T) -> Option<u32> {
+        self.members
+            .iter()
+            .find(|(m, _)| m == member)
+            .map(|(_, weight)| *weight)
+    }
+
+    /// Returns an iterator over all members in the order they were added.
+    pub fn members(&self) -> impl Iterator<Item = &T> {
+        self.members.iter().map(|(member, _)| member)
+    }
+
+    /// Returns the number of members.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Returns `true` if the ring has no members.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+impl<T: Hash + Eq + Clone> Default for HashRing<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Position of the `replica`-th virtual node of `member` on the ring, together
+/// with the hash of `member`.
+fn point<T: Hash>(member: &T, replica: u32) -> (u64, u64) {
+    (hash(&(member, replica)), hash(member))
+}
+
+fn hash<K: Hash + ?Sized>(key: &K) -> u64 {
+    let mut hasher = Fnv1a(FNV_OFFSET_BASIS);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// The FNV-1a hash function.
+///
+/// Unlike the `DefaultHasher` from std, its output is guaranteed to stay the
+/// same between Rust versions.
+struct Fnv1a(u64);
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        // FNV-1a distributes short inputs poorly over the high bits, mix them
+        // in so that points are spread over the whole ring.
+        let mut hash = self.0;
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+        hash ^= hash >> 33;
+        hash
+    }
+}
@@ -102,6 +102,18 @@ pub fn node_id() -> u64 {
     unsafe { api::distributed::node_id() }
 }
 
+/// Returns the names the current process registered itself under.
+///
+/// The registry can't be searched by process, so only names the current
+/// process registered for itself are returned. If the name was registered by
+/// another process, e.g. the parent spawned it with `start_as` or a supervisor
+/// started it, it's not returned here. See
+/// [`Process::name`](crate::Process::name) for the registration functions that
+/// are tracked.
+pub fn registered_names() -> Vec<String> {
+    crate::registry::names_of(node_id(), process_id())
+}
+
 pub fn send(node: u64, process_id: u64) {
     if node_id() == node {
         unsafe { api::message::send(process_id) }
@@ -120,6 +132,22 @@ pub fn send_receive_skip_search(node: u64, process_id: u64, wait_on_tag: i64, ti
     }
 }
 
+/// Runs `f` with `amount` of additional fuel available to the process.
+///
+/// The extra fuel is meant as a one-time top-up for rare expensive operations,
+/// without permanently raising the limit set with
+/// [`ProcessConfig::set_max_fuel`].
+///
+/// The runtime doesn't support adding fuel to a running process yet, so this
+/// currently only calls `f` and the process keeps its original budget.
+pub fn with_extra_fuel<F, R>(amount: u64, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let _ = amount;
+    f()
+}
+
 /// Utility for calling an allocating host function which is deserialized into
 /// `T`.
 ///
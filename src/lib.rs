@@ -107,38 +107,49 @@ mod process_name;
 mod tag;
 
 pub mod ap;
+pub mod bus;
+pub mod bytes;
 pub mod distributed;
 pub mod function;
+pub mod hashring;
 pub mod host;
 pub mod metrics;
 pub mod net;
 pub mod panic;
 pub mod protocol;
+pub mod registry;
 pub mod serializer;
+pub mod shutdown;
 pub mod supervisor;
-#[doc(hidden)]
 pub mod test;
 pub mod time;
 
 #[cfg(feature = "sqlite")]
 pub mod sqlite;
 
+// The `#[lunatic::test]` macro refers to `lunatic::*` paths. Inside of the
+// unit tests they need to point to this crate and not to the dev-dependency on
+// itself, otherwise two copies of the crate end up in the test binary.
+#[cfg(test)]
+extern crate self as lunatic;
+
 pub use ap::AbstractProcess;
 pub use config::ProcessConfig;
 pub use error::LunaticError;
-pub use function::process::Process;
+pub use function::process::{join_all, try_join_all, Process};
 pub use lunatic_macros::{abstract_process, main, ProcessName};
 pub use lunatic_sys::*;
 pub use lunatic_test::test;
 pub use mailbox::{
     LinkDiedSignal, Mailbox, MailboxError, MailboxResult, MessageSignal, MessageSignalConvertError,
-    ProcessDiedSignal, Signal,
+    MessageSource, ProcessDiedSignal, Signal,
 };
 pub use module::{Param, WasmModule};
 #[doc(hidden)]
 pub use process_local::statik::Key as __StaticProcessLocalInner;
 pub use process_local::ProcessLocal;
 pub use process_name::ProcessName;
+pub use shutdown::on_app_shutdown;
 pub use tag::Tag;
 
 /// Implemented for all resources held by the VM.
@@ -158,3 +169,32 @@ pub trait Resource {
 pub fn sleep(duration: std::time::Duration) {
     unsafe { host::api::process::sleep_ms(duration.as_millis() as u64) };
 }
+
+/// Suspends the current process for `duration` of time, or until a message
+/// arrives in the `mailbox`.
+///
+/// Returns the message if the sleep was interrupted by one, or `None` if the
+/// whole duration passed. This allows sleeping loops to stay responsive to
+/// control messages.
+///
+/// # Panics
+///
+/// This function will panic if the received message can't be deserialized
+/// into `M` with serializer `S`, if link failures are caught and a linked
+/// process dies during the sleep, or if a monitored process dies during the
+/// sleep.
+#[track_caller]
+pub fn sleep_or_message<M, S>(mailbox: &Mailbox<M, S>, duration: std::time::Duration) -> Option<M>
+where
+    S: serializer::CanSerialize<M>,
+{
+    match mailbox.receive_timeout(duration) {
+        Ok(message) => Some(message),
+        Err(MailboxError::TimedOut) => None,
+        Err(MailboxError::LinkDied(tag)) => panic!("Linked process died during sleep: {tag:?}"),
+        Err(MailboxError::ProcessDied(id)) => panic!("Monitored process {id} died during sleep"),
+        Err(MailboxError::DeserializationFailed(err)) => {
+            panic!("Failed to deserialize message: {err}")
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::fmt;
 use std::marker::PhantomData;
 use std::time::Duration;
@@ -8,7 +9,7 @@ use thiserror::Error;
 use crate::function::process::{IntoProcess, NoLink};
 use crate::host::api::message;
 use crate::serializer::{Bincode, CanSerialize, DecodeError};
-use crate::{host, LunaticError, Process, ProcessConfig, Tag};
+use crate::{host, process_local, LunaticError, Process, ProcessConfig, Tag};
 
 pub const DATA_MESSAGE: u32 = 0;
 pub const LINK_DIED: u32 = 1;
@@ -17,6 +18,12 @@ pub const TIMEOUT: u32 = 9027;
 
 pub type MailboxResult<T, U = ()> = Result<MessageSignal<T, U>, MailboxError>;
 
+process_local! {
+    /// Signals that were sent back to the process as placeholder messages,
+    /// together with the id written into the placeholder.
+    static REQUEUED_SIGNALS: RefCell<Vec<(i64, Signal)>> = RefCell::new(Vec::new());
+}
+
 /// The mailbox of a [`Process`].
 ///
 /// Each process in lunatic gets one dedicated mailbox. Messages sent to the
@@ -42,9 +49,10 @@ pub type MailboxResult<T, U = ()> = Result<MessageSignal<T, U>, MailboxError>;
 ///
 /// By default, if a linked process fails all the links will die too. This
 /// behavior can be changed by using the [`catch_link_failure`]() function. The
-/// returned [`Mailbox<_, _, Catching>`] will receive a special
-/// [`MailboxError::LinkDied`] in its mailbox containing the [`Tag`] used when
-/// the process was spawned ([`spawn_link_tag`](Process::spawn_link_tag)).
+/// returned [`Mailbox<_, _, LinkDiedSignal>`] will receive a special
+/// [`LinkDiedSignal`] in its mailbox containing the [`Tag`] used when the
+/// process was spawned ([`spawn_link_tag`](Process::spawn_link_tag)). Other
+/// mailboxes of the same process return it as [`MailboxError::LinkDied`].
 pub struct Mailbox<M, S = Bincode, L = ()>
 where
     S: CanSerialize<M>,
@@ -52,6 +60,15 @@ where
     phantom: PhantomData<(M, S, L)>,
 }
 
+/// A message that was received and is going to be sent back to the process.
+enum Queued<M> {
+    Message(Tag, M),
+    /// A message that couldn't be deserialized.
+    Raw(Tag, Vec<u8>),
+    /// A signal, sent back as a placeholder message.
+    Signal(Signal),
+}
+
 impl<M, S> Mailbox<M, S, ()>
 where
     S: CanSerialize<M>,
@@ -67,7 +84,7 @@ where
     /// into `M` with serializer `S`.
     #[track_caller]
     pub fn receive(&self) -> M {
-        self.receive_(&[], None).unwrap().unwrap_message()
+        self.receive_message(&[], None).unwrap()
     }
 
     /// Gets next message from process' mailbox that is tagged with one of the
@@ -77,35 +94,252 @@ where
     /// arrives. If `tags` is an empty array, it will behave the same as
     /// `receive`.
     ///
+    /// The order of `tags` doesn't give any priority. The oldest message
+    /// matching any of the tags is returned, so receiving from multiple tags
+    /// is fair: a message can only be overtaken by older messages.
+    ///
     /// # Panics
     ///
     /// This function will panic if the received message can't be deserialized
     /// into `M` with serializer `S`.
     #[track_caller]
     pub fn tag_receive(&self, tags: &[Tag]) -> M {
-        self.receive_(tags, None).unwrap().unwrap_message()
+        self.receive_message(tags, None).unwrap()
     }
 
     /// Same as `receive`, but doesn't panic in case the deserialization fails.
     /// Instead, it will return [`MailboxError::DeserializationFailed`].
     pub fn try_receive(&self) -> Result<M, MailboxError> {
-        self.receive_(&[], None).map(MessageSignal::unwrap_message)
+        self.receive_message(&[], None)
     }
 
     /// Same as `receive`, but only waits for the duration of timeout for the
     /// message. If the timeout expires it will return
     /// [`MailboxError::TimedOut`].
     pub fn receive_timeout(&self, timeout: Duration) -> Result<M, MailboxError> {
-        self.receive_(&[], Some(timeout))
-            .map(MessageSignal::unwrap_message)
+        self.receive_message(&[], Some(timeout))
     }
 
     /// Same as `tag_receive`, but only waits for the duration of timeout for
     /// the message. If the timeout expires it will return
     /// [`MailboxError::TimedOut`].
     pub fn tag_receive_timeout(&self, tags: &[Tag], timeout: Duration) -> Result<M, MailboxError> {
-        self.receive_(tags, Some(timeout))
-            .map(MessageSignal::unwrap_message)
+        self.receive_message(tags, Some(timeout))
+    }
+
+    /// Gets the first message from process' mailbox that matches the
+    /// `predicate`.
+    ///
+    /// If no such message exists, this function will block until a matching
+    /// message arrives. Messages that don't match stay in the mailbox in the
+    /// same order, including messages that can't be deserialized and signals
+    /// of dead links or monitored processes.
+    ///
+    /// The host doesn't support scanning the mailbox, so the messages that
+    /// were in the mailbox when the function was called are received and sent
+    /// back to the process. This takes O(n) time in the number of queued
+    /// messages. If no queued message matches, messages that arrive while
+    /// waiting for a match can end up in front of the messages sent back.
+    ///
+    /// Messages that can't be deserialized are sent back as raw bytes, without
+    /// the resources attached to them.
+    pub fn receive_matching(&self, predicate: impl Fn(&M) -> bool) -> M {
+        let marker = self.send_marker();
+        let mut skipped = Vec::new();
+        let mut queued = true;
+        loop {
+            let (tag, message) = match self.receive_queued(marker, &mut skipped) {
+                Ok(Some(message)) => message,
+                Ok(None) => {
+                    // Only messages arriving from now on are left.
+                    queued = false;
+                    continue;
+                }
+                // Undecodable messages and signals are kept in `skipped`.
+                Err(_) => continue,
+            };
+            if predicate(&message) {
+                self.requeue(skipped, queued.then_some(marker));
+                return message;
+            }
+            skipped.push(Queued::Message(tag, message));
+        }
+    }
+
+    /// Returns a copy of the next message, leaving it in the mailbox.
+    ///
+    /// If the mailbox is empty, this function will block until a new message
+    /// arrives. If the next message can't be deserialized,
+    /// [`MailboxError::DeserializationFailed`] is returned and the message
+    /// stays in the mailbox. If it's a signal, [`MailboxError::LinkDied`] or
+    /// [`MailboxError::ProcessDied`] is returned and the signal stays in the
+    /// mailbox.
+    ///
+    /// Like [`receive_matching`](Self::receive_matching), all messages that
+    /// were in the mailbox when the function was called are received and sent
+    /// back to the process, which takes O(n) time in the number of queued
+    /// messages.
+    pub fn peek(&self) -> Result<M, MailboxError>
+    where
+        M: Clone,
+    {
+        let marker = self.send_marker();
+        let mut skipped = Vec::new();
+        let mut queued = true;
+        let result = loop {
+            match self.receive_queued(marker, &mut skipped) {
+                Ok(Some((tag, message))) => {
+                    skipped.push(Queued::Message(tag, message.clone()));
+                    break Ok(message);
+                }
+                // The mailbox was empty, wait for the next message.
+                Ok(None) => queued = false,
+                Err(err) => break Err(err),
+            }
+        };
+        self.requeue(skipped, queued.then_some(marker));
+        result
+    }
+
+    /// Receives all messages that are currently in the mailbox, without
+    /// blocking.
+    ///
+    /// The messages are returned in the order they arrived. If the mailbox is
+    /// empty, an empty `Vec` is returned. Receiving stops at the first signal
+    /// of a dead link or monitored process, which stays in the mailbox
+    /// together with the messages behind it.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if a received message can't be deserialized
+    /// into `M` with serializer `S`.
+    #[track_caller]
+    pub fn drain(&self) -> Vec<M> {
+        let mut messages = Vec::new();
+        loop {
+            match self.receive_timeout(Duration::ZERO) {
+                Ok(message) => messages.push(message),
+                Err(MailboxError::TimedOut) => return messages,
+                Err(MailboxError::LinkDied(tag)) => {
+                    let signal = Queued::Signal(Signal::LinkDied(tag));
+                    self.requeue(vec![signal], Some(self.send_marker()));
+                    return messages;
+                }
+                Err(MailboxError::ProcessDied(id)) => {
+                    let signal = Queued::Signal(Signal::ProcessDied(id));
+                    self.requeue(vec![signal], Some(self.send_marker()));
+                    return messages;
+                }
+                Err(MailboxError::DeserializationFailed(err)) => {
+                    panic!("Failed to deserialize message: {err}")
+                }
+            }
+        }
+    }
+
+    /// Receives the next message.
+    ///
+    /// If link failures are caught, a dead link is returned as
+    /// [`MailboxError::LinkDied`]. A monitored process dying is returned as
+    /// [`MailboxError::ProcessDied`].
+    fn receive_message(&self, tags: &[Tag], timeout: Option<Duration>) -> Result<M, MailboxError> {
+        match self.receive_(tags, timeout)? {
+            MessageSignal::Message(message) => Ok(message),
+            MessageSignal::Signal(signal) => Err(signal_error(signal)),
+        }
+    }
+
+    /// Sends an empty message with a new tag to the process itself.
+    ///
+    /// All messages that are in the mailbox right now are in front of it, so
+    /// receiving the marker means that all of them were received.
+    fn send_marker(&self) -> Tag {
+        let marker = Tag::new();
+        unsafe { message::create_data(marker.id(), 0) };
+        host::send(host::node_id(), host::process_id());
+        marker
+    }
+
+    /// Receives the next message together with its tag, blocking until one
+    /// arrives.
+    ///
+    /// Returns `None` when the `marker` is received. Messages that can't be
+    /// deserialized are pushed to `skipped` as raw bytes and returned as
+    /// [`MailboxError::DeserializationFailed`]. Signals are pushed to
+    /// `skipped` and returned as [`MailboxError::LinkDied`] or
+    /// [`MailboxError::ProcessDied`].
+    fn receive_queued(
+        &self,
+        marker: Tag,
+        skipped: &mut Vec<Queued<M>>,
+    ) -> Result<Option<(Tag, M)>, MailboxError> {
+        let result = self.receive_(&[], None);
+        let tag = Tag::from(unsafe { message::get_tag() });
+        match result {
+            Ok(MessageSignal::Message(_)) | Err(MailboxError::DeserializationFailed(_))
+                if tag == marker =>
+            {
+                Ok(None)
+            }
+            Ok(MessageSignal::Message(message)) => Ok(Some((tag, message))),
+            Ok(MessageSignal::Signal(signal)) => {
+                skipped.push(Queued::Signal(signal));
+                Err(signal_error(signal))
+            }
+            Err(MailboxError::DeserializationFailed(err)) => {
+                let mut data = vec![0; unsafe { message::data_size() } as usize];
+                unsafe {
+                    message::seek_data(0);
+                    message::read_data(data.as_mut_ptr(), data.len());
+                }
+                skipped.push(Queued::Raw(tag, data));
+                Err(MailboxError::DeserializationFailed(err))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Sends `messages` back to the process, followed by all messages that
+    /// are still in front of the `marker`, to preserve their order.
+    ///
+    /// If `marker` is `None`, it was already received.
+    fn requeue(&self, mut messages: Vec<Queued<M>>, marker: Option<Tag>) {
+        if let Some(marker) = marker {
+            loop {
+                match self.receive_queued(marker, &mut messages) {
+                    Ok(Some((tag, message))) => messages.push(Queued::Message(tag, message)),
+                    Ok(None) => break,
+                    // Undecodable messages and signals were already added to
+                    // `messages`.
+                    Err(_) => continue,
+                }
+            }
+        }
+        let this = self.this();
+        for message in messages {
+            match message {
+                Queued::Message(tag, message) => this.tag_send(tag, message),
+                Queued::Raw(tag, data) => {
+                    unsafe {
+                        message::create_data(tag.id(), data.len() as u64);
+                        message::write_data(data.as_ptr(), data.len());
+                    }
+                    host::send(this.node_id(), this.id());
+                }
+                // Signals can't be sent, so a placeholder takes their place and
+                // `receive_` turns it back into the signal.
+                Queued::Signal(signal) => {
+                    let id = Tag::new().id();
+                    REQUEUED_SIGNALS.with_borrow_mut(|mut signals| signals.push((id, signal)));
+                    let data = id.to_le_bytes();
+                    unsafe {
+                        message::create_data(signal_tag(signal).id(), data.len() as u64);
+                        message::write_data(data.as_ptr(), data.len());
+                    }
+                    host::send(this.node_id(), this.id());
+                }
+            }
+        }
     }
 
     /// Allow this mailbox to catch link failures.
@@ -128,6 +362,54 @@ where
     }
 }
 
+/// A source of messages of type `M`.
+///
+/// Generic code can accept any message source through this trait instead of
+/// depending on a concrete [`Mailbox`] type.
+///
+/// ```
+/// use lunatic::MessageSource;
+///
+/// fn sum(source: &impl MessageSource<u64>, count: usize) -> u64 {
+///     (0..count).map(|_| source.receive()).sum()
+/// }
+/// ```
+pub trait MessageSource<M> {
+    /// Gets the next message, blocking until one arrives.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the received message can't be
+    /// deserialized.
+    fn receive(&self) -> M;
+
+    /// Same as `receive`, but doesn't panic in case the deserialization fails.
+    fn try_receive(&self) -> Result<M, MailboxError>;
+
+    /// Same as `receive`, but only waits for the duration of timeout for the
+    /// message. If the timeout expires it will return
+    /// [`MailboxError::TimedOut`].
+    fn receive_timeout(&self, timeout: Duration) -> Result<M, MailboxError>;
+}
+
+impl<M, S> MessageSource<M> for Mailbox<M, S, ()>
+where
+    S: CanSerialize<M>,
+{
+    #[track_caller]
+    fn receive(&self) -> M {
+        self.receive()
+    }
+
+    fn try_receive(&self) -> Result<M, MailboxError> {
+        self.try_receive()
+    }
+
+    fn receive_timeout(&self, timeout: Duration) -> Result<M, MailboxError> {
+        self.receive_timeout(timeout)
+    }
+}
+
 macro_rules! impl_mailbox_receive {
     ($signal:ty) => {
         impl<M, S> Mailbox<M, S, $signal>
@@ -274,9 +556,12 @@ where
         };
         let message_type = unsafe { message::receive(tags.as_ptr(), tags.len(), timeout_ms) };
         match message_type {
-            DATA_MESSAGE => match S::decode() {
-                Ok(msg) => Ok(MessageSignal::Message(msg)),
-                Err(err) => Err(MailboxError::DeserializationFailed(err)),
+            DATA_MESSAGE => match requeued_signal() {
+                Some(signal) => Ok(MessageSignal::Signal(signal)),
+                None => match S::decode() {
+                    Ok(msg) => Ok(MessageSignal::Message(msg)),
+                    Err(err) => Err(MailboxError::DeserializationFailed(err)),
+                },
             },
             LINK_DIED => Ok(MessageSignal::Signal(Signal::LinkDied(unsafe {
                 Tag::from(message::get_tag())
@@ -316,6 +601,46 @@ where
     }
 }
 
+/// Returns the signal if the current message is a placeholder sent by
+/// `Mailbox::requeue`.
+fn requeued_signal() -> Option<Signal> {
+    REQUEUED_SIGNALS.with_borrow_mut(|mut signals| {
+        if signals.is_empty() || unsafe { message::data_size() } != 8 {
+            return None;
+        }
+        let mut data = [0; 8];
+        unsafe {
+            message::read_data(data.as_mut_ptr(), data.len());
+            message::seek_data(0);
+        }
+        let id = i64::from_le_bytes(data);
+        let tag = Tag::from(unsafe { message::get_tag() });
+        let index = signals
+            .iter()
+            .position(|&(other, signal)| other == id && signal_tag(signal) == tag)?;
+        Some(signals.remove(index).1)
+    })
+}
+
+/// Returns the tag a placeholder for `signal` is sent with.
+///
+/// Dead links keep their tag, so that they can still be received by it.
+fn signal_tag(signal: Signal) -> Tag {
+    match signal {
+        Signal::LinkDied(tag) => tag,
+        Signal::ProcessDied(_) => Tag::none(),
+    }
+}
+
+/// Returns the error a [`Mailbox`] that isn't receiving signals returns for
+/// `signal`.
+fn signal_error(signal: Signal) -> MailboxError {
+    match signal {
+        Signal::LinkDied(tag) => MailboxError::LinkDied(tag),
+        Signal::ProcessDied(id) => MailboxError::ProcessDied(id),
+    }
+}
+
 /// Result of a `recieve*` call on a [`Mailbox`].
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum MessageSignal<T, U> {
@@ -325,6 +650,7 @@ pub enum MessageSignal<T, U> {
 
 /// An error returned when receiving from a mailbox.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum MailboxError {
     /// Message failed to be deserialized.
     #[error("deserialization failed: {0}")]
@@ -332,6 +658,19 @@ pub enum MailboxError {
     /// Receive message timed out.
     #[error("timed out")]
     TimedOut,
+    /// A linked process died while the process was catching link failures.
+    ///
+    /// Contains the [`Tag`] used when the link was created. Mailboxes
+    /// returned by [`Mailbox::catch_link_failure`] receive a
+    /// [`LinkDiedSignal`] instead.
+    #[error("linked process died (tag {0:?})")]
+    LinkDied(Tag),
+    /// A monitored process died.
+    ///
+    /// Contains the id of the process. Mailboxes returned by
+    /// [`Mailbox::monitorable`] receive a [`ProcessDiedSignal`] instead.
+    #[error("monitored process {0} died")]
+    ProcessDied(u64),
 }
 
 /// A signal received when a link dies or monitored process dies.
@@ -389,6 +728,16 @@ impl MailboxError {
     pub fn is_deserialization_failed(&self) -> bool {
         matches!(self, MailboxError::DeserializationFailed(_))
     }
+
+    /// Returns true if the error is a [`MailboxError::LinkDied`].
+    pub fn is_link_died(&self) -> bool {
+        matches!(self, MailboxError::LinkDied(_))
+    }
+
+    /// Returns true if the error is a [`MailboxError::ProcessDied`].
+    pub fn is_process_died(&self) -> bool {
+        matches!(self, MailboxError::ProcessDied(_))
+    }
 }
 
 /// Error returned when converting a [`MessageSignal`].
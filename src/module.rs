@@ -53,7 +53,20 @@ impl WasmModule {
     /// Compiles a WebAssembly module.
     ///
     /// Once a module is compiled, functions like [`spawn`](Self::spawn) can be
-    /// used to spawn new processes from it.
+    /// used to spawn new processes from it. The bytes can come from anywhere,
+    /// like a file or the network, which allows loading plugins at runtime.
+    ///
+    /// Returns [`LunaticError::PermissionDenied`] if the process isn't allowed
+    /// to compile modules, see
+    /// [`ProcessConfig::set_can_compile_modules`](crate::ProcessConfig::set_can_compile_modules).
+    ///
+    /// ```no_run
+    /// use lunatic::{Process, WasmModule};
+    ///
+    /// let bytes = std::fs::read("plugin.wasm").unwrap();
+    /// let module = WasmModule::new(&bytes).unwrap();
+    /// let _plugin: Process<()> = module.spawn("run", &[]).unwrap();
+    /// ```
     pub fn new(data: &[u8]) -> Result<Self, LunaticError> {
         let mut module_or_error_id: u64 = 0;
 
@@ -0,0 +1,227 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+/// Byte order of a fixed width length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Endianness {
+    /// Most significant byte first, also known as network byte order.
+    #[default]
+    Big,
+    /// Least significant byte first.
+    Little,
+}
+
+/// Encoding of the length prefix in front of each frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LengthPrefix {
+    /// 2 bytes.
+    U16,
+    /// 4 bytes.
+    #[default]
+    U32,
+    /// 8 bytes.
+    U64,
+    /// Unsigned LEB128, as used by Protocol Buffers. The [`Endianness`] is
+    /// ignored for this encoding.
+    Varint,
+}
+
+impl LengthPrefix {
+    /// Returns the largest frame length the prefix can encode.
+    fn max_length(&self) -> u64 {
+        match self {
+            LengthPrefix::U16 => u16::MAX as u64,
+            LengthPrefix::U32 => u32::MAX as u64,
+            LengthPrefix::U64 | LengthPrefix::Varint => u64::MAX,
+        }
+    }
+}
+
+/// Splits a byte stream into frames, each preceded by its length.
+///
+/// By default, the length is encoded as a big-endian `u32`, and frames are
+/// limited to [`DEFAULT_MAX_FRAME_LENGTH`](Self::DEFAULT_MAX_FRAME_LENGTH)
+/// bytes. Both sides of a connection need to agree on the [`Endianness`] and
+/// [`LengthPrefix`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use lunatic::net::{Endianness, LengthDelimitedCodec, LengthPrefix, TcpStream};
+///
+/// let mut stream = TcpStream::connect("127.0.0.1:1337").unwrap();
+/// let mut codec = LengthDelimitedCodec::new();
+/// codec
+///     .set_endianness(Endianness::Little)
+///     .set_length_prefix(LengthPrefix::U16);
+/// codec.write_frame(&mut stream, b"hello").unwrap();
+/// let response = codec.read_frame(&mut stream).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct LengthDelimitedCodec {
+    endianness: Endianness,
+    length_prefix: LengthPrefix,
+    max_frame_length: usize,
+}
+
+impl Default for LengthDelimitedCodec {
+    fn default() -> Self {
+        LengthDelimitedCodec {
+            endianness: Endianness::default(),
+            length_prefix: LengthPrefix::default(),
+            max_frame_length: Self::DEFAULT_MAX_FRAME_LENGTH,
+        }
+    }
+}
+
+impl LengthDelimitedCodec {
+    /// The default limit for the length of a frame, 8 MiB.
+    pub const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+    /// Creates a codec with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the byte order of the length prefix.
+    pub fn set_endianness(&mut self, endianness: Endianness) -> &mut Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Returns the byte order of the length prefix.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// Sets the encoding of the length prefix.
+    pub fn set_length_prefix(&mut self, length_prefix: LengthPrefix) -> &mut Self {
+        self.length_prefix = length_prefix;
+        self
+    }
+
+    /// Returns the encoding of the length prefix.
+    pub fn length_prefix(&self) -> LengthPrefix {
+        self.length_prefix
+    }
+
+    /// Sets the maximum length of a frame.
+    ///
+    /// Reading or writing a longer frame fails, which protects against
+    /// peers announcing huge frames.
+    pub fn set_max_frame_length(&mut self, max_frame_length: usize) -> &mut Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    /// Returns the maximum length of a frame.
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    /// Writes `frame` preceded by its length.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if the frame is longer than
+    /// the maximum frame length, or than what the length prefix can encode.
+    pub fn write_frame<W: Write>(&self, writer: &mut W, frame: &[u8]) -> Result<()> {
+        let length = frame.len() as u64;
+        if frame.len() > self.max_frame_length || length > self.length_prefix.max_length() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("frame of {length} bytes is too long"),
+            ));
+        }
+        let mut buffer = Vec::with_capacity(frame.len() + 10);
+        self.encode_length(length, &mut buffer);
+        buffer.extend_from_slice(frame);
+        writer.write_all(&buffer)
+    }
+
+    /// Reads the next frame.
+    ///
+    /// Fails with [`ErrorKind::UnexpectedEof`] if the stream ends, and with
+    /// [`ErrorKind::InvalidData`] if the announced length exceeds the
+    /// maximum frame length.
+    pub fn read_frame<R: Read>(&self, reader: &mut R) -> Result<Vec<u8>> {
+        let length = self.decode_length(reader)?;
+        if length > self.max_frame_length as u64 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("frame of {length} bytes exceeds the maximum frame length"),
+            ));
+        }
+        let mut frame = vec![0; length as usize];
+        reader.read_exact(&mut frame)?;
+        Ok(frame)
+    }
+
+    fn encode_length(&self, length: u64, buffer: &mut Vec<u8>) {
+        match (self.length_prefix, self.endianness) {
+            (LengthPrefix::U16, Endianness::Big) => {
+                buffer.extend_from_slice(&(length as u16).to_be_bytes())
+            }
+            (LengthPrefix::U16, Endianness::Little) => {
+                buffer.extend_from_slice(&(length as u16).to_le_bytes())
+            }
+            (LengthPrefix::U32, Endianness::Big) => {
+                buffer.extend_from_slice(&(length as u32).to_be_bytes())
+            }
+            (LengthPrefix::U32, Endianness::Little) => {
+                buffer.extend_from_slice(&(length as u32).to_le_bytes())
+            }
+            (LengthPrefix::U64, Endianness::Big) => buffer.extend_from_slice(&length.to_be_bytes()),
+            (LengthPrefix::U64, Endianness::Little) => {
+                buffer.extend_from_slice(&length.to_le_bytes())
+            }
+            (LengthPrefix::Varint, _) => {
+                let mut length = length;
+                while length >= 0x80 {
+                    buffer.push((length as u8) | 0x80);
+                    length >>= 7;
+                }
+                buffer.push(length as u8);
+            }
+        }
+    }
+
+    fn decode_length<R: Read>(&self, reader: &mut R) -> Result<u64> {
+        let width = match self.length_prefix {
+            LengthPrefix::U16 => 2,
+            LengthPrefix::U32 => 4,
+            LengthPrefix::U64 => 8,
+            LengthPrefix::Varint => return decode_varint(reader),
+        };
+        let mut bytes = [0; 8];
+        let bytes = &mut bytes[..width];
+        reader.read_exact(bytes)?;
+        let mut length = 0;
+        match self.endianness {
+            Endianness::Big => {
+                for byte in bytes.iter() {
+                    length = (length << 8) | *byte as u64;
+                }
+            }
+            Endianness::Little => {
+                for byte in bytes.iter().rev() {
+                    length = (length << 8) | *byte as u64;
+                }
+            }
+        }
+        Ok(length)
+    }
+}
+
+fn decode_varint<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut length = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        length |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(length);
+        }
+    }
+    Err(Error::new(
+        ErrorKind::InvalidData,
+        "varint length prefix is too long",
+    ))
+}
@@ -1,11 +1,14 @@
 //! Networking related functions.
 
+mod codec;
 mod resolver;
 mod tcp_listener;
 mod tcp_stream;
 mod tls_listener;
 mod tls_stream;
 mod udp;
+#[cfg(feature = "unix_sockets")]
+mod unix;
 
 use std::io::{Error, ErrorKind, Result};
 use std::iter::Cloned;
@@ -13,12 +16,15 @@ use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV
 use std::option::IntoIter;
 use std::slice::Iter;
 
-pub use resolver::{resolve, resolve_timeout, SocketAddrIterator};
-pub use tcp_listener::TcpListener;
-pub use tcp_stream::TcpStream;
+pub use codec::{Endianness, LengthDelimitedCodec, LengthPrefix};
+pub use resolver::{resolve, resolve_one, resolve_timeout, SocketAddrIterator};
+pub use tcp_listener::{Incoming, IncomingLimited, TcpBindOptions, TcpListener};
+pub use tcp_stream::{OwnedReadHalf, OwnedWriteHalf, PartialWrite, TcpStream};
 pub use tls_listener::TlsListener;
 pub use tls_stream::TlsStream;
 pub use udp::UdpSocket;
+#[cfg(feature = "unix_sockets")]
+pub use unix::{UnixListener, UnixStream};
 
 /// A trait for objects which can be converted or resolved to one or more
 /// [`SocketAddr`] values.
@@ -38,6 +44,10 @@ pub use udp::UdpSocket;
 ///    [`SocketAddr`] as expected by its [`FromStr`] implementation or a string
 ///    like `<host_name>:<port>` pair where `<port>` is a [`u16`] value.
 ///
+///  * `(`[`&str`]`, `[`u16`]`)`, `(`[`String`]`, `[`u16`]`)`: the string should
+///    be either a string representation of an [`IpAddr`] or a host name, that
+///    is resolved and combined with the port.
+///
 /// This trait allows constructing network objects like [`TcpStream`] easily
 /// with values of various types for the bind/connection address. It is needed
 /// because sometimes one type is more appropriate than the other: for simple
@@ -155,6 +165,29 @@ impl ToSocketAddrs for String {
     }
 }
 
+impl ToSocketAddrs for (&str, u16) {
+    type Iter = std::vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> Result<Self::Iter> {
+        let (host, port) = *self;
+        // IP addresses don't need to be resolved, and IPv6 addresses can't be
+        // joined with the port without brackets.
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)].into_iter());
+        }
+        let addrs = format!("{host}:{port}").to_socket_addrs()?;
+        Ok(addrs.collect::<Vec<_>>().into_iter())
+    }
+}
+
+impl ToSocketAddrs for (String, u16) {
+    type Iter = std::vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> Result<Self::Iter> {
+        (self.0.as_str(), self.1).to_socket_addrs()
+    }
+}
+
 /* The rest is just forwarded to the standard library implementations */
 
 impl ToSocketAddrs for SocketAddr {
@@ -1,6 +1,8 @@
+use std::io::{self, ErrorKind};
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 use std::time::Duration;
 
+use super::ToSocketAddrs;
 use crate::error::LunaticError;
 use crate::host;
 
@@ -77,6 +79,27 @@ pub fn resolve_timeout(name: &str, timeout: Duration) -> Result<SocketAddrIterat
     resolve_timeout_(name, Some(timeout))
 }
 
+/// Resolves `addr` and returns the first address.
+///
+/// Returns an error with [`ErrorKind::NotFound`] if the resolution didn't
+/// yield any address.
+///
+/// ```no_run
+/// use lunatic::net::resolve_one;
+///
+/// let addr = resolve_one("localhost:80").unwrap();
+/// assert_eq!(addr.port(), 80);
+/// ```
+pub fn resolve_one<A: ToSocketAddrs>(addr: A) -> io::Result<SocketAddr> {
+    match addr.to_socket_addrs()?.next() {
+        Some(addr) => Ok(addr),
+        None => Err(io::Error::new(
+            ErrorKind::NotFound,
+            "the address didn't resolve to any socket address",
+        )),
+    }
+}
+
 fn resolve_timeout_(
     name: &str,
     timeout: Option<Duration>,
@@ -1,10 +1,12 @@
 use std::io::{Error, ErrorKind, Result};
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use super::SocketAddrIterator;
+use crate::ap::{MonitorRef, ProcessDown};
 use crate::error::LunaticError;
-use crate::host;
 use crate::net::TcpStream;
+use crate::{host, Mailbox, Process};
 
 /// A TCP server, listening for connections.
 ///
@@ -47,6 +49,34 @@ pub struct TcpListener {
     id: u64,
 }
 
+/// Options used by [`TcpListener::bind_with`].
+#[derive(Debug, Clone, Default)]
+pub struct TcpBindOptions {
+    backlog: Option<u32>,
+}
+
+impl TcpBindOptions {
+    /// Creates options with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of pending connections in the accept queue.
+    ///
+    /// The runtime doesn't support changing the backlog yet, binding with a
+    /// value different from [`TcpListener::DEFAULT_BACKLOG`] returns an error with
+    /// [`ErrorKind::Unsupported`].
+    pub fn set_backlog(&mut self, backlog: u32) -> &mut Self {
+        self.backlog = Some(backlog);
+        self
+    }
+
+    /// Returns the maximum number of pending connections in the accept queue.
+    pub fn backlog(&self) -> u32 {
+        self.backlog.unwrap_or(TcpListener::DEFAULT_BACKLOG)
+    }
+}
+
 impl Drop for TcpListener {
     fn drop(&mut self) {
         unsafe { host::api::networking::drop_tcp_listener(self.id) };
@@ -54,6 +84,9 @@ impl Drop for TcpListener {
 }
 
 impl TcpListener {
+    /// Backlog of the accept queue used by the runtime for all listeners.
+    pub const DEFAULT_BACKLOG: u32 = 1024;
+
     /// Creates a new [`TcpListener`] bound to the given address.
     ///
     /// Binding with a port number of 0 will request that the operating system
@@ -109,6 +142,29 @@ impl TcpListener {
         Err(Error::new(ErrorKind::Other, lunatic_error))
     }
 
+    /// Creates a new [`TcpListener`] bound to the given address, using
+    /// `options`.
+    ///
+    /// Connections that arrive while the accept queue is full are dropped by
+    /// the operating system. The runtime doesn't expose a count of those
+    /// drops, they can only be observed on the host (e.g. the
+    /// `ListenOverflows` counter in `netstat -s` on Linux).
+    pub fn bind_with<A>(addr: A, options: &TcpBindOptions) -> Result<Self>
+    where
+        A: super::ToSocketAddrs,
+    {
+        if options.backlog() != Self::DEFAULT_BACKLOG {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "the runtime only supports a backlog of {}",
+                    Self::DEFAULT_BACKLOG
+                ),
+            ));
+        }
+        Self::bind(addr)
+    }
+
     /// Accepts a new incoming connection.
     ///
     /// This will block and typically needs its own dedicated child process
@@ -154,4 +210,150 @@ impl TcpListener {
             Err(Error::new(ErrorKind::Other, lunatic_error))
         }
     }
+
+    /// Returns an iterator over the connections being received on this
+    /// listener.
+    ///
+    /// The iterator never returns [`None`], each call to `next` blocks until
+    /// a connection is [`accept`](Self::accept)ed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use lunatic::{net, Mailbox, Process};
+    ///
+    /// let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    /// for tcp_stream in listener.incoming() {
+    ///     Process::spawn(tcp_stream.unwrap(), |_, _: Mailbox<()>| {});
+    /// }
+    /// ```
+    pub fn incoming(&self) -> Incoming<'_> {
+        Incoming { listener: self }
+    }
+
+    /// Returns an iterator over incoming connections that will not yield a
+    /// new connection while `max_conns` handlers are still alive.
+    ///
+    /// Handler processes need to be registered with
+    /// [`IncomingLimited::track`]. They are monitored with
+    /// [`MonitorRef`]s and removed from the live count once they die.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use lunatic::{net, Mailbox, Process};
+    ///
+    /// let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let mut incoming = listener.incoming_limited(100);
+    /// while let Some(Ok(tcp_stream)) = incoming.next() {
+    ///     let handler = Process::spawn(tcp_stream, |_, _: Mailbox<()>| {});
+    ///     incoming.track(&handler);
+    /// }
+    /// ```
+    pub fn incoming_limited(&self, max_conns: usize) -> IncomingLimited<'_> {
+        IncomingLimited {
+            listener: self,
+            max_conns,
+            handlers: Vec::new(),
+        }
+    }
+}
+
+/// An iterator over incoming connections of a [`TcpListener`].
+///
+/// Created by [`TcpListener::incoming`].
+#[derive(Debug)]
+pub struct Incoming<'a> {
+    listener: &'a TcpListener,
+}
+
+impl<'a> Iterator for Incoming<'a> {
+    type Item = Result<TcpStream>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.listener.accept().map(|(tcp_stream, _peer)| tcp_stream))
+    }
+}
+
+/// An iterator over incoming connections of a [`TcpListener`], bounded by the
+/// number of live handler processes.
+///
+/// Created by [`TcpListener::incoming_limited`].
+///
+/// Dead handlers are reaped by receiving the [`ProcessDown`] messages of
+/// their monitors. Only messages with the tags of these monitors are
+/// received, other messages stay in the process' mailbox.
+///
+/// Handlers that are still tracked when the iterator is dropped stop being
+/// monitored.
+#[derive(Debug)]
+pub struct IncomingLimited<'a> {
+    listener: &'a TcpListener,
+    max_conns: usize,
+    handlers: Vec<MonitorRef>,
+}
+
+impl<'a> IncomingLimited<'a> {
+    /// Starts tracking the handler of a connection.
+    ///
+    /// The handler will count towards the limit of live connections until it
+    /// dies.
+    pub fn track<M, S>(&mut self, handler: &Process<M, S>) {
+        // Already dead handlers don't take up a slot.
+        if unsafe { host::api::process::exists(handler.id()) } != 0 {
+            self.handlers.push(MonitorRef::new(handler.id()));
+        }
+    }
+
+    /// Returns the number of tracked handlers that are still alive.
+    pub fn live(&mut self) -> usize {
+        while self.reap(Some(Duration::ZERO)) {}
+        self.handlers.len()
+    }
+
+    /// Blocks until the number of live handlers drops under the limit.
+    fn wait_for_slot(&mut self) {
+        while self.handlers.len() >= self.max_conns {
+            self.reap(None);
+        }
+    }
+
+    /// Waits for a tracked handler to die and stops tracking it.
+    ///
+    /// If `timeout` is `None`, it waits until one dies. Returns `false` if no
+    /// handler died in time.
+    fn reap(&mut self, timeout: Option<Duration>) -> bool {
+        if self.handlers.is_empty() {
+            return false;
+        }
+        let tags: Vec<_> = self.handlers.iter().map(|monitor| monitor.tag()).collect();
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<ProcessDown> = unsafe { Mailbox::new() };
+        let down = match timeout {
+            None => mailbox.tag_receive(&tags),
+            Some(timeout) => match mailbox.tag_receive_timeout(&tags, timeout) {
+                Ok(down) => down,
+                Err(_) => return false,
+            },
+        };
+        self.handlers.retain(|monitor| *monitor != down.monitor);
+        true
+    }
+}
+
+impl<'a> Drop for IncomingLimited<'a> {
+    fn drop(&mut self) {
+        for monitor in self.handlers.drain(..) {
+            monitor.demonitor();
+        }
+    }
+}
+
+impl<'a> Iterator for IncomingLimited<'a> {
+    type Item = Result<TcpStream>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.wait_for_slot();
+        Some(self.listener.accept().map(|(tcp_stream, _peer)| tcp_stream))
+    }
 }
@@ -178,6 +178,41 @@ impl TcpStream {
         }
     }
 
+    /// Splits the stream into a read and a write half.
+    ///
+    /// Each half holds its own handle to the same socket, so the halves can be
+    /// sent to different processes. The socket is closed once both halves are
+    /// dropped.
+    pub fn split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        let write = self.clone();
+        (
+            OwnedReadHalf { stream: self },
+            OwnedWriteHalf { stream: write },
+        )
+    }
+
+    /// Sets the value of the `TCP_NODELAY` option on this socket.
+    ///
+    /// The runtime doesn't expose socket options yet, so this always returns
+    /// an error with [`ErrorKind::Unsupported`].
+    pub fn set_nodelay(&self, _nodelay: bool) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "the runtime doesn't support setting TCP_NODELAY",
+        ))
+    }
+
+    /// Gets the value of the `TCP_NODELAY` option on this socket.
+    ///
+    /// The runtime doesn't expose socket options yet, so this always returns
+    /// an error with [`ErrorKind::Unsupported`].
+    pub fn nodelay(&self) -> Result<bool> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "the runtime doesn't support reading TCP_NODELAY",
+        ))
+    }
+
     /// Sets write timeout for TcpStream
     ///
     /// This method will change the timeout for everyone holding a reference to
@@ -261,8 +296,13 @@ impl TcpStream {
 
     /// Peek value on the tcp stream without removing it from internal buffer.
     /// Any subsequent calls to `peek` will read from the internal buffer
-    /// and only calls to `read` will consume the buffered data
-    pub fn peek(&mut self, buf: &mut [u8]) -> Result<usize> {
+    /// and only calls to `read` will consume the buffered data.
+    ///
+    /// Same as [`std::net::TcpStream::peek`], it returns `Ok(0)` once the
+    /// other side closed the connection and all data was read. Waiting for
+    /// data is limited by the [peek timeout](Self::set_peek_timeout), not the
+    /// read timeout.
+    pub fn peek(&self, buf: &mut [u8]) -> Result<usize> {
         let mut nread_or_error_id: u64 = 0;
         let result = unsafe {
             host::api::networking::tcp_peek(
@@ -309,6 +349,36 @@ impl Write for TcpStream {
         }
     }
 
+    /// Writes the whole buffer, retrying after short writes.
+    ///
+    /// If the [write timeout](TcpStream::set_write_timeout) expires before
+    /// everything is written, an error of kind [`ErrorKind::TimedOut`] is
+    /// returned. The error wraps a [`PartialWrite`] with the number of bytes
+    /// that were written before the timeout.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        let mut written = 0;
+        while !buf.is_empty() {
+            match self.write(buf) {
+                Ok(0) => {
+                    return Err(Error::new(
+                        ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ))
+                }
+                Ok(n) => {
+                    written += n;
+                    buf = &buf[n..];
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                Err(err) if err.kind() == ErrorKind::TimedOut => {
+                    return Err(Error::new(ErrorKind::TimedOut, PartialWrite { written }))
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
     fn flush(&mut self) -> Result<()> {
         let mut error_id = 0;
         match unsafe { host::api::networking::tcp_flush(self.id, &mut error_id as *mut u64) } {
@@ -321,6 +391,28 @@ impl Write for TcpStream {
     }
 }
 
+/// Error returned by [`TcpStream::write_all`] if the write timeout expires
+/// before the whole buffer is written.
+///
+/// It can be recovered from the returned [`std::io::Error`]:
+///
+/// ```no_run
+/// # use std::io::Write;
+/// # use lunatic::net::{PartialWrite, TcpStream};
+/// # let mut stream = TcpStream::connect("127.0.0.1:1337").unwrap();
+/// if let Err(err) = stream.write_all(b"hello") {
+///     if let Some(partial) = err.get_ref().and_then(|e| e.downcast_ref::<PartialWrite>()) {
+///         println!("only {} bytes were written", partial.written);
+///     }
+/// }
+/// ```
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("TcpStream write timed out after writing {written} bytes")]
+pub struct PartialWrite {
+    /// Number of bytes written before the timeout.
+    pub written: usize,
+}
+
 impl Read for TcpStream {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         let mut nread_or_error_id: u64 = 0;
@@ -342,3 +434,58 @@ impl Read for TcpStream {
         }
     }
 }
+
+/// The read half of a [`TcpStream`], created by [`TcpStream::split`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OwnedReadHalf {
+    stream: TcpStream,
+}
+
+impl OwnedReadHalf {
+    /// Returns the remote address this socket is connected to.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Peeks at incoming data without consuming it, see [`TcpStream::peek`].
+    pub fn peek(&self, buf: &mut [u8]) -> Result<usize> {
+        self.stream.peek(buf)
+    }
+}
+
+impl Read for OwnedReadHalf {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+/// The write half of a [`TcpStream`], created by [`TcpStream::split`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OwnedWriteHalf {
+    stream: TcpStream,
+}
+
+impl OwnedWriteHalf {
+    /// Returns the remote address this socket is connected to.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+}
+
+impl Write for OwnedWriteHalf {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        self.stream.write_vectored(bufs)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.stream.write_all(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.stream.flush()
+    }
+}
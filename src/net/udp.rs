@@ -1,6 +1,6 @@
 use std::cell::UnsafeCell;
 use std::io::{Error, ErrorKind, Result};
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
 
 use super::SocketAddrIterator;
 use crate::error::LunaticError;
@@ -198,7 +198,8 @@ impl UdpSocket {
     where
         A: super::ToSocketAddrs,
     {
-        let mut id = 0;
+        // The socket is connected in place, `error_id` is only set on failure.
+        let mut error_id = 0;
         for addr in addr.to_socket_addrs()? {
             let result = match addr {
                 SocketAddr::V4(v4_addr) => {
@@ -212,8 +213,8 @@ impl UdpSocket {
                             port,
                             0,
                             0,
-                            0, // timeout_ms
-                            &mut id as *mut u64,
+                            u64::MAX, // no timeout
+                            &mut error_id as *mut u64,
                         )
                     }
                 }
@@ -230,18 +231,17 @@ impl UdpSocket {
                             port,
                             flow_info,
                             scope_id,
-                            0, // timeout_ms
-                            &mut id as *mut u64,
+                            u64::MAX, // no timeout
+                            &mut error_id as *mut u64,
                         )
                     }
                 }
             };
             if result == 0 {
-                //self.id = id;
                 return Ok(());
             }
         }
-        let lunatic_error = LunaticError::Error(id);
+        let lunatic_error = LunaticError::Error(error_id);
         Err(Error::new(ErrorKind::Other, lunatic_error))
     }
 
@@ -436,6 +436,9 @@ impl UdpSocket {
     /// This value sets the time-to-live field that is used in every packet sent
     /// from this socket.
     ///
+    /// Returns an error with [`ErrorKind::InvalidInput`] if `ttl` isn't in the
+    /// range `1..=255`, which the operating system would reject.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -445,9 +448,14 @@ impl UdpSocket {
     /// socket.set_ttl(42).expect("set_ttl call failed");
     /// ```
     pub fn set_ttl(&self, ttl: u32) -> Result<()> {
-        // no result for this? it's () ?
+        // The host call can't report errors, so invalid values are rejected here.
+        if !(1..=255).contains(&ttl) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid TTL {ttl}, expected a value in the range 1..=255"),
+            ));
+        }
         unsafe { host::api::networking::set_udp_socket_ttl(self.id, ttl) };
-        // there is no error for this
         Ok(())
     }
 
@@ -489,7 +497,6 @@ impl UdpSocket {
     /// assert_eq!(socket.ttl().unwrap(), 42);
     /// ```
     pub fn ttl(&self) -> Result<u32> {
-        // there is no error for this?
         let result = unsafe { host::api::networking::get_udp_socket_ttl(self.id) };
         Ok(result)
     }
@@ -516,6 +523,28 @@ impl UdpSocket {
         }
     }
 
+    /// Joins a multicast group using the `IP_ADD_MEMBERSHIP` option.
+    ///
+    /// The runtime doesn't expose multicast options yet, so this always
+    /// returns an error with [`ErrorKind::Unsupported`].
+    pub fn join_multicast_v4(&self, _multiaddr: &Ipv4Addr, _interface: &Ipv4Addr) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "the runtime doesn't support joining multicast groups",
+        ))
+    }
+
+    /// Leaves a multicast group using the `IP_DROP_MEMBERSHIP` option.
+    ///
+    /// The runtime doesn't expose multicast options yet, so this always
+    /// returns an error with [`ErrorKind::Unsupported`].
+    pub fn leave_multicast_v4(&self, _multiaddr: &Ipv4Addr, _interface: &Ipv4Addr) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "the runtime doesn't support leaving multicast groups",
+        ))
+    }
+
     /// Creates a new independently owned handle to the underlying socket.
     ///
     /// The returned `UdpSocket` is a reference to the same socket that this
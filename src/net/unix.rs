@@ -0,0 +1,71 @@
+use std::convert::Infallible;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::path::{Path, PathBuf};
+
+/// A UNIX domain socket server, listening for connections.
+///
+/// The runtime doesn't expose UNIX domain sockets yet. The API mirrors
+/// [`TcpListener`](super::TcpListener), but [`bind`](UnixListener::bind)
+/// always returns an error with [`ErrorKind::Unsupported`].
+#[derive(Debug)]
+pub struct UnixListener {
+    // Can't be constructed until the runtime supports UNIX domain sockets.
+    never: Infallible,
+}
+
+impl UnixListener {
+    /// Creates a new [`UnixListener`] bound to the given path.
+    pub fn bind<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let _ = path;
+        Err(unsupported())
+    }
+
+    /// Accepts a new incoming connection.
+    ///
+    /// Returns a UNIX stream and the path of the peer.
+    pub fn accept(&self) -> Result<(UnixStream, PathBuf)> {
+        match self.never {}
+    }
+}
+
+/// A UNIX domain socket stream between a local and a remote socket.
+///
+/// The runtime doesn't expose UNIX domain sockets yet. The API mirrors
+/// [`TcpStream`](super::TcpStream), but [`connect`](UnixStream::connect)
+/// always returns an error with [`ErrorKind::Unsupported`].
+#[derive(Debug)]
+pub struct UnixStream {
+    // Can't be constructed until the runtime supports UNIX domain sockets.
+    never: Infallible,
+}
+
+impl UnixStream {
+    /// Creates a UNIX stream connected to the socket at `path`.
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let _ = path;
+        Err(unsupported())
+    }
+}
+
+impl Read for UnixStream {
+    fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+        match self.never {}
+    }
+}
+
+impl Write for UnixStream {
+    fn write(&mut self, _buf: &[u8]) -> Result<usize> {
+        match self.never {}
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self.never {}
+    }
+}
+
+fn unsupported() -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        "UNIX domain sockets are not supported by the runtime",
+    )
+}
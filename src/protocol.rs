@@ -4,9 +4,13 @@ use std::mem::ManuallyDrop;
 use std::time::Duration;
 use std::{any, fmt};
 
+use thiserror::Error;
+
+use crate::ap::MonitorRef;
 use crate::function::process::IntoProcess;
 use crate::mailbox::MailboxError;
 use crate::serializer::{Bincode, CanSerialize};
+use crate::time::Timeout;
 use crate::{host, LunaticError, Mailbox, Process, ProcessConfig, Tag};
 
 /// A value that the protocol captures from the parent process.
@@ -37,6 +41,8 @@ pub struct Protocol<P: 'static, S = Bincode, Z: 'static = ()> {
     id: u64,
     node_id: u64,
     tag: Tag,
+    // Tag of the link to the other side, if this side spawned it linked.
+    link: Option<Tag>,
     phantom: PhantomData<(P, S, Z)>,
 }
 
@@ -81,10 +87,62 @@ impl<P, S, Z> Protocol<P, S, Z> {
             id: process.id(),
             node_id: process.node_id(),
             tag,
+            link: None,
             phantom: PhantomData,
         }
     }
 
+    /// Receives the next value of the session, or returns an error if the
+    /// other side dies first.
+    ///
+    /// Local processes are monitored while waiting. Processes on other nodes
+    /// can't be monitored, so only the timeout ends the wait for them.
+    fn receive_or_died<A>(&self, timeout: Option<Duration>) -> Result<A, ProtocolError>
+    where
+        S: CanSerialize<A>,
+    {
+        let monitor = (self.node_id == host::node_id()).then(|| MonitorRef::new(self.id));
+        let mut tags = vec![self.tag];
+        tags.extend(monitor.map(|monitor| monitor.tag()));
+        tags.extend(self.link);
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<A, S> = unsafe { Mailbox::new() };
+        // A timeout of `u64::MAX` milliseconds waits forever.
+        let timeout = timeout.unwrap_or(Duration::from_millis(u64::MAX));
+        let result = mailbox.tag_receive_timeout(&tags, timeout);
+        let tag = Tag::from(unsafe { host::api::message::get_tag() });
+        if let Some(monitor) = monitor {
+            monitor.demonitor();
+        }
+        match result {
+            Err(MailboxError::TimedOut) => Err(ProtocolError::TimedOut),
+            // The value is always sent before the process dies, so it's
+            // received before the monitor notification.
+            _ if monitor.map(|monitor| monitor.tag()) == Some(tag) => {
+                Err(ProtocolError::PeerDied(self.id))
+            }
+            Ok(received) => Ok(received),
+            Err(MailboxError::LinkDied(tag)) if Some(tag) == self.link => {
+                Err(ProtocolError::PeerDied(self.id))
+            }
+            Err(MailboxError::DeserializationFailed(err)) => {
+                Err(ProtocolError::DeserializationFailed(err.to_string()))
+            }
+            // Only messages with the tags of this session are received, signals
+            // of other links and monitors stay in the mailbox.
+            Err(MailboxError::LinkDied(tag)) => unreachable!("link {tag:?} died with session tag"),
+            Err(MailboxError::ProcessDied(id)) => {
+                unreachable!("process {id} died with session tag")
+            }
+        }
+    }
+
+    /// Closes the session after an error, so that it can be dropped.
+    fn fail(self, err: ProtocolError) -> ProtocolError {
+        let _: Protocol<End, S, Z> = self.cast();
+        err
+    }
+
     /// Cast the protocol to another type.
     fn cast<P2, Z2>(self) -> Protocol<P2, S, Z2> {
         // Don't drop the session yet.
@@ -93,6 +151,7 @@ impl<P, S, Z> Protocol<P, S, Z> {
             id: self_.id,
             node_id: self_.node_id,
             tag: self_.tag,
+            link: self_.link,
             phantom: PhantomData,
         }
     }
@@ -106,12 +165,24 @@ where
     /// protocol `P`.
     #[must_use]
     pub fn send(self, message: A) -> Protocol<P, S, Z> {
-        // Don't drop the session yet.
-        let self_ = ManuallyDrop::new(self);
         // Temporarily cast to right process type.
-        let process: Process<A, S> = unsafe { Process::new(self_.node_id, self_.id) };
-        process.tag_send(self_.tag, message);
-        Protocol::from_process_with_tag(process, self_.tag)
+        let process: Process<A, S> = unsafe { Process::new(self.node_id, self.id) };
+        process.tag_send(self.tag, message);
+        self.cast()
+    }
+
+    /// Same as `send`, but returns an error instead of sending if the other
+    /// side of the session died.
+    ///
+    /// On error the session is closed and can't be continued. For sessions
+    /// with processes on other nodes it's not possible to detect that they
+    /// died, and this function will behave the same as `send`.
+    pub fn try_send(self, message: A) -> Result<Protocol<P, S, Z>, ProtocolError> {
+        if self.node_id == host::node_id() && unsafe { host::api::process::exists(self.id) } == 0 {
+            let err = ProtocolError::PeerDied(self.id);
+            return Err(self.fail(err));
+        }
+        Ok(self.send(message))
     }
 }
 
@@ -128,6 +199,81 @@ where
         let received = mailbox.tag_receive(&[self.tag]);
         (self.cast(), received)
     }
+
+    /// Same as `receive`, but only waits for the duration of `timeout`.
+    ///
+    /// If no value arrives in time, the unchanged session is returned
+    /// together with a [`Timeout`], so that receiving can be retried later.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the received value can't be deserialized
+    /// into `A` with serializer `S`, or if link failures are caught and the
+    /// process on the other side died.
+    #[allow(clippy::type_complexity)]
+    #[track_caller]
+    pub fn receive_timeout(
+        self,
+        timeout: Duration,
+    ) -> Result<(Protocol<P, S, Z>, A), (Self, Timeout)> {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<A, S> = unsafe { Mailbox::new() };
+        match mailbox.tag_receive_timeout(&[self.tag], timeout) {
+            Ok(received) => Ok((self.cast(), received)),
+            Err(MailboxError::TimedOut) => Err((self, Timeout)),
+            Err(MailboxError::LinkDied(_) | MailboxError::ProcessDied(_)) => {
+                panic!("Process {} on the other side of the protocol died", self.id)
+            }
+            Err(MailboxError::DeserializationFailed(err)) => {
+                panic!("Failed to deserialize protocol value: {err}")
+            }
+        }
+    }
+
+    /// Same as `receive`, but returns an error if the other side of the
+    /// session died or sent a value that can't be deserialized.
+    ///
+    /// On error the session is closed and can't be continued. The other side
+    /// is monitored while waiting. For sessions with processes on other nodes
+    /// it's not possible to detect that they died, and this function will
+    /// wait forever in that case.
+    pub fn try_receive(self) -> Result<(Protocol<P, S, Z>, A), ProtocolError> {
+        self.try_receive_(None)
+    }
+
+    /// Same as `try_receive`, but also returns an error if no value arrives
+    /// before `timeout` expires.
+    pub fn try_receive_timeout(
+        self,
+        timeout: Duration,
+    ) -> Result<(Protocol<P, S, Z>, A), ProtocolError> {
+        self.try_receive_(Some(timeout))
+    }
+
+    fn try_receive_(
+        self,
+        timeout: Option<Duration>,
+    ) -> Result<(Protocol<P, S, Z>, A), ProtocolError> {
+        match self.receive_or_died(timeout) {
+            Ok(received) => Ok((self.cast(), received)),
+            Err(err) => Err(self.fail(err)),
+        }
+    }
+}
+
+/// Error returned by the fallible protocol operations, like
+/// [`Protocol::try_send`] and [`Protocol::try_receive`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// The process on the other side of the session died.
+    #[error("process {0} on the other side of the protocol died")]
+    PeerDied(u64),
+    /// No value arrived in time.
+    #[error("protocol timed out")]
+    TimedOut,
+    /// A received value couldn't be deserialized.
+    #[error("protocol value failed to deserialize: {0}")]
+    DeserializationFailed(String),
 }
 
 impl<A, S, Z> Protocol<Recv<A, TaskEnd>, S, Z>
@@ -154,8 +300,69 @@ where
         let _: Protocol<TaskEnd, S, Z> = self.cast(); // Only `End` protocols can be dropped
         result
     }
+
+    /// Waits up to `timeout` for the result of the task.
+    ///
+    /// Unlike `result_timeout`, the task is given back if the result isn't
+    /// ready yet, so that waiting can be continued later.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the result can't be deserialized, or if
+    /// link failures are caught and the task died.
+    #[track_caller]
+    pub fn poll_result(self, timeout: Duration) -> Result<A, Self> {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<A, S> = unsafe { Mailbox::new() };
+        let mut tags = vec![self.tag];
+        tags.extend(self.link);
+        match mailbox.tag_receive_timeout(&tags, timeout) {
+            Ok(result) => {
+                let _: Protocol<TaskEnd, S, Z> = self.cast(); // Only `End` protocols can be dropped
+                Ok(result)
+            }
+            Err(MailboxError::TimedOut) => Err(self),
+            Err(MailboxError::LinkDied(tag)) if Some(tag) == self.link => {
+                panic!("Task {} died", self.id)
+            }
+            // Only messages with the tags of this task are received.
+            Err(MailboxError::LinkDied(tag)) => unreachable!("link {tag:?} died with task tag"),
+            Err(MailboxError::ProcessDied(id)) => unreachable!("process {id} died with task tag"),
+            Err(MailboxError::DeserializationFailed(err)) => {
+                panic!("Failed to deserialize task result: {err}")
+            }
+        }
+    }
+
+    /// Same as `result`, but returns an error if the task died without
+    /// returning a result.
+    ///
+    /// Tasks spawned with `spawn_link!` are linked to the parent, so the parent
+    /// will also die in that case, unless it's catching link failures. The
+    /// task is monitored while waiting. For tasks running on other nodes it's
+    /// not possible to detect that they died, and this function will behave
+    /// the same as `result`.
+    pub fn try_result(self) -> Result<A, TaskDied> {
+        let result = match self.receive_or_died(None) {
+            Ok(result) => Ok(result),
+            Err(ProtocolError::PeerDied(id)) => Err(TaskDied(id)),
+            Err(ProtocolError::DeserializationFailed(err)) => {
+                panic!("Failed to deserialize task result: {err}")
+            }
+            Err(ProtocolError::TimedOut) => unreachable!("waited without a timeout"),
+        };
+        let _: Protocol<TaskEnd, S, Z> = self.cast(); // Only `End` protocols can be dropped
+        result
+    }
 }
 
+/// Error returned if a task died before returning a result.
+///
+/// Contains the process ID of the task.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("task {0} died without returning a result")]
+pub struct TaskDied(pub u64);
+
 impl<P, Q, S, Z> Protocol<Choose<P, Q>, S, Z>
 where
     S: CanSerialize<bool>,
@@ -163,23 +370,19 @@ where
     /// Perform an active choice, selecting protocol `P`.
     #[must_use]
     pub fn select_left(self) -> Protocol<P, S, Z> {
-        // Don't drop the session yet.
-        let self_ = ManuallyDrop::new(self);
         // Temporarily cast to right process type.
-        let process: Process<bool, S> = unsafe { Process::new(self_.node_id, self_.id) };
-        process.tag_send(self_.tag, true);
-        Protocol::from_process_with_tag(process, self_.tag)
+        let process: Process<bool, S> = unsafe { Process::new(self.node_id, self.id) };
+        process.tag_send(self.tag, true);
+        self.cast()
     }
 
     /// Perform an active choice, selecting protocol `Q`.
     #[must_use]
     pub fn select_right(self) -> Protocol<Q, S, Z> {
-        // Don't drop the session yet.
-        let self_ = ManuallyDrop::new(self);
         // Temporarily cast to right process type.
-        let process: Process<bool, S> = unsafe { Process::new(self_.node_id, self_.id) };
-        process.tag_send(self_.tag, false);
-        Protocol::from_process_with_tag(process, self_.tag)
+        let process: Process<bool, S> = unsafe { Process::new(self.node_id, self.id) };
+        process.tag_send(self.tag, false);
+        self.cast()
     }
 }
 
@@ -189,22 +392,26 @@ where
 {
     /// Passive choice. This allows the other end of the session to select one
     /// of two options for continuing the protocol: either `P` or `Q`.
+    ///
+    /// The choice is received as a tagged `bool`, so other messages in the
+    /// mailbox are not affected.
     #[must_use]
     pub fn offer(self) -> Branch<Protocol<P, S, Z>, Protocol<Q, S, Z>> {
-        unsafe {
-            // Temporarily cast to right mailbox type.
-            let mailbox: Mailbox<bool, S> = Mailbox::new();
-            if mailbox.receive() {
-                Branch::Left(self.cast())
-            } else {
-                Branch::Right(self.cast())
-            }
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<bool, S> = unsafe { Mailbox::new() };
+        if mailbox.tag_receive(&[self.tag]) {
+            Branch::Left(self.cast())
+        } else {
+            Branch::Right(self.cast())
         }
     }
 }
 
 impl<P, S, Z> Protocol<Rec<P>, S, Z> {
-    /// Repeat Protocol
+    /// Enters the recursive protocol `P`.
+    ///
+    /// The current protocol is kept in the environment, so that reaching
+    /// [`Pop`] inside of `P` can jump back to the start.
     #[must_use]
     pub fn repeat(self) -> Protocol<P, S, Protocol<Rec<P>, S, Z>> {
         self.cast()
@@ -212,7 +419,8 @@ impl<P, S, Z> Protocol<Rec<P>, S, Z> {
 }
 
 impl<P2, S, Z> Protocol<Pop, S, Protocol<P2, S, Z>> {
-    /// Pop
+    /// Jumps back to the enclosing [`Rec`], which can be entered again with
+    /// [`repeat`](Protocol::repeat).
     #[must_use]
     pub fn pop(self) -> Protocol<P2, S, Z> {
         self.cast()
@@ -262,10 +470,14 @@ pub struct Choose<P, Q>(PhantomData<(P, Q)>);
 /// Passive choice (offer) between `P` and `Q`
 pub struct Offer<P, Q>(PhantomData<(P, Q)>);
 
-/// Allows recursively calling a protocol
+/// Recursion point of protocol `P`.
+///
+/// A [`Pop`] inside of `P` continues with `Rec<P>` again. Combined with an
+/// [`Offer`] or [`Choose`] this allows protocols to loop until one side
+/// decides to end them, e.g. `Rec<Offer<Recv<i32, Pop>, End>>`.
 pub struct Rec<P>(PhantomData<P>);
 
-/// Allows recursing
+/// Jumps back to the innermost enclosing [`Rec`].
 pub struct Pop;
 
 /// The HasDual trait defines the dual relationship between protocols.
@@ -374,7 +586,9 @@ where
                 let child = unsafe { Process::<ProtocolCapture<C>, S>::new(node_id, id) };
 
                 child.send(capture);
-                Ok(Protocol::from_process_with_tag(child, tag))
+                let mut protocol = Protocol::from_process_with_tag(child, tag);
+                protocol.link = link;
+                Ok(protocol)
             }
             Err(err) => Err(err),
         }
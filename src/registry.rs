@@ -0,0 +1,191 @@
+//! Registry entries that expire.
+//!
+//! A name registered with
+//! [`register_with_lease`](crate::ap::ProcessRef::register_with_lease) is
+//! removed from the registry if the lease isn't refreshed in time, or as soon
+//! as the owning process dies. This prevents lookups from returning processes
+//! that crashed without unregistering.
+//!
+//! The registry itself doesn't support expiring entries, so each lease is
+//! kept by a separate process that removes the name once the lease expires.
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::mailbox::MailboxError;
+use crate::{host, process_local, Mailbox, MessageSignal, Process, ProcessDiedSignal};
+
+process_local! {
+    // Names registered by this process with `register`, `register_with_lease`
+    // or `start_as`, as `(name, registry key, node_id, process_id)`. The
+    // registry can't be searched by process, so this is the only way to find
+    // the names of a process.
+    static REGISTERED: RefCell<Vec<(String, String, u64, u64)>> = RefCell::new(Vec::new());
+}
+
+/// Remembers that the current process registered `name` under the registry
+/// `key`.
+pub(crate) fn record(name: &str, key: String, node_id: u64, process_id: u64) {
+    REGISTERED.with_borrow_mut(|mut registered| {
+        registered.retain(|(_, registered_key, _, _)| *registered_key != key);
+        registered.push((name.to_string(), key, node_id, process_id));
+    });
+}
+
+/// Returns the names the current process registered for a process, in the
+/// order they were registered.
+///
+/// Names that were removed from the registry or taken over by another
+/// process are skipped.
+pub(crate) fn names_of(node_id: u64, process_id: u64) -> Vec<String> {
+    REGISTERED.with_borrow_mut(|mut registered| {
+        registered.retain(|(_, key, node_id, process_id)| is_owner(key, *node_id, *process_id));
+        registered
+            .iter()
+            .filter(|(_, _, node, id)| *node == node_id && *id == process_id)
+            .map(|(name, _, _, _)| name.clone())
+            .collect()
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+enum LeaseMessage {
+    Refresh,
+    Release,
+}
+
+/// A lease on a registry entry.
+///
+/// The entry is removed if the lease isn't [refreshed](Lease::refresh) for
+/// the duration of its TTL, or when the registered process dies. Dropping the
+/// lease doesn't release it, so it can be handed to the registered process.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Lease {
+    keeper: Process<LeaseMessage>,
+}
+
+impl Lease {
+    /// Puts `name` into the registry under `key` and spawns the process
+    /// keeping the lease.
+    ///
+    /// `key` needs to already contain the type information of the process.
+    pub(crate) fn new(
+        name: &str,
+        key: String,
+        node_id: u64,
+        process_id: u64,
+        ttl: Duration,
+    ) -> Self {
+        unsafe { host::api::registry::put(key.as_ptr(), key.len(), node_id, process_id) };
+        record(name, key.clone(), node_id, process_id);
+        let keeper = Process::spawn(
+            (key, node_id, process_id, ttl),
+            |(name, node_id, process_id, ttl), mailbox: Mailbox<LeaseMessage>| {
+                let mailbox = mailbox.monitorable();
+                // Deaths can only be observed for processes on the same node,
+                // remote owners need to refresh the lease.
+                if node_id == host::node_id() {
+                    mailbox.monitor(unsafe { Process::<()>::new(node_id, process_id) });
+                }
+                loop {
+                    match mailbox.receive_timeout(ttl) {
+                        Ok(MessageSignal::Message(LeaseMessage::Refresh)) => continue,
+                        Ok(MessageSignal::Message(LeaseMessage::Release)) => break,
+                        Ok(MessageSignal::Signal(ProcessDiedSignal(id))) if id == process_id => {
+                            break
+                        }
+                        Err(MailboxError::TimedOut) => break,
+                        _ => continue,
+                    }
+                }
+                remove_if_owned(&name, node_id, process_id);
+            },
+        );
+        Lease { keeper }
+    }
+
+    /// Extends the lease by another TTL, counted from now.
+    pub fn refresh(&self) {
+        self.keeper.send(LeaseMessage::Refresh);
+    }
+
+    /// Removes the registry entry right away.
+    pub fn release(self) {
+        self.keeper.send(LeaseMessage::Release);
+    }
+
+    /// Refreshes the lease every `interval` from a background process.
+    ///
+    /// The refreshing stops when [`KeepAlive::stop`] is called or the caller
+    /// dies. `interval` should be shorter than the TTL.
+    pub fn keep_alive(&self, interval: Duration) -> KeepAlive {
+        let owner = unsafe { Process::<()>::new(host::node_id(), host::process_id()) };
+        let refresher = Process::spawn(
+            (*self, interval, owner),
+            |(lease, interval, owner), mailbox: Mailbox<KeepAliveMessage>| {
+                let mailbox = mailbox.monitorable();
+                mailbox.monitor(owner);
+                loop {
+                    lease.refresh();
+                    match mailbox.receive_timeout(interval) {
+                        Err(MailboxError::TimedOut) => continue,
+                        // Stopped or the owner died.
+                        _ => break,
+                    }
+                }
+            },
+        );
+        KeepAlive { refresher }
+    }
+
+    /// Returns `true` if the lease hasn't expired or was released.
+    pub fn is_active(&self) -> bool {
+        self.keeper.is_alive()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum KeepAliveMessage {
+    Stop,
+}
+
+/// Handle to the background process refreshing a lease, returned by
+/// [`Lease::keep_alive`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeepAlive {
+    refresher: Process<KeepAliveMessage>,
+}
+
+impl KeepAlive {
+    /// Stops refreshing the lease.
+    ///
+    /// The lease itself stays active until its TTL runs out.
+    pub fn stop(self) {
+        self.refresher.send(KeepAliveMessage::Stop);
+    }
+}
+
+/// Removes `name` from the registry, unless it was taken over by another
+/// process in the meantime.
+fn remove_if_owned(name: &str, node_id: u64, process_id: u64) {
+    if is_owner(name, node_id, process_id) {
+        unsafe { host::api::registry::remove(name.as_ptr(), name.len()) };
+    }
+}
+
+/// Returns `true` if `name` is registered to the process.
+fn is_owner(name: &str, node_id: u64, process_id: u64) -> bool {
+    let mut registered_node_id = 0;
+    let mut registered_process_id = 0;
+    let result = unsafe {
+        host::api::registry::get(
+            name.as_ptr(),
+            name.len(),
+            &mut registered_node_id,
+            &mut registered_process_id,
+        )
+    };
+    result == 0 && registered_node_id == node_id && registered_process_id == process_id
+}
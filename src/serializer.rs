@@ -1,6 +1,7 @@
 //! Serializer implementations for messages.
 use thiserror::Error;
 
+use crate::bytes::SharedBytes;
 use crate::host::api::message;
 
 #[derive(Error, Debug)]
@@ -19,6 +20,10 @@ pub enum EncodeError {
     #[cfg_attr(docsrs, doc(cfg(feature = "protobuf_serializer")))]
     #[error("serialization to Protocol Buffers failed: {0}")]
     ProtocolBuffers(#[from] protobuf::Error),
+    #[cfg(feature = "prost_serializer")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "prost_serializer")))]
+    #[error("serialization to Protocol Buffers failed: {0}")]
+    Prost(#[from] prost::EncodeError),
     #[error("serialization failed: {0}")]
     IO(#[from] std::io::Error),
     #[error("serialization failed: {0}")]
@@ -41,6 +46,10 @@ pub enum DecodeError {
     #[cfg_attr(docsrs, doc(cfg(feature = "protobuf_serializer")))]
     #[error("deserialization from Protocol Buffers failed: {0}")]
     ProtocolBuffers(#[from] protobuf::Error),
+    #[cfg(feature = "prost_serializer")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "prost_serializer")))]
+    #[error("deserialization from Protocol Buffers failed: {0}")]
+    Prost(#[from] prost::DecodeError),
     #[error("serialization failed: {0}")]
     IO(#[from] std::io::Error),
     #[error("deserialization failed: {0}")]
@@ -170,6 +179,33 @@ where
     }
 }
 
+/// A `Json` serializer that pretty-prints messages.
+///
+/// It's useful when the messages end up in logs or are read by external
+/// tooling. Messages are decoded the same way as by [`Json`], so the two are
+/// interchangeable on the receiving side.
+#[cfg(feature = "json_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json_serializer")))]
+#[derive(Debug, Hash)]
+pub struct JsonPretty {}
+
+#[cfg(feature = "json_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json_serializer")))]
+impl<M> CanSerialize<M> for JsonPretty
+where
+    M: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn encode(message: &M) -> Result<(), EncodeError> {
+        use std::io::Write;
+        let data = serde_json::to_vec_pretty(message)?;
+        Ok(MessageRw {}.write_all(&data)?)
+    }
+
+    fn decode() -> Result<M, DecodeError> {
+        Ok(serde_json::from_reader(MessageRw {})?)
+    }
+}
+
 /// The `ProtocolBuffers` serializer can serialize any message that satisfies
 /// the trait `protobuf::Message`.
 #[cfg(feature = "protobuf_serializer")]
@@ -195,6 +231,101 @@ where
     }
 }
 
+/// The `Prost` serializer can serialize any message that satisfies the trait
+/// `prost::Message`.
+///
+/// Prost doesn't support streaming, so messages are copied between a buffer
+/// in the guest and the message scratch buffer.
+#[cfg(feature = "prost_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "prost_serializer")))]
+#[derive(Debug, Hash)]
+pub struct Prost {}
+
+#[cfg(feature = "prost_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "prost_serializer")))]
+impl<M> CanSerialize<M> for Prost
+where
+    M: prost::Message + Default,
+{
+    fn encode(message: &M) -> Result<(), EncodeError> {
+        use std::io::Write;
+        let mut data = Vec::with_capacity(message.encoded_len());
+        message.encode(&mut data)?;
+        Ok(MessageRw {}.write_all(&data)?)
+    }
+
+    fn decode() -> Result<M, DecodeError> {
+        use std::io::Read;
+        let mut data = Vec::new();
+        MessageRw {}.read_to_end(&mut data)?;
+        Ok(M::decode(data.as_slice())?)
+    }
+}
+
+/// The `Raw` serializer passes already encoded bytes through unchanged.
+///
+/// It's meant for payloads that were serialized elsewhere (e.g. protobuf
+/// messages received from another system) and shouldn't be encoded a second
+/// time. The bytes are written verbatim after a little-endian `u64` length
+/// prefix.
+///
+/// Only `Vec<u8>` and [`SharedBytes`](crate::bytes::SharedBytes) messages are
+/// supported, so `Raw` can't be used as the serializer of an
+/// [`AbstractProcess`](crate::ap::AbstractProcess), which also needs to send
+/// its own control messages.
+#[derive(Debug, Hash)]
+pub struct Raw {}
+
+impl Raw {
+    fn write(bytes: &[u8]) -> Result<(), EncodeError> {
+        use std::io::Write;
+        let mut rw = MessageRw {};
+        rw.write_all(&(bytes.len() as u64).to_le_bytes())?;
+        Ok(rw.write_all(bytes)?)
+    }
+
+    fn read() -> Result<Vec<u8>, DecodeError> {
+        use std::io::Read;
+        let mut rw = MessageRw {};
+        let mut len = [0; 8];
+        rw.read_exact(&mut len)?;
+        // The length comes from the sender, don't allocate more than the
+        // message holds.
+        let available = unsafe { message::data_size() }.saturating_sub(len.len() as u64);
+        let len = u64::from_le_bytes(len);
+        if len > available {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("length prefix {len} exceeds the {available} bytes of the message"),
+            )
+            .into());
+        }
+        let mut bytes = vec![0; len as usize];
+        rw.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl CanSerialize<Vec<u8>> for Raw {
+    fn encode(message: &Vec<u8>) -> Result<(), EncodeError> {
+        Raw::write(message)
+    }
+
+    fn decode() -> Result<Vec<u8>, DecodeError> {
+        Raw::read()
+    }
+}
+
+impl CanSerialize<SharedBytes> for Raw {
+    fn encode(message: &SharedBytes) -> Result<(), EncodeError> {
+        Raw::write(message)
+    }
+
+    fn decode() -> Result<SharedBytes, DecodeError> {
+        Ok(Raw::read()?.into())
+    }
+}
+
 /// A helper struct to read from and write to the message scratch buffer.
 ///
 /// It simplifies streaming serialization/deserialization directly from the host
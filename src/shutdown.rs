@@ -0,0 +1,94 @@
+//! Ordered cleanup when the application shuts down.
+//!
+//! Hooks registered with [`on_app_shutdown`] are run by [`run_app_shutdown`],
+//! which is called automatically when the `main` function annotated with
+//! [`#[lunatic::main]`](crate::main) returns.
+
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+
+use crate::function::reference::FuncRef;
+use crate::{host, process_local, Mailbox, Process, Tag};
+
+/// Grace period used if none is set with [`set_app_shutdown_grace_period`].
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How often a running hook is checked for having died.
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A hook together with its priority.
+type Hook = (u32, FuncRef<fn()>);
+
+process_local! {
+    static HOOKS: RefCell<Vec<Hook>> = RefCell::new(Vec::new());
+    static GRACE_PERIOD: Cell<Duration> = Cell::new(DEFAULT_GRACE_PERIOD);
+}
+
+/// Registers `hook` to run when the application shuts down.
+///
+/// Hooks run in ascending `priority` order, hooks with the same priority in
+/// the order they were registered. Each hook runs in its own process, so a
+/// panicking hook doesn't prevent the following ones from running.
+///
+/// Hooks are registered for the current process and need to be registered
+/// from the root process to run automatically.
+pub fn on_app_shutdown(priority: u32, hook: fn()) {
+    HOOKS.with_borrow_mut(|mut hooks| {
+        // Keep the hooks sorted, inserting after hooks with the same priority.
+        let index = hooks.partition_point(|(p, _)| *p <= priority);
+        hooks.insert(index, (priority, FuncRef::new(hook)));
+    });
+}
+
+/// Sets the total time all shutdown hooks together can take.
+///
+/// Once the grace period expires, the running hook is killed and the
+/// remaining hooks are skipped. Defaults to [`DEFAULT_GRACE_PERIOD`].
+pub fn set_app_shutdown_grace_period(grace_period: Duration) {
+    GRACE_PERIOD.set(grace_period);
+}
+
+/// Runs all registered shutdown hooks and returns the number of hooks that
+/// finished successfully.
+///
+/// Each hook is only run once, calling this function again will only run
+/// hooks registered in the meantime.
+pub fn run_app_shutdown() -> usize {
+    let deadline = Instant::now() + GRACE_PERIOD.get();
+    let mut finished = 0;
+    for (_, hook) in HOOKS.take() {
+        let tag = Tag::new();
+        let parent = unsafe { Process::<()>::this() };
+        let process = Process::spawn(
+            (hook, parent, tag),
+            |(hook, parent, tag), _: Mailbox<()>| {
+                hook();
+                parent.tag_send(tag, ());
+            },
+        );
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<()> = unsafe { Mailbox::new() };
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                process.kill();
+                return finished;
+            }
+            if mailbox
+                .tag_receive_timeout(&[tag], remaining.min(HOOK_POLL_INTERVAL))
+                .is_ok()
+            {
+                finished += 1;
+                break;
+            }
+            if unsafe { host::api::process::exists(process.id()) } == 0 {
+                // The hook could have finished right before the check, otherwise it panicked.
+                if mailbox.tag_receive_timeout(&[tag], Duration::ZERO).is_ok() {
+                    finished += 1;
+                }
+                break;
+            }
+        }
+    }
+    finished
+}
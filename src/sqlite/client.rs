@@ -1,4 +1,58 @@
-use super::error::SqliteError;
+use std::ops::{BitOr, BitOrAssign};
+use std::time::Duration;
+
+use super::error::{SqliteCode, SqliteError};
+use super::query::Query;
+
+/// Flags for opening a connection with [`SqliteClient::connect_with`].
+///
+/// The values match SQLite's `SQLITE_OPEN_*` constants and can be combined
+/// with `|`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OpenFlags(u32);
+
+impl OpenFlags {
+    /// Open the database for reading only.
+    pub const READ_ONLY: OpenFlags = OpenFlags(0x0000_0001);
+    /// Open the database for reading and writing.
+    pub const READ_WRITE: OpenFlags = OpenFlags(0x0000_0002);
+    /// Create the database if it doesn't exist.
+    pub const CREATE: OpenFlags = OpenFlags(0x0000_0004);
+    /// Open a private in-memory database, the path is ignored.
+    pub const MEMORY: OpenFlags = OpenFlags(0x0000_0080);
+    /// Fail if the path is a symbolic link.
+    pub const NO_FOLLOW: OpenFlags = OpenFlags(0x0100_0000);
+
+    /// Returns the raw value of the flags.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns `true` if all flags in `other` are set.
+    pub fn contains(&self, other: OpenFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for OpenFlags {
+    fn default() -> Self {
+        OpenFlags::READ_WRITE | OpenFlags::CREATE
+    }
+}
+
+impl BitOr for OpenFlags {
+    type Output = OpenFlags;
+
+    fn bitor(self, rhs: OpenFlags) -> OpenFlags {
+        OpenFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for OpenFlags {
+    fn bitor_assign(&mut self, rhs: OpenFlags) {
+        self.0 |= rhs.0;
+    }
+}
 
 /// Sqlite client witn an existing connection.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -10,23 +64,76 @@ impl SqliteClient {
     /// Connects to the Sqlite database at `path` if present, otherwise creates
     /// a new database.
     pub fn connect(path: &str) -> Result<Self, SqliteError> {
-        let connection_id = 0;
+        Self::connect_with(path, OpenFlags::default())
+    }
+
+    /// Connects to the Sqlite database at `path` with `flags`.
+    ///
+    /// Exactly one of [`OpenFlags::READ_ONLY`] and [`OpenFlags::READ_WRITE`]
+    /// needs to be set. Writes on a read-only connection fail with
+    /// [`SqliteCode::Readonly`].
+    ///
+    /// The runtime always opens databases with `READ_WRITE | CREATE` and
+    /// applies the remaining flags afterwards. Because of this, a missing file
+    /// is created even for read-only connections, and `READ_WRITE` without
+    /// `CREATE` or [`OpenFlags::NO_FOLLOW`] return an error with
+    /// [`SqliteCode::Misuse`].
+    pub fn connect_with(path: &str, flags: OpenFlags) -> Result<Self, SqliteError> {
+        let read_only = flags.contains(OpenFlags::READ_ONLY);
+        if read_only == flags.contains(OpenFlags::READ_WRITE) {
+            return Err(misuse(
+                "exactly one of READ_ONLY and READ_WRITE must be set",
+            ));
+        }
+        if !read_only && !flags.contains(OpenFlags::CREATE) {
+            return Err(misuse("the runtime always creates missing databases"));
+        }
+        if flags.contains(OpenFlags::NO_FOLLOW) {
+            return Err(misuse("the runtime doesn't support NO_FOLLOW"));
+        }
+        let path = match flags.contains(OpenFlags::MEMORY) {
+            true => ":memory:",
+            false => path,
+        };
+
+        // The host writes the id of the new connection into `connection_id`.
+        let mut connection_id: u64 = 0;
         let res = unsafe {
             lunatic_sqlite_api::guest_api::sqlite_guest_bindings::open(
                 path.as_ptr(),
                 path.len(),
-                connection_id as *mut u32,
+                &mut connection_id as *mut u64 as *mut u32,
             )
         };
         if res != 0 {
             return Err(SqliteError::default());
         }
-        Ok(SqliteClient {
+        let client = SqliteClient {
             conn: connection_id,
-        })
+        };
+        if read_only {
+            client.execute("PRAGMA query_only = ON")?;
+        }
+        Ok(client)
+    }
+
+    /// Sets how long to wait for a locked database before failing with
+    /// [`SqliteCode::Busy`].
+    ///
+    /// Connections from other processes to the same file can hold the lock
+    /// while they write. A timeout of zero fails right away.
+    pub fn set_busy_timeout(&self, duration: Duration) -> Result<(), SqliteError> {
+        self.execute(&format!("PRAGMA busy_timeout = {}", duration.as_millis()))
     }
 
     pub(crate) fn id(&self) -> u64 {
         self.conn
     }
 }
+
+fn misuse(message: &str) -> SqliteError {
+    SqliteError {
+        code: SqliteCode::Misuse,
+        message: Some(message.to_owned()),
+    }
+}
@@ -0,0 +1,75 @@
+//! Deserialization of query results into types implementing
+//! [`Deserialize`](serde::Deserialize).
+
+use serde::de::value::{Error, MapDeserializer};
+use serde::de::{DeserializeOwned, IntoDeserializer, Visitor};
+use serde::{forward_to_deserialize_any, Deserializer};
+
+use super::error::{SqliteCode, SqliteError};
+use super::query::ColumnInfo;
+use super::value::Value;
+
+/// Deserializes a row into `T`, matching the columns to fields by name.
+pub(super) fn from_row<T: DeserializeOwned>(
+    columns: &[ColumnInfo],
+    row: Vec<Value>,
+) -> Result<T, SqliteError> {
+    let fields = columns
+        .iter()
+        .map(|column| column.name())
+        .zip(row.into_iter().map(ValueDeserializer));
+    T::deserialize(MapDeserializer::<_, Error>::new(fields)).map_err(|err| SqliteError {
+        code: SqliteCode::Mismatch,
+        message: Some(format!(
+            "failed to map row into `{}`: {err}",
+            std::any::type_name::<T>()
+        )),
+    })
+}
+
+struct ValueDeserializer(Value);
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Null => visitor.visit_unit(),
+            Value::Blob(v) => visitor.visit_byte_buf(v),
+            Value::Text(v) => visitor.visit_string(v),
+            Value::Double(v) => visitor.visit_f64(v),
+            Value::Int(v) => visitor.visit_i32(v),
+            Value::Int64(v) => visitor.visit_i64(v),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    // SQLite has no boolean type, they are stored as integers.
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Int(v) => visitor.visit_bool(v != 0),
+            Value::Int64(v) => visitor.visit_bool(v != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
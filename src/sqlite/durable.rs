@@ -0,0 +1,248 @@
+use std::collections::VecDeque;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::client::SqliteClient;
+use super::error::{SqliteCode, SqliteError};
+use super::query::{Query, Row};
+use super::value::Value;
+use crate::serializer::{Bincode, CanSerialize};
+use crate::{host, Process};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS lunatic_durable_queue (
+    queue TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    message BLOB NOT NULL,
+    PRIMARY KEY (queue, seq)
+)";
+
+/// A message sent by a [`DurableSender`], tagged with its sequence number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope<M> {
+    /// Sequence number, increasing with each message sent by the same queue.
+    pub seq: u64,
+    /// The sent message.
+    pub message: M,
+}
+
+/// A sender that doesn't lose messages if the receiving process is down.
+///
+/// Messages are delivered directly while the target process is alive. If it
+/// isn't, messages are kept in an in-memory backlog of `capacity` messages.
+/// Once the backlog is full, further messages spill over into a sqlite table
+/// and are replayed in order when the target becomes reachable again.
+///
+/// # Delivery guarantees
+///
+/// The sender guarantees **at-least-once** delivery for spilled messages. A
+/// spilled message is only removed from the database after it was sent, so if
+/// the sending process dies in-between, the message will be sent again by the
+/// next `DurableSender` opened on the same queue. Receivers should use
+/// [`Dedup`] to drop the replayed duplicates. Messages held in the in-memory
+/// backlog are lost if the sending process dies.
+///
+/// Only local target processes can be detected as down, messages to remote
+/// processes are always sent directly.
+///
+/// Spilled messages are always stored encoded with
+/// [`Bincode`](crate::serializer::Bincode), independent of the serializer `S`
+/// used for sending. Messages that can't be encoded or decoded result in an
+/// error with [`SqliteCode::Mismatch`].
+pub struct DurableSender<M, S = Bincode>
+where
+    S: CanSerialize<Envelope<M>>,
+{
+    target: Process<Envelope<M>, S>,
+    client: SqliteClient,
+    queue: String,
+    capacity: usize,
+    memory: VecDeque<Envelope<M>>,
+    spilled: usize,
+    next_seq: u64,
+}
+
+impl<M, S> DurableSender<M, S>
+where
+    M: Serialize + DeserializeOwned,
+    S: CanSerialize<Envelope<M>>,
+{
+    /// Opens the durable `queue` stored in `client`, sending to `target`.
+    ///
+    /// Messages spilled by a previous sender on the same queue are replayed as
+    /// soon as the target is reachable.
+    pub fn open(
+        client: SqliteClient,
+        queue: &str,
+        target: Process<Envelope<M>, S>,
+        capacity: usize,
+    ) -> Result<Self, SqliteError> {
+        client.execute(CREATE_TABLE)?;
+        let row = client
+            .prepare_query("SELECT MAX(seq), COUNT(*) FROM lunatic_durable_queue WHERE queue = ?")
+            .bind(queue)
+            .execute_rows()
+            .next()
+            .transpose()?;
+        let (max_seq, count) = match row.as_ref().map(|row| row.values()) {
+            Some([max_seq, count]) => (max_seq.as_int_any(), count.as_int_any().unwrap_or(0)),
+            _ => (None, 0),
+        };
+        let mut sender = DurableSender {
+            target,
+            client,
+            queue: queue.to_owned(),
+            capacity,
+            memory: VecDeque::new(),
+            spilled: count as usize,
+            next_seq: max_seq.map(|seq| seq as u64 + 1).unwrap_or(0),
+        };
+        sender.drain()?;
+        Ok(sender)
+    }
+
+    /// Sends `message` to the target, or queues it if the target is down.
+    ///
+    /// Fails if older spilled messages can't be replayed or `message` can't be
+    /// spilled.
+    pub fn send(&mut self, message: M) -> Result<(), SqliteError> {
+        let envelope = Envelope {
+            seq: self.next_seq,
+            message,
+        };
+        self.next_seq += 1;
+
+        // Deliver in order, older queued messages need to be sent first.
+        self.drain()?;
+        if self.pending() == 0 && self.is_reachable() {
+            self.target.send(envelope);
+        } else if self.spilled == 0 && self.memory.len() < self.capacity {
+            self.memory.push_back(envelope);
+        } else {
+            self.spill(&envelope)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the target process and sends all queued messages to it.
+    ///
+    /// Returns the number of messages sent.
+    pub fn reconnect(&mut self, target: Process<Envelope<M>, S>) -> Result<usize, SqliteError> {
+        self.target = target;
+        self.drain()
+    }
+
+    /// Sends all queued messages if the target is reachable.
+    ///
+    /// Returns the number of messages sent. If a spilled message can't be
+    /// decoded, the messages before it are still sent and the failing message
+    /// stays in the database, so later calls fail until it's removed.
+    pub fn drain(&mut self) -> Result<usize, SqliteError> {
+        if !self.is_reachable() {
+            return Ok(0);
+        }
+        let mut sent = 0;
+        while let Some(envelope) = self.memory.pop_front() {
+            self.target.send(envelope);
+            sent += 1;
+        }
+        if self.spilled > 0 {
+            let rows = self
+                .client
+                .prepare_query(
+                    "SELECT seq, message FROM lunatic_durable_queue WHERE queue = ? ORDER BY seq",
+                )
+                .bind(self.queue.as_str())
+                .execute_rows()
+                .collect::<Result<Vec<Row>, _>>()?;
+            for row in rows {
+                let (seq, message) = match row.values() {
+                    [Value::Int64(seq), Value::Blob(message)] => (*seq, message),
+                    _ => return Err(mismatch("durable queue row without seq and message")),
+                };
+                let message = bincode::deserialize(message).map_err(|err| {
+                    mismatch(&format!("failed to decode spilled message {seq}: {err}"))
+                })?;
+                let envelope = Envelope {
+                    seq: seq as u64,
+                    message,
+                };
+                self.target.send(envelope);
+                // Only remove the message after it was sent, this makes delivery at-least-once.
+                self.client
+                    .prepare_query("DELETE FROM lunatic_durable_queue WHERE queue = ? AND seq = ?")
+                    .bind(self.queue.as_str())
+                    .bind(seq)
+                    .execute_rows()
+                    .try_for_each(|row| row.map(drop))?;
+                self.spilled = self.spilled.saturating_sub(1);
+                sent += 1;
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Returns the number of messages waiting to be delivered.
+    pub fn pending(&self) -> usize {
+        self.memory.len() + self.spilled
+    }
+
+    /// Returns the number of messages stored in the database.
+    pub fn spilled(&self) -> usize {
+        self.spilled
+    }
+
+    fn is_reachable(&self) -> bool {
+        self.target.node_id() != host::node_id() || self.target.is_alive()
+    }
+
+    fn spill(&mut self, envelope: &Envelope<M>) -> Result<(), SqliteError> {
+        let message = bincode::serialize(&envelope.message)
+            .map_err(|err| mismatch(&format!("failed to encode spilled message: {err}")))?;
+        self.client
+            .prepare_query(
+                "INSERT INTO lunatic_durable_queue (queue, seq, message) VALUES (?, ?, ?)",
+            )
+            .bind(self.queue.as_str())
+            .bind(envelope.seq as i64)
+            .bind(message)
+            .execute_rows()
+            .try_for_each(|row| row.map(drop))?;
+        self.spilled += 1;
+        Ok(())
+    }
+}
+
+fn mismatch(message: &str) -> SqliteError {
+    SqliteError {
+        code: SqliteCode::Mismatch,
+        message: Some(message.to_owned()),
+    }
+}
+
+/// Drops messages that were already received from a [`DurableSender`].
+///
+/// Messages from one sender arrive in order, so it's enough to remember the
+/// highest sequence number seen.
+#[derive(Debug, Default, Clone)]
+pub struct Dedup {
+    last_seq: Option<u64>,
+}
+
+impl Dedup {
+    /// Creates a new, empty `Dedup`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the message if it wasn't seen before, otherwise `None`.
+    pub fn accept<M>(&mut self, envelope: Envelope<M>) -> Option<M> {
+        match self.last_seq {
+            Some(last_seq) if envelope.seq <= last_seq => None,
+            _ => {
+                self.last_seq = Some(envelope.seq);
+                Some(envelope.message)
+            }
+        }
+    }
+}
@@ -7,19 +7,28 @@
 //!
 //! let client = SqliteClient::connect("app.db")?;
 //!
-//! // Query users
-//! let mut rows = client.query("select * from users");
+//! // Query users, fetching one row at a time
+//! let rows = client.query_iter("select * from users");
 //! for row in rows {
+//!     let row = row?;
+//!     let name = row.get_named("name");
 //!     // ...
 //! }
 //! ```
 
 mod client;
+mod de;
+mod durable;
 mod error;
 mod query;
+mod snapshot;
+mod transaction;
 mod value;
 
 pub use client::*;
+pub use durable::*;
 pub use error::*;
 pub use query::*;
+pub use snapshot::*;
+pub use transaction::*;
 pub use value::*;
@@ -1,7 +1,12 @@
+use std::cell::OnceCell;
+use std::rc::Rc;
+
 use lunatic_sqlite_api::guest_api::sqlite_guest_bindings as bindings;
 use lunatic_sqlite_api::wire_format::{BindKey, BindList, BindPair, SqliteRow};
+use serde::de::DeserializeOwned;
 
 use super::client::SqliteClient;
+use super::de;
 use super::error::{SqliteCode, SqliteError, SqliteErrorExt};
 use super::value::Value;
 use crate::host::call_host_alloc;
@@ -14,6 +19,26 @@ pub trait Query {
     fn prepare_query(&self, query: &str) -> Statement;
     /// Executes a query, ignoring any results.
     fn execute(&self, query: &str) -> Result<(), SqliteError>;
+
+    /// Executes a query with no bindings, returning an iterator over rows.
+    ///
+    /// Unlike [`query`](Query::query), rows are fetched one at a time while
+    /// iterating, so large results are never buffered in memory.
+    fn query_iter(&self, query: &str) -> Rows {
+        self.prepare_query(query).execute_rows()
+    }
+
+    /// Executes a query with no bindings and deserializes each row into `T`.
+    ///
+    /// Columns are matched to the fields of `T` by name. Missing columns or
+    /// values of the wrong type result in an error with
+    /// [`SqliteCode::Mismatch`].
+    fn query_as<T: DeserializeOwned>(&self, query: &str) -> Result<Vec<T>, SqliteError>
+    where
+        Self: Sized,
+    {
+        self.prepare_query(query).execute_as()
+    }
 }
 
 impl Query for SqliteClient {
@@ -66,6 +91,17 @@ impl Statement {
         self
     }
 
+    /// Bind to the parameter at `index`, e.g. `?2` for index 2.
+    ///
+    /// Parameter indexes start at 1.
+    pub fn bind_index(mut self, index: usize, value: impl Into<Value>) -> Self {
+        self.bindings.0.push(BindPair(
+            BindKey::Numeric(index),
+            Into::<Value>::into(value).into(),
+        ));
+        self
+    }
+
     /// Bind based on a name.
     pub fn bind_named(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
         self.bindings.0.push(BindPair(
@@ -80,6 +116,26 @@ impl Statement {
         self.execute_iter().collect()
     }
 
+    /// Executes the query and deserializes each row into `T`, see
+    /// [`Query::query_as`].
+    pub fn execute_as<T: DeserializeOwned>(self) -> Result<Vec<T>, SqliteError> {
+        let mut rows = self.execute_iter();
+        let mut result = Vec::new();
+        while let Some(row) = rows.next() {
+            result.push(de::from_row(rows.columns(), row)?);
+        }
+        Ok(result)
+    }
+
+    /// Executes the query returning an iterator over [`Row`]s, see
+    /// [`Query::query_iter`].
+    pub fn execute_rows(self) -> Rows {
+        Rows {
+            rows: self.execute_iter(),
+            columns: OnceCell::new(),
+        }
+    }
+
     /// Executes the query returning an iterator over rows.
     ///
     /// The query will not be executed until the iter is iterated upon.
@@ -87,7 +143,10 @@ impl Statement {
         let encoded = bincode::serialize(&self.bindings).unwrap();
         unsafe { bindings::bind_value(self.id, encoded.as_ptr() as u32, encoded.len() as u32) };
 
-        QueryIter { statement: self }
+        QueryIter {
+            statement: self,
+            columns: OnceCell::new(),
+        }
     }
 }
 
@@ -99,31 +158,151 @@ impl Drop for Statement {
     }
 }
 
+/// Metadata of a column in a query result.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ColumnInfo {
+    name: String,
+}
+
+impl ColumnInfo {
+    /// Returns the name of the column, as assigned by the `AS` clause or
+    /// derived from the expression.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 /// Iterator for iterating query result rows.
 pub struct QueryIter {
     statement: Statement,
+    columns: OnceCell<Vec<ColumnInfo>>,
 }
 
-impl Iterator for QueryIter {
-    type Item = Vec<Value>;
+impl QueryIter {
+    /// Returns the columns of the result set.
+    ///
+    /// The metadata is read from the prepared statement, so it's available
+    /// before the first row is fetched and even if the query returns no rows.
+    pub fn columns(&self) -> &[ColumnInfo] {
+        self.columns.get_or_init(|| {
+            call_host_alloc::<Vec<String>>(|len_ptr| unsafe {
+                bindings::column_names(self.statement.id, len_ptr)
+            })
+            .unwrap()
+            .into_iter()
+            .map(|name| ColumnInfo { name })
+            .collect()
+        })
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match SqliteCode::from_code(unsafe { bindings::sqlite3_step(self.statement.id) }) {
+    /// Steps the statement and reads the next row.
+    fn try_next(&mut self) -> Option<Result<Vec<Value>, SqliteError>> {
+        let code = SqliteCode::from_code(unsafe { bindings::sqlite3_step(self.statement.id) });
+        match code {
             Some(SqliteCode::Done) => return None,
             Some(SqliteCode::Row) => {}
-            Some(code) => panic!("unexpected code {code:?} from lunatic::sqlite::sqlite3_step. Expected SQLITE_DONE or SQLITE_ROW"),
-            None => panic!("unexpected code from lunatic::sqlite::sqlite3_step. Expected SQLITE_DONE or SQLITE_ROW"),
+            _ => {
+                return Some(Err(SqliteError {
+                    code: code.unwrap_or(SqliteCode::Error),
+                    message: Some(format!(
+                        "expected SQLITE_DONE or SQLITE_ROW from sqlite3_step, got {code:?}"
+                    )),
+                }))
+            }
         }
 
-        Some(
-            call_host_alloc::<SqliteRow>(|len_ptr| unsafe {
-                bindings::read_row(self.statement.id, len_ptr)
-            })
-            .unwrap()
-            .0
-            .into_iter()
-            .map(|value| value.into())
-            .collect(),
-        )
+        let row = call_host_alloc::<SqliteRow>(|len_ptr| unsafe {
+            bindings::read_row(self.statement.id, len_ptr)
+        })
+        .map_err(|err| SqliteError {
+            code: SqliteCode::Error,
+            message: Some(format!("failed to read row: {err}")),
+        });
+        Some(row.map(|row| row.0.into_iter().map(|value| value.into()).collect()))
+    }
+}
+
+impl Iterator for QueryIter {
+    type Item = Vec<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next()
+            .map(|row| row.unwrap_or_else(|err| panic!("{err}")))
+    }
+}
+
+/// Iterator over the rows of a query result, created by
+/// [`Query::query_iter`] or [`Statement::execute_rows`].
+///
+/// Rows are fetched one at a time while iterating. Unlike [`QueryIter`],
+/// errors while stepping through the result are returned instead of
+/// panicking.
+pub struct Rows {
+    rows: QueryIter,
+    columns: OnceCell<Rc<[ColumnInfo]>>,
+}
+
+impl Rows {
+    /// Returns the columns of the result set, see [`QueryIter::columns`].
+    pub fn columns(&self) -> &[ColumnInfo] {
+        self.rows.columns()
+    }
+}
+
+impl Iterator for Rows {
+    type Item = Result<Row, SqliteError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let values = match self.rows.try_next()? {
+            Ok(values) => values,
+            Err(err) => return Some(Err(err)),
+        };
+        let columns = self
+            .columns
+            .get_or_init(|| self.rows.columns().into())
+            .clone();
+        Some(Ok(Row { columns, values }))
+    }
+}
+
+/// A row of a query result.
+///
+/// Values can be accessed by the index or the name of their column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    // Shared by all rows of the same result.
+    columns: Rc<[ColumnInfo]>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    /// Returns the value of the column at `index`, or `None` if there is no
+    /// such column.
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.values.get(index)
+    }
+
+    /// Returns the value of the column named `name`, or `None` if there is no
+    /// such column.
+    ///
+    /// If multiple columns have the same name, the first one is returned.
+    pub fn get_named(&self, name: &str) -> Option<&Value> {
+        let index = self.columns.iter().position(|column| column.name == name)?;
+        self.values.get(index)
+    }
+
+    /// Returns the columns of the row.
+    pub fn columns(&self) -> &[ColumnInfo] {
+        &self.columns
+    }
+
+    /// Returns the values of the row, in column order.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Consumes the row, returning its values in column order.
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
     }
 }
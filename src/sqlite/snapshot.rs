@@ -0,0 +1,67 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use super::client::SqliteClient;
+use super::query::Query;
+use super::value::Value;
+use crate::ap::SnapshotStore;
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS lunatic_snapshots (
+    key TEXT PRIMARY KEY NOT NULL,
+    snapshot BLOB NOT NULL
+)";
+
+/// Stores [`AbstractProcess`](crate::AbstractProcess) snapshots in a sqlite
+/// database.
+///
+/// Each store keeps one snapshot under `key`, so multiple processes can share
+/// the same database.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SqliteSnapshotStore {
+    path: String,
+    key: String,
+}
+
+impl SqliteSnapshotStore {
+    /// Creates a store that keeps the snapshot under `key` in the database at
+    /// `path`.
+    pub fn new(path: &str, key: &str) -> Self {
+        SqliteSnapshotStore {
+            path: path.to_owned(),
+            key: key.to_owned(),
+        }
+    }
+
+    fn connect(&self) -> io::Result<SqliteClient> {
+        let client = SqliteClient::connect(&self.path).map_err(io::Error::other)?;
+        client.execute(CREATE_TABLE).map_err(io::Error::other)?;
+        Ok(client)
+    }
+}
+
+impl SnapshotStore for SqliteSnapshotStore {
+    fn save(&self, snapshot: &[u8]) -> io::Result<()> {
+        self.connect()?
+            .prepare_query("INSERT OR REPLACE INTO lunatic_snapshots (key, snapshot) VALUES (?, ?)")
+            .bind(self.key.as_str())
+            .bind(snapshot.to_vec())
+            .execute_rows()
+            .try_for_each(|row| row.map(drop))
+            .map_err(io::Error::other)
+    }
+
+    fn load(&self) -> io::Result<Option<Vec<u8>>> {
+        let row = self
+            .connect()?
+            .prepare_query("SELECT snapshot FROM lunatic_snapshots WHERE key = ?")
+            .bind(self.key.as_str())
+            .execute_rows()
+            .next()
+            .transpose()
+            .map_err(io::Error::other)?;
+        Ok(row
+            .and_then(|row| row.into_values().into_iter().next())
+            .and_then(Value::into_blob))
+    }
+}
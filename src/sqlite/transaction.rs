@@ -0,0 +1,66 @@
+use super::client::SqliteClient;
+use super::error::SqliteError;
+use super::query::{Query, Statement};
+use super::value::Value;
+
+impl SqliteClient {
+    /// Starts a transaction.
+    ///
+    /// The returned guard rolls the transaction back when it's dropped, unless
+    /// it was [committed](Transaction::commit).
+    pub fn transaction(&self) -> Result<Transaction, SqliteError> {
+        self.execute("BEGIN")?;
+        Ok(Transaction {
+            client: *self,
+            finished: false,
+        })
+    }
+}
+
+/// A transaction started with [`SqliteClient::transaction`].
+///
+/// Queries are executed through the [`Query`] trait and only become visible
+/// to other connections after [`commit`](Transaction::commit).
+#[derive(Debug)]
+pub struct Transaction {
+    client: SqliteClient,
+    finished: bool,
+}
+
+impl Transaction {
+    /// Commits the transaction.
+    pub fn commit(mut self) -> Result<(), SqliteError> {
+        self.finished = true;
+        self.client.execute("COMMIT")
+    }
+
+    /// Rolls the transaction back.
+    pub fn rollback(mut self) -> Result<(), SqliteError> {
+        self.finished = true;
+        self.client.execute("ROLLBACK")
+    }
+}
+
+impl Query for Transaction {
+    fn query(&self, query: &str) -> Vec<Vec<Value>> {
+        self.client.query(query)
+    }
+
+    fn prepare_query(&self, query: &str) -> Statement {
+        self.client.prepare_query(query)
+    }
+
+    fn execute(&self, query: &str) -> Result<(), SqliteError> {
+        self.client.execute(query)
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            // Errors can't be reported from `drop`, a failed rollback leaves
+            // the transaction open on the connection.
+            let _ = self.client.execute("ROLLBACK");
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use lunatic_sqlite_api::wire_format::{BindValue, SqliteValue};
 use serde::{Deserialize, Serialize};
 
+use super::error::{SqliteCode, SqliteError};
+
 /// Sqlite value for binding in queries.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Value {
@@ -121,6 +123,53 @@ where
     }
 }
 
+macro_rules! impl_try_from_value {
+    ($t: ty, $name: literal, $($v: ident => $convert: expr),+) => {
+        impl TryFrom<Value> for $t {
+            type Error = SqliteError;
+
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                match value {
+                    $(Value::$v(v) => $convert(v),)+
+                    other => Err(mismatch(&other, $name)),
+                }
+            }
+        }
+    };
+}
+
+impl_try_from_value!(Vec<u8>, "a blob", Blob => Ok);
+impl_try_from_value!(String, "text", Text => Ok);
+impl_try_from_value!(f64, "a double", Double => Ok);
+impl_try_from_value!(
+    i32,
+    "an i32",
+    Int => Ok,
+    Int64 => |v: i64| i32::try_from(v).map_err(|_| mismatch(&Value::Int64(v), "an i32"))
+);
+impl_try_from_value!(i64, "an i64", Int => |v: i32| Ok(v as i64), Int64 => Ok);
+
+impl<T> TryFrom<Value> for Option<T>
+where
+    T: TryFrom<Value, Error = SqliteError>,
+{
+    type Error = SqliteError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::try_from(value).map(Some),
+        }
+    }
+}
+
+fn mismatch(value: &Value, expected: &str) -> SqliteError {
+    SqliteError {
+        code: SqliteCode::Mismatch,
+        message: Some(format!("expected {expected}, found {value:?}")),
+    }
+}
+
 impl From<Value> for BindValue {
     fn from(value: Value) -> Self {
         match value {
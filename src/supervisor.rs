@@ -1,11 +1,14 @@
+use std::collections::VecDeque;
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
 use crate::ap::handlers::{DeferredRequest, Request};
 use crate::ap::{
-    AbstractProcess, Config, DeferredRequestHandler, DeferredResponse, ProcessRef, RequestHandler,
-    State,
+    AbstractProcess, Config, DeferredRequestHandler, DeferredResponse, ExitValue, ProcessRef,
+    RequestHandler, State,
 };
 use crate::function::process::{process_name, ProcessType};
+use crate::mailbox::LINK_DIED;
 use crate::serializer::Bincode;
 use crate::{host, Tag};
 
@@ -68,7 +71,11 @@ where
     type Arg = T::Arg;
     type State = SupervisorConfig<T>;
     type Serializer = Bincode;
-    type Handlers = (Request<GetChildren>, DeferredRequest<ShutdownSubscribe>);
+    type Handlers = (
+        Request<GetChildren>,
+        DeferredRequest<ShutdownSubscribe>,
+        Request<GetExitValue>,
+    );
     type StartupError = ();
 
     fn init(config: Config<Self>, arg: T::Arg) -> Result<Self::State, ()> {
@@ -97,7 +104,43 @@ where
     }
 
     fn handle_link_death(mut sup_config: State<Self>, tag: Tag) {
-        T::Children::handle_failure(&mut sup_config, tag);
+        let period = sup_config.restart_period;
+        sup_config.dead_children.prune(period);
+        let child_id = T::Children::child_id(&sup_config, tag);
+        if sup_config.dead_children.take_link(tag) {
+            // The exit was handled as a normal one before this signal arrived. Restart the child
+            // now if it's still in place, because it failed after all.
+            if child_id.is_some()
+                && T::Children::child_restart(&sup_config, tag) != ChildRestart::Temporary
+            {
+                T::Children::handle_failure(&mut sup_config, tag);
+            }
+            return;
+        }
+        if let Some(child_id) = child_id {
+            sup_config.dead_children.insert(child_id, tag);
+        }
+        // Temporary children are never restarted.
+        if T::Children::child_restart(&sup_config, tag) != ChildRestart::Temporary {
+            T::Children::handle_failure(&mut sup_config, tag);
+        }
+    }
+
+    fn handle_process_death(mut sup_config: State<Self>, process_id: u64) {
+        let period = sup_config.restart_period;
+        sup_config.dead_children.prune(period);
+        // Values of restarted children can't be requested anymore.
+        if sup_config.dead_children.take_process(process_id)
+            || !T::Children::handle_exit(&mut sup_config, process_id)
+        {
+            sup_config
+                .exit_values
+                .retain(|(child_id, _)| *child_id != process_id);
+        }
+    }
+
+    fn handle_exit_value(mut sup_config: State<Self>, process_id: u64, value: ExitValue) {
+        sup_config.exit_values.push((process_id, value));
     }
 }
 
@@ -153,33 +196,96 @@ where
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct GetExitValue(u64);
+impl<T> RequestHandler<GetExitValue> for T
+where
+    T: Supervisor,
+    T: AbstractProcess<State = SupervisorConfig<T>, Serializer = Bincode>,
+{
+    type Response = Option<ExitValue>;
+
+    fn handle(state: State<Self>, GetExitValue(process_id): GetExitValue) -> Self::Response {
+        state
+            .exit_values
+            .iter()
+            .find(|(child_id, _)| *child_id == process_id)
+            .map(|(_, value)| value.clone())
+    }
+}
+
 impl<T> ProcessRef<T>
 where
     T: Supervisor,
     T: AbstractProcess<State = SupervisorConfig<T>, Serializer = Bincode>,
 {
-    pub fn children(&self) -> <<T as Supervisor>::Children as Supervisable<T>>::Processes {
+    /// Returns the final value of a child that exited normally and was not
+    /// restarted.
+    ///
+    /// Children set the value with [`set_exit_value`](crate::ap::set_exit_value)
+    /// before they exit. `None` is returned if the child is still running,
+    /// didn't set a value or was restarted.
+    pub fn exit_value<C: AbstractProcess>(&self, child: &ProcessRef<C>) -> Option<ExitValue> {
+        self.request(GetExitValue(child.id()))
+    }
+
+    /// Returns references to the currently running children.
+    ///
+    /// The references are returned in the same order as the children are
+    /// defined in [`Supervisor::Children`]. Restarted children get new
+    /// references, so they should be requested again after a restart.
+    pub fn children(&self) -> SupervisorChildren<T> {
         self.request(GetChildren)
     }
 }
 
+/// A tuple of [`ProcessRef`]s to the children of supervisor `T`.
+pub type SupervisorChildren<T> = <<T as Supervisor>::Children as Supervisable<T>>::Processes;
+
+/// Defines which children are restarted when one of them exits.
 pub enum SupervisorStrategy {
+    /// Only the exited child is restarted.
     OneForOne,
+    /// All children are shut down and restarted.
     OneForAll,
+    /// The exited child and all children started after it are restarted,
+    /// earlier children keep running.
     RestForOne,
 }
 
+/// Defines when the children of a [`Supervisor`] are restarted.
+///
+/// A child exits abnormally if it fails (panics or gets killed). It exits
+/// normally if it's shut down by someone else than the supervisor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildRestart {
+    /// The child is always restarted, even after a normal exit.
+    Permanent,
+    /// The child is only restarted after an abnormal exit.
+    Transient,
+    /// The child is never restarted.
+    Temporary,
+}
+
 pub struct SupervisorConfig<T>
 where
     T: Supervisor,
 {
     strategy: SupervisorStrategy,
+    restart: ChildRestart,
+    max_restarts: Option<u32>,
+    restart_period: Duration,
+    restarts: VecDeque<Instant>,
+    backoff: Option<Backoff>,
     children: Option<<<T as Supervisor>::Children as Supervisable<T>>::Processes>,
     children_args: Option<<<T as Supervisor>::Children as Supervisable<T>>::Args>,
     children_names: Option<<<T as Supervisor>::Children as Supervisable<T>>::Names>,
     children_configs: Option<<<T as Supervisor>::Children as Supervisable<T>>::Configs>,
+    children_restarts: Option<<<T as Supervisor>::Children as Supervisable<T>>::Restarts>,
     children_tags: Option<<<T as Supervisor>::Children as Supervisable<T>>::Tags>,
     terminate_subscribers: Vec<DeferredResponse<(), T>>,
+    exit_values: Vec<(u64, ExitValue)>,
+    dead_children: DeadChildren,
     phantom: PhantomData<T>,
 }
 
@@ -191,6 +297,58 @@ where
         self.strategy = strategy;
     }
 
+    /// Sets when children are restarted.
+    ///
+    /// It can be overwritten per child with [`set_restarts`](Self::set_restarts).
+    /// Default value is [`ChildRestart::Permanent`].
+    pub fn set_restart(&mut self, restart: ChildRestart) {
+        self.restart = restart;
+    }
+
+    /// Sets the maximum number of restarts allowed within the
+    /// [restart period](Self::set_restart_period).
+    ///
+    /// If more restarts happen, the supervisor shuts down the remaining
+    /// children and exits with a panic. By default, the number of restarts
+    /// is not limited.
+    pub fn set_max_restarts(&mut self, count: u32) {
+        self.max_restarts = Some(count);
+    }
+
+    /// Sets the time window in which restarts are counted.
+    ///
+    /// It's also the time after which the [backoff](Self::set_backoff) is
+    /// reset if no restart happened. Default value is 5 seconds.
+    pub fn set_restart_period(&mut self, period: Duration) {
+        self.restart_period = period;
+    }
+
+    /// Delays restarts, starting with `initial` and multiplying the delay by
+    /// `multiplier` after each restart, up to `max`.
+    ///
+    /// The supervisor doesn't handle any requests while it waits. The delay
+    /// is reset to `initial` once no restart happened for the duration of the
+    /// [restart period](Self::set_restart_period).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `multiplier` is not a finite number greater than or equal to
+    /// `1.0`.
+    #[track_caller]
+    pub fn set_backoff(&mut self, initial: Duration, max: Duration, multiplier: f64) {
+        assert!(
+            multiplier.is_finite() && multiplier >= 1.0,
+            "backoff multiplier must be finite and at least 1.0, got {multiplier}"
+        );
+        self.backoff = Some(Backoff {
+            initial,
+            max,
+            multiplier,
+            delay: initial,
+            last_restart: None,
+        });
+    }
+
     pub fn set_args(&mut self, args: <<T as Supervisor>::Children as Supervisable<T>>::Args) {
         self.children_args = Some(args);
     }
@@ -206,6 +364,14 @@ where
         self.children_configs = Some(configs);
     }
 
+    /// Sets when each of the children is restarted.
+    pub fn set_restarts(
+        &mut self,
+        restarts: <<T as Supervisor>::Children as Supervisable<T>>::Restarts,
+    ) {
+        self.children_restarts = Some(restarts);
+    }
+
     pub(crate) fn get_children(
         &self,
     ) -> <<T as Supervisor>::Children as Supervisable<T>>::Processes {
@@ -223,6 +389,43 @@ where
         T::Children::terminate(self);
     }
 
+    /// Records a restart and returns `false` if it exceeds the maximum number
+    /// of restarts within the restart period.
+    pub(crate) fn record_restart(&mut self) -> bool {
+        let max_restarts = match self.max_restarts {
+            Some(max_restarts) => max_restarts,
+            None => return true,
+        };
+        let now = Instant::now();
+        let period = self.restart_period;
+        self.restarts
+            .retain(|restart| now.duration_since(*restart) < period);
+        self.restarts.push_back(now);
+        self.restarts.len() <= max_restarts as usize
+    }
+
+    /// Waits for the backoff delay, if configured, before a restart.
+    pub(crate) fn wait_backoff(&mut self) {
+        let period = self.restart_period;
+        if let Some(backoff) = self.backoff.as_mut() {
+            if let Some(last_restart) = backoff.last_restart {
+                if last_restart.elapsed() >= period {
+                    backoff.delay = backoff.initial;
+                }
+            }
+            crate::sleep(backoff.delay);
+            // Compare as float first, the product could be too large for a
+            // `Duration`.
+            let delay = backoff.delay.as_secs_f64() * backoff.multiplier;
+            backoff.delay = if delay < backoff.max.as_secs_f64() {
+                Duration::from_secs_f64(delay)
+            } else {
+                backoff.max
+            };
+            backoff.last_restart = Some(Instant::now());
+        }
+    }
+
     pub(crate) fn subscribe_shutdown(&mut self, subscriber: DeferredResponse<(), T>) {
         self.terminate_subscribers.push(subscriber);
     }
@@ -239,13 +442,69 @@ where
             children_args: None,
             children_names: None,
             children_configs: None,
+            children_restarts: None,
             children_tags: None,
             terminate_subscribers: vec![],
+            exit_values: vec![],
+            dead_children: DeadChildren::default(),
             strategy: SupervisorStrategy::OneForOne,
+            restart: ChildRestart::Permanent,
+            max_restarts: None,
+            restart_period: Duration::from_secs(5),
+            restarts: VecDeque::new(),
+            backoff: None,
         }
     }
 }
 
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    multiplier: f64,
+    // Delay before the next restart.
+    delay: Duration,
+    last_restart: Option<Instant>,
+}
+
+/// Children whose death was reported by only one of the two signals so far.
+///
+/// A failed child is reported both as a dead link and as a dead monitored
+/// process, and the runtime doesn't guarantee in which order. The first signal
+/// is handled and recorded here by the child's id and link tag, so that the
+/// second one isn't handled again.
+#[derive(Default)]
+struct DeadChildren(Vec<(u64, Tag, Instant)>);
+
+impl DeadChildren {
+    fn insert(&mut self, process_id: u64, tag: Tag) {
+        self.0.push((process_id, tag, Instant::now()));
+    }
+
+    /// Removes the child with the link `tag` and returns `true` if it was
+    /// recorded.
+    fn take_link(&mut self, tag: Tag) -> bool {
+        let len = self.0.len();
+        self.0.retain(|(_, child_tag, _)| *child_tag != tag);
+        self.0.len() != len
+    }
+
+    /// Removes the child with `process_id` and returns `true` if it was
+    /// recorded.
+    fn take_process(&mut self, process_id: u64) -> bool {
+        let len = self.0.len();
+        self.0.retain(|(child_id, _, _)| *child_id != process_id);
+        self.0.len() != len
+    }
+
+    /// Forgets children recorded longer than `period` ago.
+    ///
+    /// Normal exits are never followed by a link signal, so their records
+    /// would otherwise stay forever.
+    fn prune(&mut self, period: Duration) {
+        self.0.retain(|(_, _, died)| died.elapsed() < period);
+    }
+}
+
 pub trait Supervisable<T>
 where
     T: Supervisor,
@@ -255,10 +514,16 @@ where
     type Names;
     type Configs;
     type Tags;
+    type Restarts;
 
     fn start_links(config: &mut SupervisorConfig<T>);
     fn terminate(config: SupervisorConfig<T>);
     fn handle_failure(config: &mut SupervisorConfig<T>, tag: Tag);
+    /// Returns `false` if the exited process was restarted or isn't a child.
+    fn handle_exit(config: &mut SupervisorConfig<T>, process_id: u64) -> bool;
+    fn child_restart(config: &SupervisorConfig<T>, tag: Tag) -> ChildRestart;
+    /// Returns the id of the current child linked with `tag`.
+    fn child_id(config: &SupervisorConfig<T>, tag: Tag) -> Option<u64>;
 }
 
 // Implement Supervisable for tuples with up to 12 children.
@@ -302,13 +567,18 @@ mod macros {
         ($config:ident, []) => {}; // base case
         ($config:ident, [$head_i:tt $($rest_i:tt)*]) => { // recursive case
             macros::reverse_shutdown!($config, [$($rest_i)*]);
-            $config.children.as_ref().unwrap().$head_i.shutdown();
+            // Children that are not restarted could have already exited.
+            if $config.children.as_ref().unwrap().$head_i.is_alive() {
+                $config.children.as_ref().unwrap().$head_i.shutdown();
+            }
         };
         // reverse_shutdown!(config, skip tag, [...]) shuts down all children with unmatched tags
         ($config:ident, skip $tag:ident, []) => {}; // base case
         ($config:ident, skip $tag:ident, [$head_i:tt $($rest_i:tt)*]) => { // recursive case
             macros::reverse_shutdown!($config, skip $tag, [$($rest_i)*]);
-            if $tag != $config.children_tags.as_ref().unwrap().$head_i {
+            if $tag != $config.children_tags.as_ref().unwrap().$head_i
+                && $config.children.as_ref().unwrap().$head_i.is_alive()
+            {
                 $config.children.as_ref().unwrap().$head_i.shutdown();
             }
         };
@@ -339,6 +609,7 @@ mod macros {
                     type Names = ($(macros::ignore_type!($t, Option<String>),)*);
                     type Configs = ($(macros::ignore_type!($t, Option<crate::ProcessConfig>),)*);
                     type Tags = ($(macros::tag!($t),)*);
+                    type Restarts = ($(macros::ignore_type!($t, ChildRestart),)*);
 
                     #[allow(unused_variables)]
                     fn start_links(config: &mut SupervisorConfig<K>) {
@@ -368,6 +639,8 @@ mod macros {
                                 Ok(proc) => proc,
                                 Err(err) => panic!("Supervisor failed to start child `{:?}`", err),
                             };
+                            // Monitor the child to also get notified about normal exits.
+                            unsafe { host::api::process::monitor([<proc$i>].id()) };
                         )*
                         config.children = Some(($([<proc$i>],)*));
                         config.children_tags = Some(($([<tag$i>],)*));
@@ -380,6 +653,15 @@ mod macros {
 
                     #[allow(unused_variables)]
                     fn handle_failure(config: &mut SupervisorConfig<K>, tag: Tag) {
+                        if !config.record_restart() {
+                            // Give up, the children would most likely keep failing.
+                            macros::reverse_shutdown!(config, [ $($i)* ]);
+                            panic!(
+                                "Supervisor {} exceeded the maximum number of restarts",
+                                std::any::type_name::<K>()
+                            );
+                        }
+                        config.wait_backoff();
                         match config.strategy {
                             // After a failure, just restart the same process.
                             SupervisorStrategy::OneForOne => {
@@ -416,6 +698,7 @@ mod macros {
                                             Ok(proc) => proc,
                                             Err(err) => panic!("Supervisor failed to start child `{:?}`", err),
                                         };
+                                        unsafe { host::api::process::monitor(proc.id()) };
                                         config.children.as_mut().unwrap().$i = proc;
                                         config.children_tags.as_mut().unwrap().$i = link_tag;
                                     } else
@@ -478,6 +761,7 @@ mod macros {
                                         Ok(proc) => proc,
                                         Err(err) => panic!("Supervisor failed to start child `{:?}`", err),
                                     };
+                                    unsafe { host::api::process::monitor(proc.id()) };
                                     config.children.as_mut().unwrap().$i = proc;
                                     config.children_tags.as_mut().unwrap().$i = link_tag;
 
@@ -541,6 +825,7 @@ mod macros {
                                                 Ok(proc) => proc,
                                                 Err(err) => panic!("Supervisor failed to start child `{:?}`", err),
                                             };
+                                            unsafe { host::api::process::monitor(proc.id()) };
                                             config.children.as_mut().unwrap().$i = proc;
                                             config.children_tags.as_mut().unwrap().$i = link_tag;
 
@@ -551,6 +836,58 @@ mod macros {
                             }
                         }
                     }
+
+                    #[allow(unused_variables)]
+                    fn handle_exit(config: &mut SupervisorConfig<K>, process_id: u64) -> bool {
+                        $(
+                            if process_id == config.children.as_ref().unwrap().$i.id() {
+                                let tag = config.children_tags.unwrap().$i;
+                                // If the child failed, its link signal may not have been handled
+                                // yet. Take it from the mailbox if it's already there, otherwise
+                                // record the exit in case the signal still arrives.
+                                let failed = unsafe {
+                                    host::api::message::receive(&tag.id(), 1, 0)
+                                } == LINK_DIED;
+                                if !failed {
+                                    config.dead_children.insert(process_id, tag);
+                                }
+                                let restart = match Self::child_restart(config, tag) {
+                                    ChildRestart::Permanent => true,
+                                    ChildRestart::Transient => failed,
+                                    ChildRestart::Temporary => false,
+                                };
+                                if restart {
+                                    Self::handle_failure(config, tag);
+                                    return false;
+                                }
+                                return true;
+                            }
+                        )*
+                        false
+                    }
+
+                    #[allow(unused_variables)]
+                    fn child_id(config: &SupervisorConfig<K>, tag: Tag) -> Option<u64> {
+                        $(
+                            if tag == config.children_tags.unwrap().$i {
+                                return Some(config.children.as_ref().unwrap().$i.id());
+                            }
+                        )*
+                        None
+                    }
+
+                    #[allow(unused_variables)]
+                    fn child_restart(config: &SupervisorConfig<K>, tag: Tag) -> ChildRestart {
+                        $(
+                            if tag == config.children_tags.unwrap().$i {
+                                return match &config.children_restarts {
+                                    Some(restarts) => restarts.$i,
+                                    None => config.restart,
+                                };
+                            }
+                        )*
+                        config.restart
+                    }
                 }
             }
         };
@@ -561,11 +898,14 @@ mod macros {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use lunatic_test::test;
 
-    use super::{Supervisor, SupervisorConfig};
+    use super::{DeadChildren, Supervisor, SupervisorConfig};
     use crate::ap::{AbstractProcess, Config};
     use crate::serializer::Bincode;
+    use crate::Tag;
 
     struct SimpleServer;
 
@@ -596,4 +936,32 @@ mod tests {
     fn supervisor_test() {
         SimpleSup::link().start(()).unwrap();
     }
+
+    #[test]
+    fn dead_children_link_first() {
+        let mut dead = DeadChildren::default();
+        let tag = Tag::new();
+        dead.insert(1, tag);
+        assert!(dead.take_process(1));
+        assert!(!dead.take_link(tag));
+    }
+
+    #[test]
+    fn dead_children_process_first() {
+        let mut dead = DeadChildren::default();
+        let tag = Tag::new();
+        dead.insert(1, tag);
+        assert!(dead.take_link(tag));
+        assert!(!dead.take_process(1));
+    }
+
+    #[test]
+    fn dead_children_prune() {
+        let mut dead = DeadChildren::default();
+        let tag = Tag::new();
+        dead.insert(1, tag);
+        dead.prune(Duration::ZERO);
+        assert!(!dead.take_link(tag));
+        assert!(!dead.take_process(1));
+    }
 }
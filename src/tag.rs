@@ -36,6 +36,19 @@ impl Tag {
         Tag(0)
     }
 
+    /// Creates a tag with a specific `id`.
+    ///
+    /// This is useful if two processes need to agree on a tag, e.g. for a
+    /// hand-rolled request/response protocol. Unlike tags returned by
+    /// [`Tag::new`], the value is not unique. It can collide with tags created
+    /// by `Tag::new` in the receiving process and with tags used internally,
+    /// like the ones of [`AbstractProcess`](crate::ap::AbstractProcess)
+    /// messages. Prefer ids in the range reserved by [`Tag::special`] to
+    /// avoid such collisions.
+    pub fn from_id(id: i64) -> Tag {
+        Tag(id)
+    }
+
     /// Create a special purpose tag.
     ///
     /// The `id` must be in the range between 64 and 128 or the function will
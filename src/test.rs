@@ -1,5 +1,70 @@
+//! Utilities for testing failure handling.
+//!
+//! Code that reacts to processes or nodes going away is hard to test without
+//! guessing with `sleep` when a death has propagated. The functions in this
+//! module return only after it did.
+
+use std::io::{self, ErrorKind};
+
+use crate::{host, Mailbox, MessageSignal, Process, ProcessDiedSignal, Tag};
+
+/// Kills `process` and blocks until it died.
+///
+/// The process is monitored before it's killed, and this function returns
+/// after the [`ProcessDiedSignal`](crate::ProcessDiedSignal) was received.
+/// The runtime notifies all linked and monitoring processes at the same time,
+/// so once this function returns the signals are waiting in their mailboxes.
+///
+/// ```no_run
+/// let process = lunatic::spawn!(|| loop {
+///     lunatic::sleep(std::time::Duration::from_secs(1));
+/// });
+/// lunatic::test::kill_and_wait(process);
+/// assert!(!process.is_alive());
+/// ```
+pub fn kill_and_wait<M, S>(process: Process<M, S>) {
+    let tag = Tag::new();
+    let parent = unsafe { Process::<()>::this() };
+    let target = (process.node_id(), process.id());
+    Process::spawn(
+        (parent, tag, target),
+        |(parent, tag, (node, id)), mailbox: Mailbox<()>| {
+            let mailbox = mailbox.monitorable();
+            let process = unsafe { Process::<()>::new(node, id) };
+            mailbox.monitor(process);
+            // A local process that is already gone will not produce a signal.
+            if node != host::node_id() || process.is_alive() {
+                process.kill();
+                loop {
+                    if let MessageSignal::Signal(ProcessDiedSignal(died)) = mailbox.receive() {
+                        if died == id {
+                            break;
+                        }
+                    }
+                }
+            }
+            parent.tag_send(tag, ());
+        },
+    );
+    // Temporarily cast to right mailbox type.
+    let mailbox: Mailbox<()> = unsafe { Mailbox::new() };
+    mailbox.tag_receive(&[tag]);
+}
+
+/// Disconnects `node` from the current one, as if it crashed.
+///
+/// The runtime doesn't expose a way to drop the connection to another node,
+/// so this function always returns an error with [`ErrorKind::Unsupported`].
+pub fn simulate_node_down(node: u64) -> io::Result<()> {
+    Err(io::Error::new(
+        ErrorKind::Unsupported,
+        format!("simulating node {node} going down is not supported by the runtime"),
+    ))
+}
+
 // This function is used internally by the `#[lunatic::test]` macro to check if
 // the value returned from the test is not `Result::Err`.
+#[doc(hidden)]
 pub fn assert_test_result<T: TestReturnValue + std::fmt::Debug>(result: T) {
     assert!(
         result.is_success(),
@@ -8,6 +73,7 @@ pub fn assert_test_result<T: TestReturnValue + std::fmt::Debug>(result: T) {
     );
 }
 
+#[doc(hidden)]
 pub trait TestReturnValue {
     fn is_success(&self) -> bool;
 }
@@ -1,27 +1,240 @@
 //! Contains helper structures to deal with time-related functionality.
 
-use std::time::Duration;
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
 
 use crate::ap::messages::{RequestMessage, ShutdownMessage};
-use crate::ap::{AbstractProcess, DeferredRequestHandler, ProcessRef, RequestHandler};
-use crate::host;
+use crate::ap::{
+    AbstractProcess, DeferredRequestHandler, MonitorRef, ProcessDown, ProcessRef, RequestHandler,
+};
+use crate::function::FuncRef;
 use crate::serializer::CanSerialize;
+use crate::{host, process_local, Mailbox, MailboxError, MessageSignal, Process, Tag};
+
+process_local! {
+    // Timers issued by this process that have not been canceled yet, together
+    // with the point in time at which they are going to fire.
+    static TIMERS: RefCell<Vec<(u64, Instant)>> = RefCell::new(Vec::new());
+}
 
 /// A reference to a timer created from send_after.
 #[derive(Clone, Copy)]
 pub struct TimerRef(u64);
 
 impl TimerRef {
-    pub(crate) fn new(timer_id: u64) -> Self {
+    pub(crate) fn new(timer_id: u64, duration: Duration) -> Self {
+        let deadline = Instant::now() + duration;
+        TIMERS.with_borrow_mut(|mut timers| {
+            timers.retain(|(_, deadline)| *deadline > Instant::now());
+            timers.push((timer_id, deadline));
+        });
         TimerRef(timer_id)
     }
 
     /// Cancel the timer, blocking until the timer is canceled.
+    ///
+    /// Returns `true` if the timer was still pending, or `false` if the
+    /// message was already sent.
     pub fn cancel(self) -> bool {
+        TIMERS.with_borrow_mut(|mut timers| timers.retain(|(id, _)| *id != self.0));
         unsafe { host::api::timer::cancel_timer(self.0) == 1 }
     }
 }
 
+/// A reference to a repeating timer created by [`interval`] or
+/// [`Config::send_interval`](crate::ap::Config::send_interval).
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct IntervalRef {
+    ticker: Process<()>,
+}
+
+impl IntervalRef {
+    pub(crate) fn new(ticker: Process<()>) -> Self {
+        IntervalRef { ticker }
+    }
+
+    /// Stops the timer.
+    pub fn cancel(self) {
+        self.ticker.kill();
+    }
+
+    /// Returns `true` if the timer is still running.
+    pub fn is_active(&self) -> bool {
+        self.ticker.is_alive()
+    }
+}
+
+/// Cancels all pending timers that were created by the current process.
+///
+/// Returns the number of timers that were canceled before they fired.
+pub fn cancel_all() -> usize {
+    let timers = TIMERS.take();
+    timers
+        .into_iter()
+        .filter(|(timer_id, _)| unsafe { host::api::timer::cancel_timer(*timer_id) == 1 })
+        .count()
+}
+
+/// Returns an estimate of the number of timers created by the current process
+/// that are still waiting to fire.
+///
+/// The host doesn't report when a timer fires, so the count is based on the
+/// deadline recorded when the timer was created. A timer counts as pending
+/// until its deadline passes, even if the host delivers the message late.
+/// Timers canceled with [`TimerRef::cancel`] or [`cancel_all`] are not
+/// counted, but timers canceled directly through the host API still are.
+pub fn pending_count() -> usize {
+    TIMERS.with_borrow_mut(|mut timers| {
+        timers.retain(|(_, deadline)| *deadline > Instant::now());
+        timers.len()
+    })
+}
+
+/// Message sent by an [`interval`] timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Tick;
+
+/// Sends a [`Tick`] to the current process every `every`, until the process
+/// dies or the returned [`IntervalRef`] is canceled.
+///
+/// The ticks are sent with the default serializer, so they can be received
+/// from a `Mailbox<Tick>`. If the process is slower than `every`, the ticks
+/// queue up in the mailbox.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use lunatic::time::{interval, Tick};
+/// use lunatic::Mailbox;
+///
+/// #[lunatic::main]
+/// fn main(mailbox: Mailbox<Tick>) {
+///     let ticks = interval(Duration::from_secs(1));
+///     for _ in 0..3 {
+///         mailbox.receive();
+///     }
+///     ticks.cancel();
+/// }
+/// ```
+pub fn interval(every: Duration) -> IntervalRef {
+    let owner = unsafe { Process::<Tick>::this() };
+    let ticker = Process::spawn((owner, every), |(owner, every), mailbox: Mailbox<()>| {
+        run_ticker(mailbox, owner, every, || owner.send(Tick))
+    });
+    IntervalRef::new(ticker)
+}
+
+/// The body of an interval ticker process.
+///
+/// Calls `tick` every `every` until `owner` dies.
+pub(crate) fn run_ticker<T, U>(
+    mailbox: Mailbox<()>,
+    owner: Process<T, U>,
+    every: Duration,
+    mut tick: impl FnMut(),
+) {
+    let mailbox = mailbox.monitorable();
+    mailbox.monitor(owner);
+    loop {
+        // Stop once the owner dies.
+        if let Ok(MessageSignal::Signal(_)) = mailbox.receive_timeout(every) {
+            break;
+        }
+        tick();
+    }
+}
+
+/// Runs `op` with `capture` and waits at most `timeout` for it to finish.
+///
+/// The operation runs in a separate process, so any blocking call can be
+/// raced against the deadline. If the deadline expires first, the process is
+/// killed and [`DeadlineError::TimedOut`] is returned.
+///
+/// Resources moved into `capture`, like a
+/// [`TcpStream`](crate::net::TcpStream), are dropped together with the killed
+/// process. A timed out read can't leave the caller with a partially consumed
+/// stream, because the caller doesn't own the stream anymore.
+///
+/// The process is monitored, so if `op` panics [`DeadlineError::Failed`] is
+/// returned right away.
+///
+/// ```no_run
+/// use std::io::Read;
+/// use std::time::Duration;
+///
+/// use lunatic::net::TcpStream;
+/// use lunatic::time::with_deadline;
+///
+/// let stream = TcpStream::connect("127.0.0.1:1337").unwrap();
+/// let result = with_deadline(Duration::from_secs(1), stream, |mut stream| {
+///     let mut buffer = vec![0; 1024];
+///     let n = stream.read(&mut buffer).unwrap();
+///     buffer.truncate(n);
+///     buffer
+/// });
+/// ```
+pub fn with_deadline<C, T>(
+    timeout: Duration,
+    capture: C,
+    op: fn(C) -> T,
+) -> Result<T, DeadlineError>
+where
+    C: Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned,
+{
+    let tag = Tag::new();
+    let this = unsafe { Process::<T>::this() };
+    let worker = Process::spawn(
+        (capture, FuncRef::new(op), this, tag),
+        |(capture, op, parent, tag), _: Mailbox<()>| parent.tag_send(tag, op(capture)),
+    );
+    let monitor = MonitorRef::new(worker.id());
+    // Temporarily cast to right mailbox type.
+    let mailbox: Mailbox<T> = unsafe { Mailbox::new() };
+    let result = mailbox.tag_receive_timeout(&[tag, monitor.tag()], timeout);
+    let received = Tag::from(unsafe { host::api::message::get_tag() });
+    let result = match result {
+        Err(MailboxError::TimedOut) => {
+            worker.kill();
+            // The result could have arrived right before the process was killed.
+            mailbox
+                .tag_receive_timeout(&[tag], Duration::ZERO)
+                .map_err(|_| DeadlineError::TimedOut)
+        }
+        // The result is always sent before the process exits, so it's received
+        // before the monitor notification.
+        _ if received == monitor.tag() => return Err(DeadlineError::Failed),
+        Ok(result) => Ok(result),
+        Err(MailboxError::DeserializationFailed(err)) => {
+            Err(DeadlineError::DeserializationFailed(err.to_string()))
+        }
+        Err(err) => unreachable!("unexpected error with result tag: {err}"),
+    };
+    // The process exits right after sending the result or being killed. Wait
+    // for it, so that the monitor notification doesn't stay in the mailbox.
+    let mailbox: Mailbox<ProcessDown> = unsafe { Mailbox::new() };
+    mailbox.tag_receive(&[monitor.tag()]);
+    result
+}
+
+/// Error returned by [`with_deadline`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DeadlineError {
+    /// The operation didn't finish before the deadline.
+    #[error("deadline expired")]
+    TimedOut,
+    /// The operation panicked or its process was killed.
+    #[error("operation failed")]
+    Failed,
+    /// The result of the operation couldn't be deserialized.
+    #[error("result failed to deserialize: {0}")]
+    DeserializationFailed(String),
+}
+
 /// Modifies `T` so that all functions on it will return a timeout.
 ///
 /// It's used to time out calls such as [`ProcessRef::shutdown`],
@@ -66,6 +279,22 @@ impl<T: AbstractProcess> WithTimeout<ProcessRef<T>> {
         self.item.request_timeout(request, Some(self.timeout))
     }
 
+    /// Make a request to a handler that uses the serializer `S`.
+    ///
+    /// The function will only wait for the duration of the specified timeout on
+    /// the response, before returning `Err(Timeout)`.
+    #[track_caller]
+    pub fn request_with<S, R: 'static>(&self, request: R) -> Result<T::Response, Timeout>
+    where
+        T: RequestHandler<R>,
+        S: CanSerialize<R> + 'static,
+        S: CanSerialize<T::Response>,
+        S: CanSerialize<RequestMessage<R, T::Response, S>>,
+    {
+        self.item
+            .request_with_timeout::<S, R>(request, Some(self.timeout))
+    }
+
     /// Make a deferred request to the process.
     ///
     /// The function will only wait for the duration of the specified timeout on
@@ -1,13 +1,15 @@
 use std::time::Duration;
 
-use lunatic::ap::handlers::{DeferredRequest, Message, Request};
+use lunatic::ap::handlers::{DeferredRequest, Message, Request, StreamRequest};
 use lunatic::ap::{
-    AbstractProcess, Config, DeferredRequestHandler, DeferredResponse, MessageHandler, ProcessRef,
-    RequestHandler, StartupError, State,
+    AbstractProcess, Config, ConversionError, DeadLetter, DeadLetterReason, DeferredRequestHandler,
+    DeferredResponse, ExitReason, MessageHandler, ProcessDown, ProcessRef, RequestHandler,
+    ResponseSender, Snapshot, SnapshotStore, StartupError, State, StreamRequestHandler,
+    StreamResponder, StringifiedError,
 };
 use lunatic::serializer::Bincode;
 use lunatic::time::Timeout;
-use lunatic::{sleep, spawn_link, test};
+use lunatic::{distributed, sleep, spawn_link, test, Mailbox, Process};
 
 /// This `AbstractProcess` always panics on `init`.
 struct InitPanicksAP;
@@ -52,6 +54,33 @@ fn init_error() {
     );
 }
 
+/// This `AbstractProcess` fails on `init` with an error that can't be
+/// serialized.
+struct InitIoErrorAP;
+
+impl AbstractProcess for InitIoErrorAP {
+    type State = ();
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = ();
+    type StartupError = StringifiedError;
+
+    fn init(_: Config<Self>, _: Self::Arg) -> Result<(), StringifiedError> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "missing file",
+        ))?
+    }
+}
+
+#[test]
+fn init_stringified_error() {
+    match InitIoErrorAP::start(()) {
+        Err(StartupError::Custom(err)) => assert_eq!(err.to_string(), "missing file"),
+        _ => panic!("expected a custom startup error"),
+    }
+}
+
 /// `AbstractProcess` that starts normally.
 struct InitOkAP;
 
@@ -78,6 +107,23 @@ fn shutdown_ok() {
     ap.shutdown();
 }
 
+#[test]
+fn snapshot() {
+    let ap = InitOkAP::start_as(&"snapshot_test", ()).unwrap();
+    sleep(Duration::from_millis(5));
+    let snapshot = ap.snapshot();
+    assert_eq!(snapshot.id, ap.id());
+    assert!(snapshot.alive);
+    assert_eq!(snapshot.names, vec!["snapshot_test".to_owned()]);
+    assert!(snapshot.uptime.unwrap() >= Duration::from_millis(5));
+
+    ap.shutdown();
+    sleep(Duration::from_millis(5));
+    let snapshot = ap.snapshot();
+    assert!(!snapshot.alive);
+    assert_eq!(snapshot.uptime, None);
+}
+
 /// `AbstractProcess` that fails to shut down in time.
 struct ShutdownTimeoutAP;
 
@@ -207,6 +253,23 @@ fn self_ref() {
     assert_eq!(ap.request(Count), 10);
 }
 
+#[test]
+fn convert_process() {
+    let ap = SelfRefAP::link().start(0).unwrap();
+    let process: Process<(), Bincode> = ap.into();
+    assert_eq!(process.id(), ap.id());
+
+    let converted = ProcessRef::<SelfRefAP>::from_process(process, Duration::from_secs(1)).unwrap();
+    assert_eq!(converted, ap);
+    // The process isn't running `InitOkAP`, even if the serializer matches.
+    let err = ProcessRef::<InitOkAP>::from_process(process, Duration::from_millis(100));
+    assert!(matches!(err, Err(ConversionError::Mismatch(_))));
+
+    ap.shutdown();
+    let err = ProcessRef::<SelfRefAP>::from_process(process, Duration::from_millis(100));
+    assert_eq!(err, Err(ConversionError::NoAnswer));
+}
+
 /// `AbstractProcess` that is registered under a well-known name.
 struct RegisteredAP;
 
@@ -409,6 +472,21 @@ fn request_timeout() {
     assert_eq!(response, Err(Timeout));
 }
 
+#[test]
+fn request_async() {
+    let first = StringRequestHandlerAP::link().start(()).unwrap();
+    let second = StringRequestHandlerAP::link().start(()).unwrap();
+    let slow = RequestHandlerTimeoutAP::link().start(()).unwrap();
+    let slow_reply = slow.request_async(());
+    let first_reply = first.request_async("Hello".to_owned());
+    let second_reply = second.request_async("Goodbye".to_owned());
+    assert!(slow_reply.try_recv().is_none());
+    // Replies can be collected in any order.
+    assert_eq!(second_reply.recv(), "Goodbye world");
+    assert_eq!(first_reply.recv(), "Hello world");
+    slow_reply.recv();
+}
+
 /// `AbstractProcess` that handles a deferred `String` request/response
 struct DeferredStringRequestHandlerAP;
 
@@ -446,6 +524,51 @@ fn deferred_handle_request() {
     assert_eq!(response, "Hello world");
 }
 
+/// `AbstractProcess` that forwards deferred requests to a worker process
+struct ForwardingRequestHandlerAP {
+    worker: Process<(String, ResponseSender<String, Bincode>)>,
+}
+
+impl AbstractProcess for ForwardingRequestHandlerAP {
+    type State = Self;
+    type Serializer = Bincode;
+    type Arg = Process<(String, ResponseSender<String, Bincode>)>;
+    type Handlers = (DeferredRequest<String>,);
+    type StartupError = ();
+
+    fn init(_: Config<Self>, worker: Self::Arg) -> Result<Self, ()> {
+        Ok(Self { worker })
+    }
+}
+
+impl DeferredRequestHandler<String> for ForwardingRequestHandlerAP {
+    type Response = String;
+
+    fn handle(
+        state: State<Self>,
+        request: String,
+        deferred_response: DeferredResponse<Self::Response, Self>,
+    ) {
+        state
+            .worker
+            .send((request, deferred_response.into_sender()));
+    }
+}
+
+#[test]
+fn deferred_response_sender() {
+    let worker = spawn_link!(
+        |mailbox: Mailbox<(String, ResponseSender<String, Bincode>)>| {
+            let (mut request, sender) = mailbox.receive();
+            request.push_str(" from worker");
+            sender.send_response(request);
+        }
+    );
+    let ap = ForwardingRequestHandlerAP::link().start(worker).unwrap();
+    let response = ap.deferred_request("Hello".to_owned());
+    assert_eq!(response, "Hello from worker");
+}
+
 /// `AbstractProcess` that times out on a deferred request/response
 struct DeferredRequestTimeoutAP;
 
@@ -477,3 +600,363 @@ fn deferred_request_timeout() {
         .deferred_request("Hello".to_owned());
     assert_eq!(response, Err(Timeout));
 }
+
+/// `AbstractProcess` that shuts down after being idle for 20 ms.
+struct IdleAP(Option<DeferredResponse<(), IdleAP>>);
+
+impl AbstractProcess for IdleAP {
+    type State = Self;
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = (DeferredRequest<()>, Message<()>);
+    type StartupError = ();
+
+    fn init(config: Config<Self>, _: Self::Arg) -> Result<Self, ()> {
+        config.set_idle_timeout(Duration::from_millis(20));
+        Ok(Self(None))
+    }
+}
+
+impl DeferredRequestHandler<()> for IdleAP {
+    type Response = ();
+
+    fn handle(mut state: State<Self>, _: (), deferred_response: DeferredResponse<(), Self>) {
+        // Hold on to the response until a message arrives.
+        state.0 = Some(deferred_response);
+    }
+}
+
+impl MessageHandler<()> for IdleAP {
+    fn handle(mut state: State<Self>, _: ()) {
+        if let Some(deferred_response) = state.0.take() {
+            deferred_response.send_response(());
+        }
+    }
+}
+
+#[test]
+fn idle_timeout() {
+    let ap = IdleAP::link().start(()).unwrap();
+    for _ in 0..5 {
+        sleep(Duration::from_millis(10));
+        ap.send(());
+    }
+    assert!(ap.is_alive());
+    sleep(Duration::from_millis(50));
+    assert!(!ap.is_alive());
+}
+
+#[test]
+fn idle_timeout_waits_on_deferred_response() {
+    let ap = IdleAP::link().start(()).unwrap();
+    spawn_link!(|ap| ap.deferred_request(()));
+    sleep(Duration::from_millis(50));
+    assert!(ap.is_alive());
+    ap.send(());
+    sleep(Duration::from_millis(50));
+    assert!(!ap.is_alive());
+}
+
+/// `AbstractProcess` that streams back the numbers up to the request.
+struct StreamRequestAP;
+
+impl AbstractProcess for StreamRequestAP {
+    type State = Self;
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = (StreamRequest<u32>,);
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: Self::Arg) -> Result<Self, ()> {
+        Ok(Self)
+    }
+}
+
+impl StreamRequestHandler<u32> for StreamRequestAP {
+    type Item = u32;
+
+    fn handle(_: State<Self>, request: u32, responder: StreamResponder<Self::Item, Self>) {
+        spawn_link!(|request, responder| {
+            for i in 0..request {
+                responder.push(i);
+            }
+            responder.finish();
+        });
+    }
+}
+
+#[test]
+fn subscribe_request() {
+    let ap = StreamRequestAP::link().start(()).unwrap();
+    let items: Vec<u32> = ap.subscribe_request(5).collect();
+    assert_eq!(items, vec![0, 1, 2, 3, 4]);
+    // The responder completes the stream when dropped.
+    let mut stream = ap.subscribe_request(0);
+    assert_eq!(stream.next(), None);
+    assert_eq!(stream.next(), None);
+}
+
+#[test]
+fn await_registration() {
+    spawn_link!(|| {
+        sleep(Duration::from_millis(20));
+        RegisteredAP::start_as(&"AwaitedAP", ()).unwrap();
+    });
+    assert!(
+        distributed::await_registration_with_interval::<RegisteredAP, _>(
+            &"AwaitedAP",
+            Duration::from_secs(1),
+            Duration::from_millis(5),
+        )
+        .is_some()
+    );
+    assert!(distributed::await_registration::<RegisteredAP, _>(
+        &"NeverRegistered",
+        Duration::from_millis(10)
+    )
+    .is_none());
+}
+
+/// `AbstractProcess` that collects dead letters.
+struct DeadLetterCollectorAP(Vec<DeadLetter>);
+
+impl AbstractProcess for DeadLetterCollectorAP {
+    type State = Self;
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = (Message<DeadLetter>, Request<()>);
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: Self::Arg) -> Result<Self, ()> {
+        Ok(Self(Vec::new()))
+    }
+}
+
+impl MessageHandler<DeadLetter> for DeadLetterCollectorAP {
+    fn handle(mut state: State<Self>, dead_letter: DeadLetter) {
+        state.0.push(dead_letter);
+    }
+}
+
+impl RequestHandler<()> for DeadLetterCollectorAP {
+    type Response = Vec<DeadLetter>;
+
+    fn handle(state: State<Self>, _: ()) -> Self::Response {
+        state.0.clone()
+    }
+}
+
+/// Only odd numbers can be deserialized.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "u32")]
+struct Odd(u32);
+
+impl TryFrom<u32> for Odd {
+    type Error = &'static str;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        if value % 2 == 1 {
+            Ok(Odd(value))
+        } else {
+            Err("not odd")
+        }
+    }
+}
+
+/// `AbstractProcess` that forwards undecodable messages to a dead-letter sink.
+struct DeadLetterAP;
+
+impl AbstractProcess for DeadLetterAP {
+    type State = Self;
+    type Serializer = Bincode;
+    type Arg = ProcessRef<DeadLetterCollectorAP>;
+    type Handlers = (Message<Odd>,);
+    type StartupError = ();
+
+    fn init(config: Config<Self>, sink: Self::Arg) -> Result<Self, ()> {
+        config.dead_letter_sink(sink);
+        Ok(Self)
+    }
+}
+
+impl MessageHandler<Odd> for DeadLetterAP {
+    fn handle(_: State<Self>, _: Odd) {}
+}
+
+#[test]
+fn dead_letter_sink() {
+    let collector = DeadLetterCollectorAP::link().start(()).unwrap();
+    let ap = DeadLetterAP::link().start(collector).unwrap();
+    ap.send(Odd(1));
+    ap.send(Odd(2));
+    sleep(Duration::from_millis(10));
+
+    let dead_letters = collector.request(());
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].process_id, ap.id());
+    assert!(matches!(
+        dead_letters[0].reason,
+        DeadLetterReason::DecodeFailed(_)
+    ));
+
+    // Replaying the message will reject it again.
+    dead_letters[0].replay(ap);
+    sleep(Duration::from_millis(10));
+    assert_eq!(collector.request(()).len(), 2);
+}
+
+/// `AbstractProcess` that counts odd numbers.
+struct OddCounterAP(u32);
+
+impl AbstractProcess for OddCounterAP {
+    type State = Self;
+    type Serializer = Bincode;
+    type Arg = bool;
+    type Handlers = (Message<Odd>, Request<()>);
+    type StartupError = ();
+
+    fn init(config: Config<Self>, strict: bool) -> Result<Self, ()> {
+        config.set_strict_decoding(strict);
+        Ok(Self(0))
+    }
+}
+
+impl MessageHandler<Odd> for OddCounterAP {
+    fn handle(mut state: State<Self>, _: Odd) {
+        state.0 += 1;
+    }
+}
+
+impl RequestHandler<()> for OddCounterAP {
+    type Response = u32;
+
+    fn handle(state: State<Self>, _: ()) -> Self::Response {
+        state.0
+    }
+}
+
+#[test]
+fn skip_undecodable_message() {
+    let ap = OddCounterAP::link().start(false).unwrap();
+    ap.send(Odd(1));
+    ap.send(Odd(2));
+    ap.send(Odd(3));
+    assert_eq!(ap.request(()), 2);
+}
+
+#[test]
+fn broadcast_message() {
+    let counters: Vec<_> = (0..100)
+        .map(|_| OddCounterAP::link().start(false).unwrap())
+        .collect();
+    lunatic::ap::broadcast(&counters, Odd(1));
+    for counter in counters {
+        assert_eq!(counter.request(()), 1);
+    }
+}
+
+#[test]
+fn monitor_normal_exit(mailbox: Mailbox<ProcessDown>) {
+    let ap = OddCounterAP::start(false).unwrap();
+    let monitor = ap.monitor();
+    ap.shutdown();
+    let down = mailbox.tag_receive(&[monitor.tag()]);
+    assert_eq!(
+        down,
+        ProcessDown {
+            monitor,
+            reason: ExitReason::Normal
+        }
+    );
+}
+
+#[test]
+fn wait_for_exit() {
+    let ap = OddCounterAP::start(false).unwrap();
+    Process::spawn(ap, |ap, _: Mailbox<()>| {
+        sleep(Duration::from_millis(10));
+        ap.shutdown();
+    });
+    assert_eq!(ap.wait(), ExitReason::Normal);
+}
+
+#[test]
+#[should_panic]
+fn strict_decoding() {
+    let ap = OddCounterAP::link().start(true).unwrap();
+    ap.send(Odd(2));
+    // The linked process fails on the bad message.
+    sleep(Duration::from_millis(100));
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+struct SnapshotCounter(u32);
+
+impl Snapshot for SnapshotCounter {}
+
+/// Forwards saved snapshots to a process and loads a fixed snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ForwardingStore {
+    saved: Process<Vec<u8>>,
+    stored: Option<Vec<u8>>,
+}
+
+impl SnapshotStore for ForwardingStore {
+    fn save(&self, snapshot: &[u8]) -> std::io::Result<()> {
+        self.saved.send(snapshot.to_vec());
+        Ok(())
+    }
+
+    fn load(&self) -> std::io::Result<Option<Vec<u8>>> {
+        Ok(self.stored.clone())
+    }
+}
+
+/// `AbstractProcess` that persists its state.
+struct SnapshotAP;
+
+impl AbstractProcess for SnapshotAP {
+    type State = SnapshotCounter;
+    type Serializer = Bincode;
+    type Arg = ForwardingStore;
+    type Handlers = (Message<()>,);
+    type StartupError = ();
+
+    fn init(config: Config<Self>, store: ForwardingStore) -> Result<SnapshotCounter, ()> {
+        let counter = config.restore_snapshot(&store).unwrap();
+        config.enable_snapshots(Duration::from_millis(20), store);
+        Ok(counter.unwrap_or(SnapshotCounter(0)))
+    }
+}
+
+impl MessageHandler<()> for SnapshotAP {
+    fn handle(mut state: State<Self>, _: ()) {
+        state.0 += 1;
+    }
+}
+
+#[test]
+fn periodic_snapshots(mailbox: Mailbox<Vec<u8>>) {
+    let store = ForwardingStore {
+        saved: mailbox.this(),
+        stored: Some(bincode::serialize(&SnapshotCounter(5)).unwrap()),
+    };
+    let ap = SnapshotAP::link().start(store).unwrap();
+    ap.send(());
+    ap.send(());
+
+    // The state is restored on `init` and persisted on a timer.
+    let snapshot = mailbox.receive_timeout(Duration::from_secs(1)).unwrap();
+    let counter: SnapshotCounter = bincode::deserialize(&snapshot).unwrap();
+    assert_eq!(counter, SnapshotCounter(7));
+
+    // A final snapshot is taken on shutdown.
+    ap.send(());
+    ap.shutdown();
+    let mut last = None;
+    while let Ok(snapshot) = mailbox.receive_timeout(Duration::from_millis(100)) {
+        last = Some(snapshot);
+    }
+    let counter: SnapshotCounter = bincode::deserialize(&last.unwrap()).unwrap();
+    assert_eq!(counter, SnapshotCounter(8));
+}
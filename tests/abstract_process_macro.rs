@@ -1,8 +1,11 @@
+use std::cell::RefCell;
 use std::f32::consts::PI;
 use std::time::Duration;
 
-use lunatic::ap::{AbstractProcess, Config};
-use lunatic::{abstract_process, host, sleep, spawn_link, test, Tag};
+use lunatic::ap::{AbstractProcess, Config, MessageMetric, ProcessSignal};
+use lunatic::{
+    abstract_process, host, process_local, sleep, spawn_link, test, Mailbox, Process, Tag,
+};
 
 #[test]
 fn init() {
@@ -45,6 +48,38 @@ fn shutdown() {
     a.shutdown();
 }
 
+#[test]
+fn shutdown_with_reason(mailbox: Mailbox<Option<StopReason>>) {
+    struct A(Process<Option<StopReason>>);
+
+    #[abstract_process]
+    impl A {
+        #[init]
+        fn init(_: Config<Self>, parent: Process<Option<StopReason>>) -> Result<A, ()> {
+            Ok(A(parent))
+        }
+
+        #[terminate]
+        fn terminate(self, reason: Option<StopReason>) {
+            println!("Exiting: {reason:?}");
+            self.0.send(reason);
+        }
+    }
+
+    let a = A::link().start(mailbox.this()).unwrap();
+    a.shutdown_with(StopReason::Upgrade);
+    assert_eq!(mailbox.receive(), Some(StopReason::Upgrade));
+
+    let a = A::link().start(mailbox.this()).unwrap();
+    a.shutdown();
+    assert_eq!(mailbox.receive(), None);
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+enum StopReason {
+    Upgrade,
+}
+
 #[test]
 fn handle_link_trapped() {
     struct A {
@@ -79,6 +114,43 @@ fn handle_link_trapped() {
     assert!(a.is_link_trapped());
 }
 
+#[test]
+fn on_message() {
+    process_local!(static HANDLERS: RefCell<Vec<&'static str>> = RefCell::new(Vec::new()));
+
+    struct A;
+
+    #[abstract_process]
+    impl A {
+        #[init]
+        fn init(_: Config<Self>, _: ()) -> Result<A, ()> {
+            Ok(A)
+        }
+
+        #[on_message]
+        fn record(metric: MessageMetric) {
+            HANDLERS.with_borrow_mut(|mut handlers| handlers.push(metric.handler));
+        }
+
+        #[handle_message]
+        fn ping(&self) {}
+
+        #[handle_request]
+        fn handlers(&self) -> Vec<String> {
+            HANDLERS.with_borrow(|handlers| handlers.iter().map(|h| h.to_string()).collect())
+        }
+    }
+
+    let a = A::link().start(()).unwrap();
+    a.ping();
+    a.ping();
+    // The hook is called after the handler finishes, so the current request
+    // isn't recorded yet.
+    let handlers = a.handlers();
+    assert_eq!(handlers.len(), 2);
+    assert!(handlers.iter().all(|h| h.contains("MsgWrapPing")));
+}
+
 #[test]
 fn handle_zero_argument() {
     struct Counter {
@@ -300,6 +372,81 @@ fn handle_comments() {
     assert_eq!(3, counter.count());
 }
 
+#[test]
+fn handle_signal() {
+    struct Worker {
+        paused: bool,
+        done: u32,
+    }
+
+    #[abstract_process]
+    impl Worker {
+        #[init]
+        fn init(_config: Config<Self>, _: ()) -> Result<Self, ()> {
+            Ok(Self {
+                paused: false,
+                done: 0,
+            })
+        }
+
+        #[handle_signal]
+        fn on_signal(&mut self, signal: ProcessSignal) {
+            match signal {
+                ProcessSignal::Pause => self.paused = true,
+                ProcessSignal::Resume => self.paused = false,
+                _ => (),
+            }
+        }
+
+        #[handle_message]
+        fn work(&mut self) {
+            if !self.paused {
+                self.done += 1;
+            }
+        }
+
+        #[handle_request]
+        fn done(&self) -> u32 {
+            self.done
+        }
+    }
+
+    let worker = Worker::link().start(()).unwrap();
+    worker.work();
+    worker.signal(ProcessSignal::Pause);
+    worker.work();
+    worker.signal(ProcessSignal::Resume);
+    worker.work();
+    assert_eq!(worker.done(), 2);
+}
+
+#[test]
+fn handle_timeout(mailbox: Mailbox<String>) {
+    struct Worker {
+        parent: Process<String>,
+    }
+
+    #[abstract_process]
+    impl Worker {
+        #[init]
+        fn init(config: Config<Self>, parent: Process<String>) -> Result<Self, ()> {
+            config.set_idle_timeout(Duration::from_millis(30));
+            Ok(Self { parent })
+        }
+
+        #[handle_timeout]
+        fn idle(&mut self) {
+            self.parent.send("idle".to_owned());
+        }
+    }
+
+    let worker = Worker::link().start(mailbox.this()).unwrap();
+    let message = mailbox.receive_timeout(Duration::from_millis(500)).unwrap();
+    assert_eq!(message, "idle");
+    sleep(Duration::from_millis(10));
+    assert!(!worker.is_alive());
+}
+
 #[test]
 fn handle_differing_names() {
     struct Counter {
@@ -562,3 +709,52 @@ fn generics() {
         .unwrap();
     assert_eq!(PI * 2f32, s);
 }
+
+#[test]
+fn handler_serializer() {
+    struct A;
+
+    #[abstract_process(serializer = lunatic::serializer::Bincode)]
+    impl A {
+        #[init]
+        fn init(_: Config<Self>, _: ()) -> Result<Self, ()> {
+            Ok(Self)
+        }
+
+        #[handle_request]
+        fn internal(&self, value: u32) -> u32 {
+            value + 1
+        }
+
+        #[handle_request(serializer = "lunatic::serializer::Json")]
+        fn external(&self, name: Name) -> Name {
+            match name {
+                Name::Id(id) => Name::Text(format!("#{id}")),
+                Name::Text(text) => Name::Text(format!("Hello {text}")),
+            }
+        }
+    }
+
+    // Untagged enums can't be decoded by Bincode, so the request and response
+    // only arrive if they are sent as Json.
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    #[serde(untagged)]
+    enum Name {
+        Id(u32),
+        Text(String),
+    }
+
+    let process = A::link().start(()).unwrap();
+    assert_eq!(process.internal(1), 2);
+    assert_eq!(
+        process.external(Name::Text("world".to_owned())),
+        Name::Text("Hello world".to_owned())
+    );
+    assert_eq!(
+        process
+            .with_timeout(Duration::from_millis(100))
+            .external(Name::Id(7))
+            .unwrap(),
+        Name::Text("#7".to_owned())
+    );
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use lunatic::bus::EventBus;
+use lunatic::{sleep, spawn_link, test, AbstractProcess, Mailbox};
+
+#[test]
+fn publish_to_subscribers(mailbox: Mailbox<u32>) {
+    let bus = EventBus::<u32>::link().start(()).unwrap();
+    bus.subscribe(mailbox.this());
+    bus.subscribe_filtered(mailbox.this(), |event| event % 2 == 0);
+
+    bus.publish(1);
+    bus.publish(2);
+    assert_eq!(mailbox.receive(), 1);
+    assert_eq!(mailbox.receive(), 2);
+    assert_eq!(mailbox.receive(), 2);
+
+    bus.unsubscribe(mailbox.this());
+    bus.publish(3);
+    assert!(mailbox.receive_timeout(Duration::from_millis(10)).is_err());
+}
+
+#[test]
+fn dead_subscribers_are_pruned(mailbox: Mailbox<u32>) {
+    let bus = EventBus::<u32>::link().start(()).unwrap();
+    // The subscriber exits right after subscribing.
+    spawn_link!(|bus, mailbox: Mailbox<u32>| bus.subscribe(mailbox.this()));
+    sleep(Duration::from_millis(10));
+
+    bus.subscribe(mailbox.this());
+    assert_eq!(bus.subscriber_count(), 2);
+    bus.publish(1);
+    assert_eq!(mailbox.receive(), 1);
+    assert_eq!(bus.subscriber_count(), 1);
+}
@@ -16,5 +16,5 @@ fn catch_panic_capture() {
 
 #[test]
 fn catch_assert_fail() {
-    assert!(catch_panic(|| assert!(false)).is_err())
+    assert!(catch_panic(|| assert_eq!(1, 2)).is_err())
 }
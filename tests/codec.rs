@@ -0,0 +1,73 @@
+use std::io::{Cursor, ErrorKind};
+
+use lunatic::net::{Endianness, LengthDelimitedCodec, LengthPrefix};
+use lunatic::test;
+
+// Fixtures produced with Python's `struct.pack` and a Protocol Buffers
+// `writeDelimitedTo` of a 300 byte message.
+const BIG_ENDIAN_U32: &[u8] = b"\x00\x00\x00\x05hello";
+const LITTLE_ENDIAN_U16: &[u8] = b"\x05\x00hello";
+const BIG_ENDIAN_U64: &[u8] = b"\x00\x00\x00\x00\x00\x00\x00\x05hello";
+const VARINT_PREFIX_300: &[u8] = b"\xac\x02";
+
+fn codec(endianness: Endianness, length_prefix: LengthPrefix) -> LengthDelimitedCodec {
+    let mut codec = LengthDelimitedCodec::new();
+    codec
+        .set_endianness(endianness)
+        .set_length_prefix(length_prefix);
+    codec
+}
+
+fn encode(codec: &LengthDelimitedCodec, frame: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    codec.write_frame(&mut buffer, frame).unwrap();
+    buffer
+}
+
+#[test]
+fn default_is_big_endian_u32() {
+    let codec = LengthDelimitedCodec::new();
+    assert_eq!(codec.endianness(), Endianness::Big);
+    assert_eq!(codec.length_prefix(), LengthPrefix::U32);
+    assert_eq!(encode(&codec, b"hello"), BIG_ENDIAN_U32);
+}
+
+#[test]
+fn fixed_width_fixtures() {
+    for (codec, fixture) in [
+        (codec(Endianness::Big, LengthPrefix::U32), BIG_ENDIAN_U32),
+        (
+            codec(Endianness::Little, LengthPrefix::U16),
+            LITTLE_ENDIAN_U16,
+        ),
+        (codec(Endianness::Big, LengthPrefix::U64), BIG_ENDIAN_U64),
+    ] {
+        assert_eq!(encode(&codec, b"hello"), fixture);
+        let frame = codec.read_frame(&mut Cursor::new(fixture)).unwrap();
+        assert_eq!(frame, b"hello");
+    }
+}
+
+#[test]
+fn varint_fixture() {
+    let codec = codec(Endianness::Big, LengthPrefix::Varint);
+    let frame = vec![1; 300];
+    let encoded = encode(&codec, &frame);
+    assert_eq!(&encoded[..2], VARINT_PREFIX_300);
+    assert_eq!(codec.read_frame(&mut Cursor::new(encoded)).unwrap(), frame);
+}
+
+#[test]
+fn frame_length_limits() {
+    let mut codec = codec(Endianness::Little, LengthPrefix::U16);
+    let err = codec
+        .write_frame(&mut Vec::new(), &vec![0; 70_000])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    codec.set_max_frame_length(4);
+    let err = codec
+        .read_frame(&mut Cursor::new(LITTLE_ENDIAN_U16))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
@@ -71,6 +71,26 @@ fn config_with_memory_limit() {
     let _ = task.result();
 }
 
+#[test]
+fn cloned_config_keeps_settings() {
+    let config = ProcessConfig::new()
+        .unwrap()
+        .with_max_memory(50_000_000)
+        .with_env("hello", "world")
+        .with_argument("test1");
+    let copy = config.clone();
+    drop(config);
+    assert_eq!(copy.get_max_memory(), 50_000_000);
+
+    let task = spawn_link!(@task &copy, || {
+        let args: Vec<String> = std::env::args().collect();
+        (std::env::var("hello").unwrap(), args)
+    });
+    let (env, args) = task.result();
+    assert_eq!(env, "world");
+    assert_eq!(args, vec!["test1".to_owned()]);
+}
+
 #[test]
 #[should_panic]
 fn config_with_compute_limit() {
@@ -101,6 +121,19 @@ fn config_env_variable() {
     assert!(std::env::var("foo").is_err());
 }
 
+#[test]
+fn config_builder() {
+    let config = ProcessConfig::new()
+        .unwrap()
+        .with_max_memory(5_000_000)
+        .with_can_spawn_processes(true)
+        .with_env("hello", "world");
+    assert_eq!(config.get_max_memory(), 5_000_000);
+
+    let task = spawn_link!(@task &config, || std::env::var("hello").unwrap());
+    assert_eq!(task.result(), "world");
+}
+
 #[test]
 fn config_cli_args() {
     let mut config = ProcessConfig::new().unwrap();
@@ -127,3 +160,54 @@ fn config_file_operations() {
     });
     let _ = task.result();
 }
+
+#[rustversion::before(1.67)]
+#[test]
+fn config_preopen_dir_as() {
+    let mut config = ProcessConfig::new().unwrap();
+    assert!(config.preopen_dir_as(".", "/data").is_err());
+    config.preopen_dir_as(".", ".").unwrap();
+
+    let task = spawn_link!(@task &config, || {
+        // Only the pre-opened directory is accessible
+        std::fs::read_dir(".").is_ok() && std::fs::read_dir("..").is_err()
+    });
+    assert!(task.result());
+}
+
+#[test]
+fn capability_flags() {
+    let mut config = ProcessConfig::new().unwrap();
+    assert!(!config.allow_process_spawn());
+    assert!(!config.allow_distributed());
+    assert!(!config.allow_filesystem());
+    assert!(config.allow_networking());
+
+    config.set_allow_process_spawn(true);
+    assert!(config.allow_process_spawn());
+    assert!(config.allow_distributed());
+
+    // Only capabilities that the runtime can enforce are accepted.
+    assert!(config.set_allow_networking(true).is_ok());
+    assert!(config.set_allow_networking(false).is_err());
+    assert!(config.set_allow_filesystem(false).is_ok());
+    assert!(config.set_allow_filesystem(true).is_err());
+    assert!(config.set_allow_distributed(false).is_err());
+}
+
+#[test]
+fn serialize_config() {
+    let mut config = ProcessConfig::new().unwrap();
+    config.set_max_memory(5_000_000);
+    config.set_max_fuel(10);
+    config.set_can_spawn_processes(true);
+
+    let bytes = bincode::serialize(&config).unwrap();
+    let copy: ProcessConfig = bincode::deserialize(&bytes).unwrap();
+    assert_ne!(copy.id(), config.id());
+    assert_eq!(copy.get_max_memory(), 5_000_000);
+    assert_eq!(copy.get_max_fuel(), 10);
+    assert!(copy.can_spawn_processes());
+    assert!(!copy.can_compile_modules());
+    assert!(!copy.can_create_configs());
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use lunatic::ap::{AbstractProcess, Config};
+use lunatic::distributed::{self, NodePicker};
+use lunatic::serializer::Bincode;
+use lunatic::{sleep, Mailbox, Process, ProcessConfig};
+use lunatic_test::test;
+
+#[test]
+fn nodes_contain_local_node() {
+    let nodes = distributed::nodes();
+    assert!(nodes.contains(&distributed::node_id()));
+}
+
+#[test]
+fn remote_nodes_exclude_local_node() {
+    let nodes = distributed::remote_nodes();
+    assert!(!nodes.contains(&distributed::node_id()));
+}
+
+struct Worker;
+
+impl AbstractProcess for Worker {
+    type Arg = ();
+    type State = ();
+    type Serializer = Bincode;
+    type Handlers = ();
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: ()) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+struct LocalPicker;
+
+impl NodePicker for LocalPicker {
+    fn pick_node(&self, nodes: &[u64]) -> u64 {
+        let local = distributed::node_id();
+        assert!(nodes.contains(&local));
+        local
+    }
+}
+
+#[test]
+fn spawn_on_picked_node() {
+    let worker = Worker::on_any_node_with(&LocalPicker).start(()).unwrap();
+    assert_eq!(worker.node_id(), distributed::node_id());
+}
+
+#[test]
+fn spawn_on_node_with_config(mailbox: Mailbox<bool>) {
+    // The spawned process is not allowed to create configs, unlike the test.
+    let config = ProcessConfig::new().unwrap();
+    assert!(!config.can_create_configs());
+    Process::spawn_node_config(
+        distributed::node_id(),
+        &config,
+        mailbox.this(),
+        |parent, _: Mailbox<()>| parent.send(ProcessConfig::new().is_err()),
+    );
+    assert!(mailbox.receive());
+}
+
+#[test]
+fn node_event_listener_stops() {
+    let listener = distributed::on_node_event(|_| {});
+    sleep(Duration::from_millis(150));
+    assert!(listener.is_active());
+    listener.stop();
+    sleep(Duration::from_millis(10));
+    assert!(!listener.is_active());
+}
@@ -0,0 +1,23 @@
+use lunatic::{LunaticError, WasmModule};
+use lunatic_test::test;
+
+#[test]
+fn error_display() {
+    let error = match WasmModule::new(b"not a wasm module") {
+        Ok(_) => panic!("invalid module compiled"),
+        Err(error) => error,
+    };
+    assert!(!error.to_string().is_empty());
+
+    // Can be used as a standard error.
+    let error: Box<dyn std::error::Error> = Box::new(error);
+    assert!(!error.to_string().is_empty());
+}
+
+#[test]
+fn permission_denied_display() {
+    assert_eq!(
+        LunaticError::PermissionDenied.to_string(),
+        "Permission denied"
+    );
+}
@@ -0,0 +1,71 @@
+use lunatic::hashring::HashRing;
+use lunatic::test;
+
+fn assignments(ring: &HashRing<u64>) -> Vec<u64> {
+    (0..10_000).map(|key| *ring.get(&key).unwrap()).collect()
+}
+
+#[test]
+fn empty_ring() {
+    let ring = HashRing::<u64>::new();
+    assert_eq!(ring.get(&"key"), None);
+    assert!(ring.is_empty());
+}
+
+#[test]
+fn add_remaps_only_to_new_member() {
+    let mut ring = HashRing::new();
+    for node in 0..4 {
+        ring.add(node);
+    }
+    let before = assignments(&ring);
+    ring.add(4);
+    let after = assignments(&ring);
+
+    let moved: Vec<_> = before
+        .iter()
+        .zip(&after)
+        .filter(|(before, after)| before != after)
+        .collect();
+    // All moved keys go to the new member, about a fifth of them.
+    assert!(moved.iter().all(|(_, after)| **after == 4));
+    assert!(moved.len() > 1_000 && moved.len() < 3_000);
+}
+
+#[test]
+fn remove_remaps_only_removed_member() {
+    let mut ring = HashRing::new();
+    for node in 0..5 {
+        ring.add(node);
+    }
+    let before = assignments(&ring);
+    assert!(ring.remove(&2));
+    assert!(!ring.remove(&2));
+    let after = assignments(&ring);
+
+    for (before, after) in before.iter().zip(&after) {
+        if *before != 2 {
+            assert_eq!(before, after);
+        }
+        assert_ne!(*after, 2);
+    }
+}
+
+#[test]
+fn weighted_members() {
+    let mut ring = HashRing::new();
+    ring.add(0);
+    ring.add_weighted(1, 3);
+    assert_eq!(ring.weight(&1), Some(3));
+
+    let heavy = assignments(&ring).iter().filter(|node| **node == 1).count();
+    // The heavier member gets about three quarters of the keys.
+    assert!(heavy > 6_500 && heavy < 8_500);
+}
+
+#[test]
+#[should_panic]
+fn weight_overflow() {
+    let mut ring = HashRing::new();
+    ring.add_weighted(0, u32::MAX);
+}
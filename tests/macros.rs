@@ -1,5 +1,5 @@
 use lunatic::protocol::End;
-use lunatic::{spawn, spawn_link, test, ProcessConfig};
+use lunatic::{spawn, spawn_link, test, ProcessConfig, ProcessName};
 
 #[test]
 fn spawn() {
@@ -181,3 +181,12 @@ fn func1() {}
 fn func2(a: i32, b: i32) {
     assert_eq!(a, b)
 }
+
+#[derive(ProcessName)]
+#[lunatic(process_name_format = "{pkg}:{ident}")]
+struct FormattedName;
+
+#[test]
+fn process_name_format() {
+    assert_eq!(FormattedName.process_name(), "lunatic:FormattedName");
+}
@@ -2,8 +2,10 @@ use std::time::Duration;
 
 use lunatic::ap::handlers::Request;
 use lunatic::ap::{AbstractProcess, Config, RequestHandler, State};
+use lunatic::bytes::SharedBytes;
+use lunatic::distributed::{receive_chunked, send_chunked, Chunk, ChunkError};
 use lunatic::serializer::Json;
-use lunatic::{spawn_link, Mailbox, Process};
+use lunatic::{spawn_link, Mailbox, MailboxError, MessageSource, Process, Tag};
 use lunatic_test::test;
 
 #[test]
@@ -18,6 +20,13 @@ fn message_vector() {
     assert_eq!(child.result(), vec![1, 2, 3, 4, 5]);
 }
 
+#[test]
+fn message_shared_bytes() {
+    let bytes = SharedBytes::from(vec![1, 2, 3, 4, 5]);
+    let child = spawn_link!(@task |input = { bytes.clone() }| input);
+    assert_eq!(child.result(), bytes);
+}
+
 #[test]
 fn message_custom_type() {
     let child = spawn_link!(@task || X {
@@ -114,6 +123,142 @@ fn timeout(mailbox: Mailbox<u64>) {
     assert!(result.unwrap_err().is_timed_out())
 }
 
+#[test]
+fn sleep_or_message(mailbox: Mailbox<u64>) {
+    assert_eq!(
+        lunatic::sleep_or_message(&mailbox, Duration::from_millis(10)),
+        None
+    );
+
+    let this = mailbox.this();
+    this.send_after(7, Duration::from_millis(10));
+    let started = std::time::Instant::now();
+    assert_eq!(
+        lunatic::sleep_or_message(&mailbox, Duration::from_secs(10)),
+        Some(7)
+    );
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn tag_receive_is_fair(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    let (a, b) = (Tag::new(), Tag::new());
+    for i in 0..4 {
+        this.tag_send(if i % 2 == 0 { b } else { a }, i);
+    }
+    // Messages are received in arrival order, independent of the tag order.
+    for i in 0..4 {
+        assert_eq!(mailbox.tag_receive(&[a, b]), i);
+    }
+}
+
+#[test]
+fn tag_from_id(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    this.send(1);
+    this.tag_send(Tag::from_id(42), 2);
+    assert_eq!(mailbox.tag_receive(&[Tag::from_id(42)]), 2);
+    assert_eq!(mailbox.receive(), 1);
+}
+
+#[test]
+fn receive_matching(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    for i in 1..=3 {
+        this.send(i);
+    }
+    assert_eq!(mailbox.receive_matching(|i| *i == 2), 2);
+    // The other messages keep their order.
+    assert_eq!(mailbox.receive(), 1);
+    assert_eq!(mailbox.receive(), 3);
+}
+
+#[test]
+fn receive_matching_keeps_undecodable(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    let bytes = unsafe { Process::<u8>::new(this.node_id(), this.id()) };
+    bytes.send(7);
+    this.send(2);
+    assert_eq!(mailbox.receive_matching(|i| *i == 2), 2);
+    assert!(mailbox.peek().is_err());
+    // The message that can't be read as `u64` is still in the mailbox.
+    let mailbox: Mailbox<u8> = unsafe { Mailbox::new() };
+    assert_eq!(mailbox.receive(), 7);
+}
+
+#[test]
+fn receive_matching_keeps_signals(mailbox: Mailbox<u64>) {
+    let _ = mailbox.catch_link_failure();
+    let this = mailbox.this();
+    this.send(1);
+    let tag = Tag::new();
+    Process::spawn_link_tag((), tag, |_, _: Mailbox<()>| panic!("fail"));
+    // Give the link time to die.
+    lunatic::sleep(Duration::from_millis(50));
+    this.send(2);
+    assert_eq!(mailbox.receive_matching(|i| *i == 2), 2);
+    assert_eq!(mailbox.receive(), 1);
+    match mailbox.try_receive() {
+        Err(MailboxError::LinkDied(died)) => assert_eq!(died, tag),
+        other => panic!("expected a dead link, got {other:?}"),
+    }
+}
+
+#[test]
+fn peek(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    this.send(1);
+    this.send(2);
+    assert_eq!(mailbox.peek().unwrap(), 1);
+    assert_eq!(mailbox.receive(), 1);
+    assert_eq!(mailbox.receive(), 2);
+}
+
+#[test]
+fn peek_process_died(mailbox: Mailbox<u64>) {
+    let child = Process::spawn((), |_, _: Mailbox<()>| {
+        lunatic::sleep(Duration::from_millis(50));
+    });
+    mailbox.monitorable().monitor(child);
+    // Blocks until the monitored process dies.
+    match mailbox.peek() {
+        Err(MailboxError::ProcessDied(id)) => assert_eq!(id, child.id()),
+        other => panic!("expected a dead process, got {other:?}"),
+    }
+    // The signal stays in the mailbox.
+    match mailbox.try_receive() {
+        Err(MailboxError::ProcessDied(id)) => assert_eq!(id, child.id()),
+        other => panic!("expected a dead process, got {other:?}"),
+    }
+}
+
+#[test]
+fn drain(mailbox: Mailbox<u64>) {
+    assert!(mailbox.drain().is_empty());
+    let this = mailbox.this();
+    for i in 1..=5 {
+        this.send(i);
+    }
+    assert_eq!(mailbox.drain(), vec![1, 2, 3, 4, 5]);
+    assert!(mailbox.drain().is_empty());
+}
+
+fn sum_messages(source: &impl MessageSource<u64>, count: usize) -> u64 {
+    (0..count).map(|_| source.receive()).sum()
+}
+
+#[test]
+fn mailbox_as_message_source(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    for i in 1..=4 {
+        this.send(i);
+    }
+    assert_eq!(sum_messages(&mailbox, 4), 10);
+    let result = MessageSource::receive_timeout(&mailbox, Duration::from_millis(10));
+    assert!(result.unwrap_err().is_timed_out());
+}
+
 #[derive(serde::Serialize, serde::Deserialize)]
 struct Proc(Process<i32>);
 
@@ -143,3 +288,28 @@ struct X {
     enb: E,
     enc: E,
 }
+
+#[test]
+fn chunked_send(mailbox: Mailbox<Chunk>) {
+    let data: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+    let tag = Tag::new();
+    send_chunked(mailbox.this(), tag, &data, 1024);
+    let received = receive_chunked(&mailbox, tag, Duration::from_millis(100));
+    assert_eq!(received, Ok(data));
+}
+
+#[test]
+fn chunked_send_missing_chunk(mailbox: Mailbox<Chunk>) {
+    let tag = Tag::new();
+    let this = mailbox.this();
+    this.tag_send(
+        tag,
+        Chunk::Data {
+            index: 0,
+            bytes: vec![1],
+        },
+    );
+    this.tag_send(tag, Chunk::Complete { count: 2 });
+    let received = receive_chunked(&mailbox, tag, Duration::from_millis(10));
+    assert_eq!(received, Err(ChunkError::Missing(1)));
+}
@@ -0,0 +1,24 @@
+use lunatic::serializer::Bincode;
+use lunatic::{LunaticError, Process, WasmModule};
+use lunatic_test::test;
+
+/// A module exporting an empty `hello` function.
+const HELLO_MODULE: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section: () -> ()
+    0x03, 0x02, 0x01, 0x00, // function section
+    0x07, 0x09, 0x01, 0x05, b'h', b'e', b'l', b'l', b'o', 0x00, 0x00, // export section
+    0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // code section
+];
+
+#[test]
+fn spawn_from_bytes() {
+    let module = match WasmModule::new(HELLO_MODULE) {
+        Ok(module) => module,
+        // The runtime doesn't allow compiling modules.
+        Err(LunaticError::PermissionDenied) => return,
+        Err(error) => panic!("failed to compile module: {error}"),
+    };
+    let process: Process<(), Bincode> = module.spawn("hello", &[]).unwrap();
+    assert_ne!(process.id(), unsafe { Process::<()>::this() }.id());
+}
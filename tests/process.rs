@@ -2,7 +2,7 @@ use std::time::Duration;
 
 use lunatic::host::api::message::receive;
 use lunatic::host::api::process::die_when_link_dies;
-use lunatic::{spawn_link, Mailbox, Process, ProcessConfig};
+use lunatic::{spawn_link, Mailbox, MailboxError, Process, ProcessConfig, Tag};
 use lunatic_test::test;
 
 #[test]
@@ -144,8 +144,60 @@ fn is_alive() {
     });
     // Give enough time for process to be spawned
     lunatic::sleep(Duration::from_millis(10));
-    assert_eq!(child.is_alive(), true);
+    assert!(child.is_alive());
     // Give enough time to process to finish
     lunatic::sleep(Duration::from_millis(150));
-    assert_eq!(child.is_alive(), false);
+    assert!(!child.is_alive());
+}
+
+#[test]
+fn kill_and_wait(m: Mailbox<()>) {
+    let m = m.catch_link_failure();
+    let child = Process::spawn_link((), |_, _: Mailbox<()>| loop {
+        lunatic::sleep(Duration::from_secs(1));
+    });
+    lunatic::test::kill_and_wait(child);
+    assert!(!child.is_alive());
+    // The link died signal is already delivered.
+    assert!(m.try_receive().unwrap().is_signal());
+}
+
+#[test]
+fn trapped_link_died(mailbox: Mailbox<()>) {
+    // Don't die together with the failing child.
+    let _ = mailbox.catch_link_failure();
+    let tag = Tag::new();
+    Process::spawn_link_tag((), tag, |_, _: Mailbox<()>| panic!("fail"));
+    match mailbox.receive_timeout(Duration::from_secs(1)) {
+        Err(MailboxError::LinkDied(died)) => assert_eq!(died, tag),
+        other => panic!("expected a dead link, got {other:?}"),
+    }
+}
+
+#[test]
+fn registered_name(mailbox: Mailbox<()>) {
+    let this = mailbox.this();
+    assert_eq!(this.name(), None);
+
+    this.register(&"registered_name_test");
+    assert_eq!(this.name(), Some("registered_name_test".to_string()));
+    assert_eq!(
+        lunatic::host::registered_names(),
+        vec!["registered_name_test".to_string()]
+    );
+}
+
+#[test]
+fn name_registered_by_parent(mailbox: Mailbox<Vec<String>>) {
+    let child = Process::spawn_link(mailbox.this(), |parent, mailbox: Mailbox<()>| {
+        // Wait until the parent registered the name.
+        mailbox.receive();
+        parent.send(lunatic::host::registered_names());
+    });
+    child.register(&"name_registered_by_parent");
+    assert_eq!(child.name(), Some("name_registered_by_parent".to_string()));
+
+    // Only the parent knows about the registration.
+    child.send(());
+    assert_eq!(mailbox.receive(), Vec::<String>::new());
 }
@@ -1,4 +1,4 @@
-use lunatic::Process;
+use lunatic::{Mailbox, Process};
 use lunatic_test::test;
 
 #[test]
@@ -13,6 +13,82 @@ fn drop_unfinished() {
     let _ = protocol.receive();
 }
 
+#[test]
+fn try_receive_from_dead_peer() {
+    use lunatic::host::api::process::die_when_link_dies;
+    use lunatic::protocol::{End, Protocol, ProtocolError, Recv, Send};
+    // Protocols are always linked, survive the child failing.
+    unsafe { die_when_link_dies(0) };
+    let protocol = Process::spawn_link((), |_, protocol: Protocol<Recv<i32, Send<i32, End>>>| {
+        let (protocol, _) = protocol.receive();
+        // Dies without responding, the protocol is never finished.
+        std::mem::forget(protocol);
+        panic!("failed");
+    });
+    let protocol = protocol.try_send(1).unwrap();
+    let id = protocol.id();
+    assert_eq!(
+        protocol.try_receive().unwrap_err(),
+        ProtocolError::PeerDied(id)
+    );
+}
+
+#[test]
+fn try_receive_ignores_other_links(mailbox: Mailbox<()>) {
+    use std::time::Duration;
+
+    use lunatic::protocol::{End, Protocol, Send};
+    use lunatic::{MailboxError, Tag};
+    let _ = mailbox.catch_link_failure();
+    let tag = Tag::new();
+    Process::spawn_link_tag((), tag, |_, _: Mailbox<()>| panic!("fail"));
+    let protocol = Process::spawn_link((), |_, protocol: Protocol<Send<i32, End>>| {
+        lunatic::sleep(Duration::from_millis(50));
+        let _ = protocol.send(1);
+    });
+    let (_, value) = protocol.try_receive().unwrap();
+    assert_eq!(value, 1);
+    // The unrelated dead link is still in the mailbox.
+    match mailbox.try_receive() {
+        Err(MailboxError::LinkDied(died)) => assert_eq!(died, tag),
+        other => panic!("expected a dead link, got {other:?}"),
+    }
+}
+
+#[test]
+fn try_receive_timeout() {
+    use std::time::Duration;
+
+    use lunatic::protocol::{End, Protocol, ProtocolError, Send};
+    let protocol = Process::spawn_link((), |_, protocol: Protocol<Send<i32, End>>| {
+        lunatic::sleep(Duration::from_millis(100));
+        let _ = protocol.send(1);
+    });
+    let err = protocol
+        .try_receive_timeout(Duration::from_millis(10))
+        .unwrap_err();
+    assert_eq!(err, ProtocolError::TimedOut);
+}
+
+#[test]
+fn receive_timeout_returns_protocol() {
+    use std::time::Duration;
+
+    use lunatic::protocol::{End, Protocol, Send};
+    let protocol = Process::spawn_link((), |_, protocol: Protocol<Send<i32, End>>| {
+        lunatic::sleep(Duration::from_millis(50));
+        let _ = protocol.send(1);
+    });
+    // The peer is too slow, the protocol is handed back.
+    let (protocol, _) = protocol
+        .receive_timeout(Duration::from_millis(10))
+        .unwrap_err();
+    let (_, value) = protocol
+        .receive_timeout(Duration::from_millis(100))
+        .unwrap();
+    assert_eq!(value, 1);
+}
+
 #[cfg(feature = "msgpack_serializer")]
 #[test]
 fn msg_pack_serializer() {
@@ -36,6 +112,61 @@ fn msg_pack_serializer() {
     assert_eq!(0.88, result);
 }
 
+#[test]
+fn offer_add_or_quit() {
+    use lunatic::protocol::{Branch, End, Offer, Protocol, Recv, Send};
+    // Either add two numbers or quit right away.
+    type Calculator = Offer<Recv<(i32, i32), Send<i32, End>>, End>;
+
+    let spawn = || {
+        Process::spawn_link((), |(), proto: Protocol<Calculator>| match proto.offer() {
+            Branch::Left(add) => {
+                let (add, (a, b)) = add.receive();
+                let _ = add.send(a + b);
+            }
+            Branch::Right(_quit) => (),
+        })
+    };
+
+    let add = spawn().select_left().send((2, 3));
+    let (_, sum) = add.receive();
+    assert_eq!(sum, 5);
+
+    let _quit = spawn().select_right();
+}
+
+#[test]
+fn receive_in_loop() {
+    use lunatic::protocol::{Branch, End, Offer, Pop, Protocol, Rec, Recv, Send};
+    // Receive numbers until the other side ends the protocol, then send the sum.
+    type Sum = Rec<Offer<Recv<i32, Pop>, Send<i32, End>>>;
+
+    let protocol = Process::spawn_link((), |(), proto: Protocol<Sum>| {
+        let mut sum = 0;
+        let mut proto = proto.repeat();
+        loop {
+            match proto.offer() {
+                Branch::Left(number) => {
+                    let (number, value) = number.receive();
+                    sum += value;
+                    proto = number.pop().repeat();
+                }
+                Branch::Right(done) => {
+                    let _ = done.send(sum);
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut protocol = protocol.repeat();
+    for i in 1..=3 {
+        protocol = protocol.select_left().send(i).pop().repeat();
+    }
+    let (_, sum) = protocol.select_right().receive();
+    assert_eq!(sum, 6);
+}
+
 #[test]
 fn recursive_protocols() {
     use lunatic::protocol::Branch;
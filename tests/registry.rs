@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use lunatic::ap::{AbstractProcess, Config, ProcessRef};
+use lunatic::serializer::Bincode;
+use lunatic::{sleep, test};
+
+struct LeasedAP;
+
+impl AbstractProcess for LeasedAP {
+    type State = ();
+    type Serializer = Bincode;
+    type Arg = ();
+    type Handlers = ();
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: Self::Arg) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn lease_expires() {
+    let ap = LeasedAP::link().start(()).unwrap();
+    let lease = ap.register_with_lease(&"expires", Duration::from_millis(20));
+    assert_eq!(ProcessRef::<LeasedAP>::lookup(&"expires"), Some(ap));
+    sleep(Duration::from_millis(50));
+    assert!(!lease.is_active());
+    assert_eq!(ProcessRef::<LeasedAP>::lookup(&"expires"), None);
+}
+
+#[test]
+fn lease_keep_alive() {
+    let ap = LeasedAP::link().start(()).unwrap();
+    let lease = ap.register_with_lease(&"kept", Duration::from_millis(20));
+    let keep_alive = lease.keep_alive(Duration::from_millis(5));
+    sleep(Duration::from_millis(50));
+    assert_eq!(ProcessRef::<LeasedAP>::lookup(&"kept"), Some(ap));
+
+    keep_alive.stop();
+    sleep(Duration::from_millis(50));
+    assert_eq!(ProcessRef::<LeasedAP>::lookup(&"kept"), None);
+}
+
+#[test]
+fn lease_ends_with_process() {
+    let ap = LeasedAP::start(()).unwrap();
+    let lease = ap.register_with_lease(&"owner", Duration::from_secs(10));
+    ap.shutdown();
+    sleep(Duration::from_millis(10));
+    assert!(!lease.is_active());
+    assert_eq!(ProcessRef::<LeasedAP>::lookup(&"owner"), None);
+}
+
+#[test]
+fn lease_release() {
+    let ap = LeasedAP::link().start(()).unwrap();
+    let lease = ap.register_with_lease(&"released", Duration::from_secs(10));
+    lease.release();
+    sleep(Duration::from_millis(10));
+    assert_eq!(ProcessRef::<LeasedAP>::lookup(&"released"), None);
+}
@@ -0,0 +1,22 @@
+use lunatic::net::resolve_one;
+use lunatic_test::test;
+
+#[test]
+fn resolve_one_localhost() {
+    let addr = resolve_one("localhost:80").unwrap();
+    assert!(addr.ip().is_loopback());
+    assert_eq!(addr.port(), 80);
+}
+
+#[test]
+fn host_port_tuples() {
+    let addr = resolve_one(("localhost", 443u16)).unwrap();
+    assert!(addr.ip().is_loopback());
+    assert_eq!(addr.port(), 443);
+
+    let addr = resolve_one(("localhost".to_string(), 443u16)).unwrap();
+    assert_eq!(addr.port(), 443);
+
+    let addr = resolve_one(("::1", 8080u16)).unwrap();
+    assert_eq!(addr, "[::1]:8080".parse().unwrap());
+}
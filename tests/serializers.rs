@@ -1,6 +1,13 @@
+use std::io::{Read, Write};
+
+use lunatic::bytes::SharedBytes;
 use lunatic::net::TcpStream;
-use lunatic::serializer::{Bincode, Json, MessagePack};
+use lunatic::serializer::{
+    Bincode, CanSerialize, DecodeError, EncodeError, Json, JsonPretty, MessagePack, MessageRw,
+    Prost, Raw,
+};
 use lunatic::{test, Mailbox, Process};
+use serde::{Deserialize, Serialize};
 
 #[test]
 fn bincode_resource_serialization() {
@@ -19,3 +26,85 @@ fn msgpack_resource_serialization() {
     let stream = TcpStream::connect("google.com:80").unwrap();
     Process::spawn(stream, |_, _: Mailbox<(), MessagePack>| {});
 }
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Point {
+    #[prost(int32, tag = "1")]
+    x: i32,
+    #[prost(string, tag = "2")]
+    label: String,
+}
+
+#[test]
+fn prost_serialization(mailbox: Mailbox<Point, Prost>) {
+    let point = Point {
+        x: 42,
+        label: "origin".to_owned(),
+    };
+    mailbox.this().send(point.clone());
+    assert_eq!(mailbox.receive(), point);
+}
+
+#[test]
+fn raw_serialization(mailbox: Mailbox<Vec<u8>, Raw>) {
+    // Already encoded payload, e.g. a protobuf message from another system.
+    let payload = vec![0x08, 0x2a, 0x12, 0x06, b'o', b'r', b'i', b'g', b'i', b'n'];
+    mailbox.this().send(payload.clone());
+    assert_eq!(mailbox.receive(), payload);
+    mailbox.this().send(Vec::new());
+    assert_eq!(mailbox.receive(), Vec::<u8>::new());
+}
+
+#[test]
+fn raw_rejects_oversized_length(mailbox: Mailbox<Vec<u8>, Raw>) {
+    let this = mailbox.this();
+    // A length prefix without the promised payload.
+    let prefix = unsafe { Process::<u64, Bincode>::new(this.node_id(), this.id()) };
+    prefix.send(u64::MAX);
+    assert!(mailbox.try_receive().is_err());
+}
+
+#[test]
+fn raw_shared_bytes_serialization(mailbox: Mailbox<SharedBytes, Raw>) {
+    let payload = SharedBytes::from(vec![7u8; 1024]);
+    mailbox.this().send(payload.clone());
+    assert_eq!(mailbox.receive(), payload);
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Line {
+    from: (i32, i32),
+    to: (i32, i32),
+}
+
+/// Receives the encoded message as text.
+struct Text;
+
+impl CanSerialize<String> for Text {
+    fn encode(message: &String) -> Result<(), EncodeError> {
+        Ok(MessageRw {}.write_all(message.as_bytes())?)
+    }
+
+    fn decode() -> Result<String, DecodeError> {
+        let mut text = String::new();
+        MessageRw {}.read_to_string(&mut text)?;
+        Ok(text)
+    }
+}
+
+#[test]
+fn json_pretty_serialization(mailbox: Mailbox<String, Text>) {
+    let line = Line {
+        from: (0, 0),
+        to: (3, 4),
+    };
+    let this = unsafe { Process::<Line, JsonPretty>::this() };
+    this.send(line.clone());
+    let text = mailbox.receive();
+    assert!(text.contains('\n'), "not pretty-printed: {text}");
+
+    // Pretty output is decoded like any other Json.
+    this.send(line.clone());
+    let mailbox: Mailbox<Line, Json> = unsafe { Mailbox::new() };
+    assert_eq!(mailbox.receive(), line);
+}
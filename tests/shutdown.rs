@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use lunatic::shutdown::{run_app_shutdown, set_app_shutdown_grace_period};
+use lunatic::{on_app_shutdown, sleep, test, Mailbox, Process};
+
+// Each test registers its own name, so that tests running at the same time
+// don't report to each other.
+const PRIORITY_ORDER: &str = "shutdown-hooks-priority-order";
+const GRACE_PERIOD: &str = "shutdown-hooks-grace-period";
+
+fn report(name: &str, value: u32) {
+    Process::<u32>::lookup(&name).unwrap().send(value);
+}
+
+#[test]
+fn hooks_run_in_priority_order(mailbox: Mailbox<u32>) {
+    mailbox.this().register(&PRIORITY_ORDER);
+    on_app_shutdown(2, || report(PRIORITY_ORDER, 3));
+    on_app_shutdown(1, || report(PRIORITY_ORDER, 1));
+    on_app_shutdown(1, || report(PRIORITY_ORDER, 2));
+    on_app_shutdown(0, || panic!("failing hook"));
+
+    assert_eq!(run_app_shutdown(), 3);
+    for i in 1..=3 {
+        assert_eq!(mailbox.receive(), i);
+    }
+    // Hooks only run once.
+    assert_eq!(run_app_shutdown(), 0);
+}
+
+#[test]
+fn grace_period_skips_remaining_hooks(mailbox: Mailbox<u32>) {
+    mailbox.this().register(&GRACE_PERIOD);
+    set_app_shutdown_grace_period(Duration::from_millis(20));
+    on_app_shutdown(0, || sleep(Duration::from_millis(100)));
+    on_app_shutdown(1, || report(GRACE_PERIOD, 1));
+
+    assert_eq!(run_app_shutdown(), 0);
+    assert!(mailbox.receive_timeout(Duration::from_millis(50)).is_err());
+}
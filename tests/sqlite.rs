@@ -1,4 +1,9 @@
-use lunatic::sqlite::{Query, SqliteClient, Value};
+use std::time::Duration;
+
+use lunatic::sqlite::{
+    Dedup, DurableSender, Envelope, OpenFlags, Query, SqliteClient, SqliteCode, Value,
+};
+use lunatic::{sleep, spawn_link, Mailbox};
 use lunatic_test::test;
 
 #[test]
@@ -19,9 +24,287 @@ fn prepared_query() {
     assert_eq!(rows, vec![vec![Value::Text("Foo!".to_string())]]);
 }
 
+#[test]
+fn prepared_insert_with_bindings() {
+    let client = SqliteClient::connect("").unwrap();
+    client
+        .execute("create table users (name text, age integer)")
+        .unwrap();
+
+    client
+        .prepare_query("insert into users (name, age) values (?1, ?2)")
+        .bind_index(2, 36)
+        .bind_index(1, "Ada")
+        .execute();
+    client
+        .prepare_query("insert into users (name, age) values (:name, :age)")
+        .bind_named(":name", "Alan")
+        .bind_named(":age", 41)
+        .execute();
+
+    let rows = client.query("select name, age from users order by age");
+    assert_eq!(
+        rows,
+        vec![
+            vec![Value::Text("Ada".to_string()), Value::Int64(36)],
+            vec![Value::Text("Alan".to_string()), Value::Int64(41)],
+        ]
+    );
+}
+
+#[test]
+fn transaction_rolls_back_on_drop() {
+    let client = SqliteClient::connect("").unwrap();
+    client.execute("create table users (name text)").unwrap();
+
+    {
+        let transaction = client.transaction().unwrap();
+        transaction
+            .execute("insert into users (name) values ('Ada')")
+            .unwrap();
+    }
+    assert_eq!(
+        client.query("select count(*) from users"),
+        vec![vec![Value::Int64(0)]]
+    );
+
+    let transaction = client.transaction().unwrap();
+    transaction
+        .execute("insert into users (name) values ('Ada')")
+        .unwrap();
+    transaction.commit().unwrap();
+    assert_eq!(
+        client.query("select count(*) from users"),
+        vec![vec![Value::Int64(1)]]
+    );
+}
+
+#[test]
+fn query_as_struct() {
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct User {
+        id: i64,
+        name: String,
+        email: Option<String>,
+    }
+
+    let client = SqliteClient::connect("").unwrap();
+    client
+        .execute("create table users (id integer, name text, email text)")
+        .unwrap();
+    client
+        .execute("insert into users values (1, 'Ada', null), (2, 'Alan', 'alan@example.com')")
+        .unwrap();
+
+    let users: Vec<User> = client
+        .query_as("select id, name, email from users order by id")
+        .unwrap();
+    assert_eq!(
+        users,
+        vec![
+            User {
+                id: 1,
+                name: "Ada".to_string(),
+                email: None
+            },
+            User {
+                id: 2,
+                name: "Alan".to_string(),
+                email: Some("alan@example.com".to_string())
+            },
+        ]
+    );
+
+    let err = client
+        .query_as::<User>("select id, email from users")
+        .unwrap_err();
+    assert_eq!(err.code, SqliteCode::Mismatch);
+}
+
+#[test]
+fn query_iter_streams_rows() {
+    let client = SqliteClient::connect("").unwrap();
+
+    let rows = client.query_iter(
+        "with recursive numbers(n) as (select 1 union all select n + 1 from numbers where n < 10000) \
+         select n from numbers",
+    );
+    let mut count = 0;
+    for (i, row) in rows.enumerate() {
+        let row = row.unwrap();
+        assert_eq!(row.get(0), Some(&Value::Int64(i as i64 + 1)));
+        assert_eq!(row.get_named("n"), row.get(0));
+        assert_eq!(row.get_named("missing"), None);
+        count += 1;
+    }
+    assert_eq!(count, 10_000);
+}
+
+#[test]
+fn value_conversions() {
+    assert_eq!(i64::try_from(Value::from(42i64)), Ok(42));
+    assert_eq!(i32::try_from(Value::from(42i64)), Ok(42));
+    assert_eq!(f64::try_from(Value::from(1.5)), Ok(1.5));
+    assert_eq!(
+        String::try_from(Value::from("text")),
+        Ok("text".to_string())
+    );
+    assert_eq!(Vec::<u8>::try_from(Value::from(vec![1, 2])), Ok(vec![1, 2]));
+    assert_eq!(Option::<i64>::try_from(Value::from(None::<i64>)), Ok(None));
+    assert_eq!(
+        Option::<i64>::try_from(Value::from(Some(7i64))),
+        Ok(Some(7))
+    );
+
+    let err = i64::try_from(Value::from("text")).unwrap_err();
+    assert_eq!(err.code, SqliteCode::Mismatch);
+    let err = i32::try_from(Value::from(i64::MAX)).unwrap_err();
+    assert_eq!(err.code, SqliteCode::Mismatch);
+}
+
+#[test]
+fn query_columns() {
+    let client = SqliteClient::connect("").unwrap();
+    client
+        .execute("create table users (id integer, name text)")
+        .unwrap();
+
+    let rows = client
+        .prepare_query("select id, name as username from users")
+        .execute_iter();
+    let names: Vec<_> = rows.columns().iter().map(|column| column.name()).collect();
+    assert_eq!(names, vec!["id", "username"]);
+}
+
+#[test]
+fn connect_with_flags() {
+    let client = SqliteClient::connect_with(
+        "",
+        OpenFlags::READ_WRITE | OpenFlags::CREATE | OpenFlags::MEMORY,
+    )
+    .unwrap();
+    client.execute("create table users (name text)").unwrap();
+    client
+        .execute("insert into users (name) values ('Ada')")
+        .unwrap();
+
+    let read_only =
+        SqliteClient::connect_with("", OpenFlags::READ_ONLY | OpenFlags::MEMORY).unwrap();
+    let err = read_only
+        .execute("create table users (name text)")
+        .unwrap_err();
+    assert_eq!(err.code, SqliteCode::Readonly);
+
+    let err =
+        SqliteClient::connect_with("", OpenFlags::READ_ONLY | OpenFlags::READ_WRITE).unwrap_err();
+    assert_eq!(err.code, SqliteCode::Misuse);
+}
+
+#[test]
+fn busy_timeout_concurrent_writers() {
+    // Opened by the host, so it needs to be a real file and not `""`.
+    let path = format!("{}/busy_timeout.db", env!("CARGO_TARGET_TMPDIR"));
+    let client = SqliteClient::connect(&path).unwrap();
+    client.execute("drop table if exists events").unwrap();
+    client
+        .execute("create table events (writer integer)")
+        .unwrap();
+
+    let writers: Vec<_> = (0..2)
+        .map(|writer| {
+            spawn_link!(@task |path = { path.clone() }, writer| {
+                let client = SqliteClient::connect(&path).unwrap();
+                client.set_busy_timeout(Duration::from_millis(500)).unwrap();
+                let transaction = client.transaction().unwrap();
+                for _ in 0..50 {
+                    transaction
+                        .execute(&format!("insert into events (writer) values ({writer})"))
+                        .unwrap();
+                }
+                sleep(Duration::from_millis(20));
+                transaction.commit().is_ok()
+            })
+        })
+        .collect();
+    for writer in writers {
+        assert!(writer.result());
+    }
+    assert_eq!(
+        client.query("select count(*) from events"),
+        vec![vec![Value::Int64(100)]]
+    );
+    // Only works if the directory is pre-opened, otherwise the file is left
+    // in the temporary directory of the target.
+    let _ = std::fs::remove_file(&path);
+}
+
 #[test]
 fn execute() {
     let client = SqliteClient::connect("").unwrap();
 
     client.execute("select \"Hello\"").unwrap();
 }
+
+#[test]
+fn durable_sender_spills_to_disk(mailbox: Mailbox<Envelope<String>>) {
+    let client = SqliteClient::connect("").unwrap();
+
+    // Target that is already dead.
+    let dead = spawn_link!(|_mailbox: Mailbox<Envelope<String>>| {});
+    sleep(Duration::from_millis(10));
+
+    let mut sender = DurableSender::open(client, "audit", dead, 1).unwrap();
+    for event in ["a", "b", "c"] {
+        sender.send(event.to_owned()).unwrap();
+    }
+    assert_eq!(sender.pending(), 3);
+    assert_eq!(sender.spilled(), 2);
+
+    assert_eq!(sender.reconnect(mailbox.this()).unwrap(), 3);
+    assert_eq!(sender.pending(), 0);
+
+    let mut dedup = Dedup::new();
+    let received: Vec<_> = (0..3)
+        .filter_map(|_| dedup.accept(mailbox.receive()))
+        .collect();
+    assert_eq!(received, vec!["a", "b", "c"]);
+}
+
+#[test]
+fn durable_sender_rejects_undecodable_message(mailbox: Mailbox<Envelope<String>>) {
+    let client = SqliteClient::connect("").unwrap();
+    let dead = spawn_link!(|_mailbox: Mailbox<Envelope<String>>| {});
+    sleep(Duration::from_millis(10));
+    let mut sender = DurableSender::open(client, "broken", dead, 0).unwrap();
+    sender.send("a".to_owned()).unwrap();
+
+    // Not a valid bincode encoded `String`.
+    client
+        .prepare_query("UPDATE lunatic_durable_queue SET message = ? WHERE queue = ?")
+        .bind(vec![0xffu8])
+        .bind("broken")
+        .execute();
+    let err = DurableSender::open(client, "broken", mailbox.this(), 0).err();
+    assert_eq!(err.map(|err| err.code), Some(SqliteCode::Mismatch));
+}
+
+#[test]
+fn durable_sender_returns_failed_spill() {
+    let client = SqliteClient::connect("").unwrap();
+    let dead = spawn_link!(|_mailbox: Mailbox<Envelope<String>>| {});
+    sleep(Duration::from_millis(10));
+    let mut first = DurableSender::open(client, "shared", dead, 0).unwrap();
+    let mut second = DurableSender::open(client, "shared", dead, 0).unwrap();
+    first.send("a".to_owned()).unwrap();
+    // Both senders use the same sequence number, the insert violates the key.
+    assert!(second.send("b".to_owned()).is_err());
+    assert_eq!(second.spilled(), 0);
+}
+
+#[test]
+fn dedup_drops_replayed_messages() {
+    let mut dedup = Dedup::new();
+    assert_eq!(dedup.accept(Envelope { seq: 0, message: 1 }), Some(1));
+    assert_eq!(dedup.accept(Envelope { seq: 1, message: 2 }), Some(2));
+    assert_eq!(dedup.accept(Envelope { seq: 1, message: 2 }), None);
+}
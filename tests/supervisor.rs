@@ -1,9 +1,13 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use lunatic::ap::handlers::{Message, Request};
-use lunatic::ap::{AbstractProcess, Config, MessageHandler, ProcessRef, RequestHandler, State};
+use lunatic::ap::{
+    set_exit_value, AbstractProcess, Config, MessageHandler, ProcessRef, RequestHandler, State,
+};
 use lunatic::serializer::{Json, MessagePack};
-use lunatic::supervisor::{Supervisor, SupervisorConfig, SupervisorStrategy};
+use lunatic::supervisor::{
+    ChildRestart, Supervisor, SupervisorChildren, SupervisorConfig, SupervisorStrategy,
+};
 use lunatic::{sleep, spawn, test, ProcessConfig};
 
 const LOGGER_NAME: &'static str = "logger/assert_order";
@@ -444,6 +448,56 @@ fn four_failing_process_rest_for_all() {
     );
 }
 
+#[test]
+fn rest_for_one_keeps_earlier_children() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A, A, A);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_strategy(SupervisorStrategy::RestForOne);
+            config.set_args(((0, 'a'), (0, 'b'), (0, 'c')));
+        }
+    }
+
+    let sup = Sup::link().start(()).unwrap();
+    let (a, b, c) = sup.children();
+    b.send(Panic);
+    sleep(Duration::from_millis(10));
+
+    let (a_new, b_new, c_new) = sup.children();
+    assert_eq!(a.id(), a_new.id());
+    assert_ne!(b.id(), b_new.id());
+    assert_ne!(c.id(), c_new.id());
+    assert!(a.is_alive());
+    assert!(!c.is_alive());
+}
+
+#[test]
+fn children_refs() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A, A);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_args(((1, 'a'), (2, 'b')));
+        }
+    }
+
+    let sup = Sup::link().start(()).unwrap();
+    let (a, b): SupervisorChildren<Sup> = sup.children();
+    assert_eq!(a.request(Count), 1);
+    assert_eq!(b.request(Count), 2);
+
+    // The references are updated after a restart.
+    b.send(Panic);
+    sleep(Duration::from_millis(10));
+    let (_, b) = sup.children();
+    assert_eq!(b.request(Count), 2);
+}
+
 #[test]
 fn ten_children_sup() {
     struct Sup;
@@ -621,3 +675,226 @@ fn env_var_config() {
     );
     assert_eq!(named.request(GetEnvVar("no".to_string())), None);
 }
+
+#[test]
+fn max_restarts_exceeded() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A,);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_max_restarts(3);
+            config.set_restart_period(Duration::from_millis(100));
+            config.set_args(((0, 'a'),));
+        }
+    }
+
+    let sup = Sup::start(()).unwrap();
+    // Crash the child 5 times within 100ms
+    for _ in 0..5 {
+        if !sup.is_alive() {
+            break;
+        }
+        sup.children().0.send(Panic);
+        sleep(Duration::from_millis(10));
+    }
+    assert!(!sup.is_alive());
+}
+
+#[test]
+fn backoff_between_restarts() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A,);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_backoff(Duration::from_millis(20), Duration::from_secs(1), 2.0);
+            config.set_args(((0, 'a'),));
+        }
+    }
+
+    let sup = Sup::link().start(()).unwrap();
+    let restart_delay = || {
+        let child = sup.children().0;
+        let start = Instant::now();
+        child.send(Panic);
+        while sup.children().0.id() == child.id() {
+            sleep(Duration::from_millis(1));
+        }
+        start.elapsed()
+    };
+    let first = restart_delay();
+    let second = restart_delay();
+    assert!(first >= Duration::from_millis(20));
+    assert!(second > first);
+}
+
+#[test]
+#[should_panic]
+fn backoff_rejects_shrinking_multiplier() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A,);
+
+        fn init(_: &mut SupervisorConfig<Self>, _: ()) {}
+    }
+
+    let mut config = SupervisorConfig::<Sup>::default();
+    config.set_backoff(Duration::from_millis(20), Duration::from_secs(1), 0.5);
+}
+
+#[test]
+fn permanent_child_restarts_after_normal_exit() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A,);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_restart(ChildRestart::Permanent);
+            config.set_args(((0, 'a'),));
+        }
+    }
+
+    let sup = Sup::link().start(()).unwrap();
+    let child = sup.children().0;
+    child.shutdown();
+    sleep(Duration::from_millis(10));
+
+    let restarted = sup.children().0;
+    assert_ne!(child.id(), restarted.id());
+    assert_eq!(restarted.request(Count), 0);
+}
+
+#[test]
+fn transient_child_restarts_only_after_failure() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A,);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_restart(ChildRestart::Transient);
+            config.set_args(((0, 'a'),));
+        }
+    }
+
+    let sup = Sup::link().start(()).unwrap();
+
+    // Failures restart the child
+    let child = sup.children().0;
+    child.send(Panic);
+    sleep(Duration::from_millis(10));
+    let restarted = sup.children().0;
+    assert_ne!(child.id(), restarted.id());
+
+    // Normal exits don't
+    restarted.shutdown();
+    sleep(Duration::from_millis(10));
+    let exited = sup.children().0;
+    assert_eq!(restarted.id(), exited.id());
+    assert!(!exited.is_alive());
+}
+
+#[test]
+fn restart_per_child() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A, A);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_restarts((ChildRestart::Transient, ChildRestart::Temporary));
+            config.set_args(((0, 'a'), (0, 'b')));
+        }
+    }
+
+    let sup = Sup::link().start(()).unwrap();
+    let (a, b) = sup.children();
+    a.send(Panic);
+    b.send(Panic);
+    sleep(Duration::from_millis(10));
+
+    // Only the transient child is restarted after a failure
+    let (a_restarted, b_failed) = sup.children();
+    assert_ne!(a.id(), a_restarted.id());
+    assert_eq!(b.id(), b_failed.id());
+    assert!(!b_failed.is_alive());
+
+    // but not after a normal exit
+    a_restarted.shutdown();
+    sleep(Duration::from_millis(10));
+    let (a_exited, _) = sup.children();
+    assert_eq!(a_restarted.id(), a_exited.id());
+    assert!(!a_exited.is_alive());
+}
+
+struct Doubler;
+
+impl AbstractProcess for Doubler {
+    type Arg = u32;
+    type State = u32;
+    type Serializer = Json;
+    type Handlers = ();
+    type StartupError = ();
+
+    fn init(_: Config<Self>, value: u32) -> Result<u32, ()> {
+        Ok(value)
+    }
+
+    fn terminate(value: u32) {
+        set_exit_value(&(value * 2));
+    }
+}
+
+#[test]
+fn transient_child_exit_value() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (Doubler,);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_restart(ChildRestart::Transient);
+            config.set_args((21,));
+        }
+    }
+
+    let sup = Sup::link().start(()).unwrap();
+    let child = sup.children().0;
+    assert_eq!(sup.exit_value(&child), None);
+
+    child.shutdown();
+    sleep(Duration::from_millis(10));
+    let value = sup.exit_value(&child).unwrap();
+    assert_eq!(value.decode::<u32>(), Some(42));
+}
+
+#[test]
+fn temporary_child_never_restarts() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A,);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_restart(ChildRestart::Temporary);
+            config.set_args(((0, 'a'),));
+        }
+    }
+
+    let sup = Sup::link().start(()).unwrap();
+    let child = sup.children().0;
+    child.send(Panic);
+    sleep(Duration::from_millis(10));
+
+    let failed = sup.children().0;
+    assert_eq!(child.id(), failed.id());
+    assert!(!failed.is_alive());
+
+    // Shutting down the supervisor skips exited children
+    sup.shutdown();
+}
@@ -1,6 +1,7 @@
 use std::time::Duration;
 
-use lunatic::{sleep, spawn_link, ProcessConfig};
+use lunatic::protocol::TaskDied;
+use lunatic::{join_all, sleep, spawn_link, try_join_all, Mailbox, ProcessConfig};
 use lunatic_test::test;
 
 #[test]
@@ -45,3 +46,41 @@ fn timeout_task() {
     let result = task.result_timeout(Duration::from_millis(10));
     assert!(result.unwrap_err().is_timed_out());
 }
+
+#[test]
+fn poll_task_result() {
+    let task = spawn_link!(@task || {
+        sleep(Duration::from_millis(50));
+        5
+    });
+    let task = task.poll_result(Duration::from_millis(10)).unwrap_err();
+    let result = task.poll_result(Duration::from_millis(100));
+    assert_eq!(result.ok(), Some(5));
+}
+
+#[test]
+fn join_all_tasks() {
+    let tasks = (0..5u64)
+        .map(|i| {
+            spawn_link!(@task |i| {
+                sleep(Duration::from_millis(10 * (5 - i)));
+                i * 2
+            })
+        })
+        .collect();
+    assert_eq!(join_all(tasks), vec![0, 2, 4, 6, 8]);
+}
+
+#[test]
+fn try_join_all_tasks(mailbox: Mailbox<()>) {
+    // Don't die together with the failing task.
+    let _ = mailbox.catch_link_failure();
+    let tasks = vec![
+        spawn_link!(@task || 1),
+        spawn_link!(@task || panic!("")),
+        spawn_link!(@task || 3),
+    ];
+    let failed = tasks[1].id();
+    let results = try_join_all(tasks);
+    assert_eq!(results, vec![Ok(1), Err(TaskDied(failed)), Ok(3)]);
+}
@@ -0,0 +1,125 @@
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use lunatic::{net, sleep, spawn_link, test, Mailbox};
+
+#[test]
+fn incoming() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    for _ in 0..2 {
+        spawn_link!(|addr| {
+            let _ = net::TcpStream::connect(addr).unwrap();
+        });
+    }
+
+    let streams: Vec<_> = listener.incoming().take(2).collect();
+    assert_eq!(streams.len(), 2);
+    assert!(streams.iter().all(|stream| stream.is_ok()));
+}
+
+#[test]
+fn incoming_limited(mailbox: Mailbox<u64>) {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    for _ in 0..2 {
+        spawn_link!(|addr| {
+            let _ = net::TcpStream::connect(addr).unwrap();
+        });
+    }
+
+    let mut incoming = listener.incoming_limited(1);
+    let _stream = incoming.next().unwrap().unwrap();
+    let handler = spawn_link!(|| sleep(Duration::from_millis(25)));
+    incoming.track(&handler);
+    assert_eq!(incoming.live(), 1);
+    mailbox.this().send(7);
+
+    // The second connection is only accepted after the handler finishes.
+    let start = Instant::now();
+    let _stream = incoming.next().unwrap().unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert_eq!(incoming.live(), 0);
+    // Other messages are not received while waiting.
+    assert_eq!(mailbox.receive(), 7);
+}
+
+#[test]
+fn bind_with_backlog() {
+    let mut options = net::TcpBindOptions::new();
+    assert_eq!(options.backlog(), net::TcpListener::DEFAULT_BACKLOG);
+    assert!(net::TcpListener::bind_with("127.0.0.1:0", &options).is_ok());
+
+    options.set_backlog(16);
+    let err = net::TcpListener::bind_with("127.0.0.1:0", &options).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn write_all_large_buffer() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_link!(|addr| {
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        stream.write_all(&vec![7u8; 4 * 1024 * 1024]).unwrap();
+    });
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut received = Vec::new();
+    stream.read_to_end(&mut received).unwrap();
+    assert_eq!(received.len(), 4 * 1024 * 1024);
+    assert!(received.iter().all(|&byte| byte == 7));
+}
+
+#[test]
+fn peek_does_not_consume() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_link!(|addr| {
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"PING").unwrap();
+    });
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut peeked = [0; 4];
+    let mut n = 0;
+    while n < 4 {
+        n = stream.peek(&mut peeked).unwrap();
+    }
+    let mut read = [0; 4];
+    stream.read_exact(&mut read).unwrap();
+    assert_eq!(peeked, read);
+    assert_eq!(stream.peek(&mut peeked).unwrap(), 0);
+}
+
+#[test]
+fn nodelay_unsupported() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let err = stream.set_nodelay(true).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    let err = stream.nodelay().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn split_halves_in_different_processes() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_link!(|addr| {
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        let mut buffer = [0; 5];
+        stream.read_exact(&mut buffer).unwrap();
+        stream.write_all(&buffer).unwrap();
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let (mut read_half, write_half) = stream.split();
+    spawn_link!(|write_half| {
+        write_half.write_all(b"hello").unwrap();
+    });
+
+    let mut echoed = [0; 5];
+    read_half.read_exact(&mut echoed).unwrap();
+    assert_eq!(&echoed, b"hello");
+}
@@ -1,8 +1,10 @@
 use std::time::Duration;
 
-use lunatic::ap::handlers::Message;
-use lunatic::ap::{AbstractProcess, Config, MessageHandler, ProcessRef, State};
+use lunatic::ap::handlers::{Message, Request};
+use lunatic::ap::{AbstractProcess, Config, MessageHandler, ProcessRef, RequestHandler, State};
 use lunatic::serializer::Bincode;
+use lunatic::time::{interval, DeadlineError, Tick};
+use lunatic::Mailbox;
 use lunatic_test::test;
 
 struct P;
@@ -51,3 +53,126 @@ fn cancel_send_after() {
     // give enough time for the message to be sent if it wasn't canceled
     lunatic::sleep(Duration::from_millis(25));
 }
+
+#[test]
+fn cancelled_message_never_arrives(mailbox: Mailbox<u32>) {
+    let timer_ref = mailbox.this().send_after(1, Duration::from_millis(100));
+    lunatic::sleep(Duration::from_millis(10));
+    assert!(timer_ref.cancel());
+    assert!(mailbox.receive_timeout(Duration::from_millis(150)).is_err());
+}
+
+#[test]
+fn cancel_all_timers() {
+    let process = P::link().start(()).unwrap();
+    process.with_delay(Duration::from_millis(10)).send(());
+    process.with_delay(Duration::from_millis(15)).send(());
+    assert_eq!(lunatic::time::pending_count(), 2);
+
+    assert_eq!(lunatic::time::cancel_all(), 2);
+    assert_eq!(lunatic::time::pending_count(), 0);
+
+    // give enough time for the messages to be sent if they weren't canceled
+    lunatic::sleep(Duration::from_millis(25));
+}
+
+#[test]
+fn with_deadline() {
+    let result = lunatic::time::with_deadline(Duration::from_millis(100), 21, |n| n * 2);
+    assert_eq!(result, Ok(42));
+
+    let result = lunatic::time::with_deadline(Duration::from_millis(10), (), |_| {
+        lunatic::sleep(Duration::from_millis(100));
+    });
+    assert_eq!(result, Err(DeadlineError::TimedOut));
+
+    // A panic is reported before the deadline expires.
+    let result =
+        lunatic::time::with_deadline::<_, u32>(Duration::from_secs(10), (), |_| panic!("failed"));
+    assert_eq!(result, Err(DeadlineError::Failed));
+}
+
+struct Ticker(u32);
+impl AbstractProcess for Ticker {
+    type Arg = ();
+    type State = Self;
+    type Serializer = Bincode;
+    type Handlers = (Message<()>, Request<()>);
+    type StartupError = ();
+
+    fn init(config: Config<Self>, _: ()) -> Result<Self, ()> {
+        config.send_interval((), Duration::from_millis(10));
+        Ok(Ticker(0))
+    }
+}
+impl MessageHandler<()> for Ticker {
+    fn handle(mut state: State<Self>, _: ()) {
+        state.0 += 1;
+    }
+}
+impl RequestHandler<()> for Ticker {
+    type Response = u32;
+
+    fn handle(state: State<Self>, _: ()) -> u32 {
+        state.0
+    }
+}
+
+#[test]
+fn send_interval() {
+    let ticker = Ticker::link().start(()).unwrap();
+    lunatic::sleep(Duration::from_millis(55));
+    assert!(ticker.request(()) >= 5);
+}
+
+#[test]
+fn interval_ticks(mailbox: Mailbox<Tick>) {
+    let ticks = interval(Duration::from_millis(10));
+    for _ in 0..3 {
+        assert_eq!(
+            mailbox.receive_timeout(Duration::from_millis(50)).unwrap(),
+            Tick
+        );
+    }
+    ticks.cancel();
+
+    // Drop the ticks that were sent before the interval got canceled.
+    lunatic::sleep(Duration::from_millis(15));
+    while mailbox.receive_timeout(Duration::ZERO).is_ok() {}
+    assert!(mailbox.receive_timeout(Duration::from_millis(30)).is_err());
+}
+
+struct Counter(u32);
+impl AbstractProcess for Counter {
+    type Arg = ();
+    type State = Self;
+    type Serializer = Bincode;
+    type Handlers = (Message<u32>, Request<()>);
+    type StartupError = ();
+
+    fn init(_: Config<Self>, _: ()) -> Result<Self, ()> {
+        Ok(Counter(0))
+    }
+}
+impl MessageHandler<u32> for Counter {
+    fn handle(mut state: State<Self>, increment: u32) {
+        state.0 += increment;
+    }
+}
+impl RequestHandler<()> for Counter {
+    type Response = u32;
+
+    fn handle(state: State<Self>, _: ()) -> u32 {
+        state.0
+    }
+}
+
+#[test]
+fn process_ref_send_after() {
+    let counter = Counter::link().start(()).unwrap();
+    counter.send_after(1, Duration::from_millis(20));
+    assert_eq!(counter.request(()), 0);
+
+    lunatic::sleep(Duration::from_millis(30));
+    assert_eq!(counter.request(()), 1);
+}
@@ -1,7 +1,7 @@
 use std::io::ErrorKind;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
-use lunatic::net;
+use lunatic::{net, spawn_link, Mailbox};
 use lunatic_test::test;
 
 #[test]
@@ -109,13 +109,26 @@ fn udp_ttl_setter_getter() {
     assert_eq!(cur_ttl, 42);
 }
 
+#[test]
+fn udp_ttl_range() {
+    let socket = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_ttl(5).unwrap();
+    assert_eq!(socket.ttl().unwrap(), 5);
+
+    let err = socket.set_ttl(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = socket.set_ttl(256).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(socket.ttl().unwrap(), 5);
+}
+
 #[test]
 fn udp_broadcast_setter_getter_true() {
     let sender = net::UdpSocket::bind("127.0.0.1:0").unwrap();
     sender.set_broadcast(true).unwrap();
     let cur_broadcast = sender.broadcast().unwrap();
 
-    assert_eq!(cur_broadcast, true);
+    assert!(cur_broadcast);
 }
 
 #[test]
@@ -125,5 +138,41 @@ fn udp_broadcast_setter_getter_false() {
     sender.set_broadcast(false).unwrap();
     let cur_broadcast = sender.broadcast().unwrap();
 
-    assert_eq!(cur_broadcast, false);
+    assert!(!cur_broadcast);
+}
+
+#[test]
+fn udp_multicast_unsupported() {
+    let socket = net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    let group = Ipv4Addr::new(239, 255, 0, 1);
+    let err = socket
+        .join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+    let err = socket
+        .leave_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn udp_connected_round_trip(mailbox: Mailbox<SocketAddr>) {
+    let parent = mailbox.this();
+    spawn_link!(|parent| {
+        let echo = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        parent.send(echo.local_addr().unwrap());
+        let mut buf = [0; 4];
+        let (len, addr) = echo.recv_from(&mut buf).unwrap();
+        echo.send_to(&buf[..len], addr).unwrap();
+    });
+    let echo_addr = mailbox.receive();
+
+    let socket = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.connect(echo_addr).expect("couldn't connect");
+    assert_eq!(socket.peer_addr().unwrap(), echo_addr);
+    socket.send(b"ECHO").expect("couldn't send message");
+
+    let mut buf = [0; 4];
+    let len = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"ECHO");
 }